@@ -0,0 +1,877 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! The type system of the Algorand ABI (ARC-4), which defines how typed values
+//! are encoded into the byte strings passed as application call args.
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+use core::str::FromStr;
+
+use sha2::{Digest as _, Sha512_256};
+use thiserror::Error;
+
+use crate::types::Address;
+
+/// Maximum length of a dynamic array or string, and maximum offset within a tuple.
+const MAX_DYNAMIC_LEN: usize = u16::MAX as usize;
+
+/// Maximum length of a static array, bounded like that of dynamic ones.
+const MAX_STATIC_LEN: usize = MAX_DYNAMIC_LEN;
+
+/// Prefix of the log entry carrying a method's return value, the hash of `return`.
+pub const RETURN_PREFIX: [u8; 4] = [0x15, 0x1f, 0x7c, 0x75];
+
+/// Number of method args that are passed in separate application args if there are
+/// more than 15, in which case the remaining ones are passed together in a tuple.
+const MAX_SEPARATE_ARGS: usize = 14;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AbiError {
+    #[error("invalid ABI type: {0}")]
+    InvalidType(String),
+    #[error("value does not match ABI type {0}")]
+    TypeMismatch(AbiType),
+    #[error("value does not fit into ABI type {0}")]
+    ValueTooLarge(AbiType),
+    #[error("invalid encoding of ABI type {0}")]
+    InvalidEncoding(AbiType),
+    #[error("invalid method signature: {0}")]
+    InvalidSignature(String),
+    #[error("method takes {expected} args, but got {got}")]
+    WrongArgCount { expected: usize, got: usize },
+    #[error("no return value logged")]
+    MissingReturn,
+}
+
+/// A method of an application, as identified by its signature, e.g. `add(uint64,uint64)uint128`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Method {
+    pub name: String,
+    pub args: Vec<AbiType>,
+    /// Type of the return value, or `None` for `void`.
+    pub returns: Option<AbiType>,
+}
+
+/// A type of the ABI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbiType {
+    /// An unsigned integer of the given number of bits,
+    /// which is a multiple of 8 between 8 and 512.
+    Uint(u16),
+    Byte,
+    Bool,
+    Address,
+    /// A UTF-8 string, encoded like `byte[]`.
+    String,
+    StaticArray(Box<AbiType>, usize),
+    DynamicArray(Box<AbiType>),
+    Tuple(Vec<AbiType>),
+}
+
+/// A value of an ABI type.
+///
+/// Integers are limited to 128 bits, even for wider types.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbiValue {
+    Uint(u128),
+    Byte(u8),
+    Bool(bool),
+    Address(Address),
+    String(String),
+    /// Elements of a static or dynamic array.
+    Array(Vec<AbiValue>),
+    Tuple(Vec<AbiValue>),
+}
+
+impl AbiType {
+    /// Checks whether the encoding of values of this type has a variable length.
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            AbiType::String | AbiType::DynamicArray(_) => true,
+            AbiType::StaticArray(elem, _) => elem.is_dynamic(),
+            AbiType::Tuple(elems) => elems.iter().any(AbiType::is_dynamic),
+            _ => false,
+        }
+    }
+
+    /// Encodes the value into the ABI's byte layout for this type.
+    pub fn encode(&self, value: &AbiValue) -> Result<Vec<u8>, AbiError> {
+        match (self, value) {
+            (AbiType::StaticArray(_, len), _) if *len > MAX_STATIC_LEN => {
+                Err(AbiError::InvalidType(self.to_string()))
+            }
+            (AbiType::Uint(bits), AbiValue::Uint(n)) => {
+                let len = usize::from(*bits / 8);
+                if *bits < 128 && n >> bits != 0 {
+                    return Err(AbiError::ValueTooLarge(self.clone()));
+                }
+                let bytes = n.to_be_bytes();
+                let mut encoded = vec![0; len.saturating_sub(bytes.len())];
+                encoded.extend_from_slice(&bytes[bytes.len().saturating_sub(len)..]);
+                Ok(encoded)
+            }
+            (AbiType::Byte, AbiValue::Byte(b)) => Ok(vec![*b]),
+            (AbiType::Bool, AbiValue::Bool(b)) => Ok(vec![if *b { 0x80 } else { 0 }]),
+            (AbiType::Address, AbiValue::Address(addr)) => Ok(addr.0.to_vec()),
+            (AbiType::String, AbiValue::String(s)) => {
+                with_len_prefix(s.len(), s.as_bytes().to_vec(), self)
+            }
+            (AbiType::StaticArray(elem, len), AbiValue::Array(elems)) if elems.len() == *len => {
+                encode_tuple(&vec![(**elem).clone(); *len], elems).map_err(|e| in_context(e, self))
+            }
+            (AbiType::DynamicArray(elem), AbiValue::Array(elems)) => {
+                let encoded = encode_tuple(&vec![(**elem).clone(); elems.len()], elems)
+                    .map_err(|e| in_context(e, self))?;
+                with_len_prefix(elems.len(), encoded, self)
+            }
+            (AbiType::Tuple(types), AbiValue::Tuple(elems)) if elems.len() == types.len() => {
+                encode_tuple(types, elems)
+            }
+            _ => Err(AbiError::TypeMismatch(self.clone())),
+        }
+    }
+
+    /// Decodes a value of this type from its ABI encoding, which must span all of `bytes`.
+    pub fn decode(&self, bytes: &[u8]) -> Result<AbiValue, AbiError> {
+        let invalid = || AbiError::InvalidEncoding(self.clone());
+        match self {
+            AbiType::StaticArray(_, len) if *len > MAX_STATIC_LEN => {
+                Err(AbiError::InvalidType(self.to_string()))
+            }
+            AbiType::Uint(bits) => {
+                if bytes.len() != usize::from(*bits / 8) {
+                    return Err(invalid());
+                }
+                let (high, low) = bytes.split_at(bytes.len().saturating_sub(16));
+                if high.iter().any(|&b| b != 0) {
+                    return Err(AbiError::ValueTooLarge(self.clone()));
+                }
+                Ok(AbiValue::Uint(
+                    low.iter().fold(0, |n, &b| n << 8 | u128::from(b)),
+                ))
+            }
+            AbiType::Byte => match bytes {
+                [b] => Ok(AbiValue::Byte(*b)),
+                _ => Err(invalid()),
+            },
+            AbiType::Bool => match bytes {
+                [0x80] => Ok(AbiValue::Bool(true)),
+                [0] => Ok(AbiValue::Bool(false)),
+                _ => Err(invalid()),
+            },
+            AbiType::Address => bytes
+                .try_into()
+                .map(|addr| AbiValue::Address(Address(addr)))
+                .map_err(|_| invalid()),
+            AbiType::String => {
+                let (len, content) = split_len_prefix(bytes).ok_or_else(invalid)?;
+                if content.len() != len {
+                    return Err(invalid());
+                }
+                String::from_utf8(content.to_vec())
+                    .map(AbiValue::String)
+                    .map_err(|_| invalid())
+            }
+            AbiType::StaticArray(elem, len) => decode_tuple(&vec![(**elem).clone(); *len], bytes)
+                .map(AbiValue::Array)
+                .map_err(|e| in_context(e, self)),
+            AbiType::DynamicArray(elem) => {
+                let (len, content) = split_len_prefix(bytes).ok_or_else(invalid)?;
+                decode_tuple(&vec![(**elem).clone(); len], content)
+                    .map(AbiValue::Array)
+                    .map_err(|e| in_context(e, self))
+            }
+            AbiType::Tuple(types) => decode_tuple(types, bytes)
+                .map(AbiValue::Tuple)
+                .map_err(|e| in_context(e, self)),
+        }
+    }
+
+    /// Returns the length of the encoding of values of this static type,
+    /// or `None` if it overflows.
+    fn static_len(&self) -> Option<usize> {
+        match self {
+            AbiType::Uint(bits) => Some(usize::from(*bits / 8)),
+            AbiType::Byte | AbiType::Bool => Some(1),
+            AbiType::Address => Some(32),
+            AbiType::StaticArray(elem, len) if **elem == AbiType::Bool => Some(len.div_ceil(8)),
+            AbiType::StaticArray(elem, len) => len.checked_mul(elem.static_len()?),
+            AbiType::Tuple(types) => {
+                let mut len = 0usize;
+                let mut i = 0;
+                while i < types.len() {
+                    if types[i] == AbiType::Bool {
+                        i += bool_run_len(&types[i..]);
+                        len = len.checked_add(1)?;
+                    } else {
+                        len = len.checked_add(types[i].static_len()?)?;
+                        i += 1;
+                    }
+                }
+                Some(len)
+            }
+            AbiType::String | AbiType::DynamicArray(_) => unreachable!("type is dynamic"),
+        }
+    }
+}
+
+/// Encodes the elements of a tuple: first the heads of all elements, which are the encodings
+/// of static elements and offsets to the encodings of dynamic elements, then the latter.
+/// Consecutive bools are packed into the bits of a single byte, starting with the highest.
+fn encode_tuple(types: &[AbiType], values: &[AbiValue]) -> Result<Vec<u8>, AbiError> {
+    let mut heads = Vec::new();
+    let mut tails = Vec::new();
+    let mut i = 0;
+    while i < types.len() {
+        if types[i] == AbiType::Bool {
+            let run = bool_run_len(&types[i..]);
+            let mut packed = 0;
+            for (j, value) in values[i..i + run].iter().enumerate() {
+                match value {
+                    AbiValue::Bool(true) => packed |= 0x80 >> j,
+                    AbiValue::Bool(false) => {}
+                    _ => return Err(AbiError::TypeMismatch(AbiType::Bool)),
+                }
+            }
+            heads.push(vec![packed]);
+            tails.push(None);
+            i += run;
+        } else if types[i].is_dynamic() {
+            heads.push(vec![0, 0]);
+            tails.push(Some(types[i].encode(&values[i])?));
+            i += 1;
+        } else {
+            heads.push(types[i].encode(&values[i])?);
+            tails.push(None);
+            i += 1;
+        }
+    }
+
+    let mut offset: usize = heads.iter().map(Vec::len).sum();
+    for (head, tail) in heads.iter_mut().zip(&tails) {
+        if let Some(tail) = tail {
+            let offset_u16 = u16::try_from(offset)
+                .map_err(|_| AbiError::ValueTooLarge(AbiType::Tuple(types.to_vec())))?;
+            *head = offset_u16.to_be_bytes().to_vec();
+            offset += tail.len();
+        }
+    }
+    let tails = tails.into_iter().flatten();
+    Ok(heads.into_iter().chain(tails).flatten().collect())
+}
+
+/// Decodes the elements of a tuple, the inverse of `encode_tuple`.
+fn decode_tuple(types: &[AbiType], bytes: &[u8]) -> Result<Vec<AbiValue>, AbiError> {
+    let invalid = || AbiError::InvalidEncoding(AbiType::Tuple(types.to_vec()));
+    let mut values = Vec::with_capacity(types.len());
+    let mut dynamic = Vec::new();
+    let mut pos = 0;
+    let mut i = 0;
+    while i < types.len() {
+        if types[i] == AbiType::Bool {
+            let packed = *bytes.get(pos).ok_or_else(invalid)?;
+            let run = bool_run_len(&types[i..]);
+            values.extend((0..run).map(|j| Some(AbiValue::Bool(packed & (0x80 >> j) != 0))));
+            pos += 1;
+            i += run;
+        } else if types[i].is_dynamic() {
+            let offset = bytes.get(pos..pos + 2).ok_or_else(invalid)?;
+            dynamic.push((i, usize::from(u16::from_be_bytes([offset[0], offset[1]]))));
+            values.push(None);
+            pos += 2;
+            i += 1;
+        } else {
+            let len = types[i].static_len().ok_or_else(invalid)?;
+            let end = pos.checked_add(len).ok_or_else(invalid)?;
+            let encoded = bytes.get(pos..end).ok_or_else(invalid)?;
+            values.push(Some(types[i].decode(encoded)?));
+            pos += len;
+            i += 1;
+        }
+    }
+
+    // The encodings of dynamic elements follow the heads back to back, in order.
+    let ends = dynamic.iter().skip(1).map(|&(_, offset)| offset);
+    for (&(i, start), end) in dynamic.iter().zip(ends.chain([bytes.len()])) {
+        if start != pos || end < start || end > bytes.len() {
+            return Err(invalid());
+        }
+        values[i] = Some(types[i].decode(&bytes[start..end])?);
+        pos = end;
+    }
+    if pos != bytes.len() {
+        return Err(invalid());
+    }
+    Ok(values.into_iter().flatten().collect())
+}
+
+/// Returns the number of bools at the start of `types` that are packed into a single byte.
+fn bool_run_len(types: &[AbiType]) -> usize {
+    types
+        .iter()
+        .take(8)
+        .take_while(|t| **t == AbiType::Bool)
+        .count()
+}
+
+fn with_len_prefix(len: usize, encoded: Vec<u8>, t: &AbiType) -> Result<Vec<u8>, AbiError> {
+    if len > MAX_DYNAMIC_LEN {
+        return Err(AbiError::ValueTooLarge(t.clone()));
+    }
+    Ok([&(len as u16).to_be_bytes()[..], &encoded].concat())
+}
+
+fn split_len_prefix(bytes: &[u8]) -> Option<(usize, &[u8])> {
+    match bytes {
+        [high, low, rest @ ..] => Some((usize::from(u16::from_be_bytes([*high, *low])), rest)),
+        _ => None,
+    }
+}
+
+/// Attributes an error in the encoding of an element to the array or tuple as a whole.
+fn in_context(error: AbiError, t: &AbiType) -> AbiError {
+    match error {
+        AbiError::InvalidEncoding(_) => AbiError::InvalidEncoding(t.clone()),
+        other => other,
+    }
+}
+
+impl Method {
+    /// Parses a method signature, consisting of the name,
+    /// the tuple of argument types and the return type.
+    pub fn from_signature(signature: &str) -> Result<Method, AbiError> {
+        let invalid = || AbiError::InvalidSignature(signature.to_owned());
+        let (name, rest) = signature.split_once('(').ok_or_else(invalid)?;
+        if name.is_empty() {
+            return Err(invalid());
+        }
+
+        // The args end at the parenthesis that closes the one opening them.
+        let mut depth = 1usize;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })
+            .map(|(i, _)| i)
+            .ok_or_else(invalid)?;
+        let args = split_tuple(&rest[..end])
+            .ok_or_else(invalid)?
+            .into_iter()
+            .map(parse_type)
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+        let returns = match &rest[end + 1..] {
+            "void" => None,
+            returns => Some(parse_type(returns).ok_or_else(invalid)?),
+        };
+
+        Ok(Method {
+            name: name.to_owned(),
+            args,
+            returns,
+        })
+    }
+
+    /// Returns the canonical signature of the method.
+    pub fn signature(&self) -> String {
+        let args = AbiType::Tuple(self.args.clone());
+        match &self.returns {
+            Some(returns) => format!("{}{}{}", self.name, args, returns),
+            None => format!("{}{}void", self.name, args),
+        }
+    }
+
+    /// Computes the selector identifying the method in the first application arg,
+    /// which is the first 4 bytes of the SHA512/256 hash of the signature.
+    pub fn selector(&self) -> [u8; 4] {
+        let hash = Sha512_256::digest(self.signature().as_bytes());
+        [hash[0], hash[1], hash[2], hash[3]]
+    }
+
+    /// Encodes the application args for calling the method with `args`: the selector,
+    /// followed by the encoded args. If there are more than 15 args,
+    /// those past the 14th are encoded together in a tuple.
+    pub fn encode_args(&self, args: &[AbiValue]) -> Result<Vec<Vec<u8>>, AbiError> {
+        if args.len() != self.args.len() {
+            return Err(AbiError::WrongArgCount {
+                expected: self.args.len(),
+                got: args.len(),
+            });
+        }
+
+        let mut encoded = vec![self.selector().to_vec()];
+        if args.len() <= MAX_SEPARATE_ARGS + 1 {
+            for (t, arg) in self.args.iter().zip(args) {
+                encoded.push(t.encode(arg)?);
+            }
+        } else {
+            let (types, rest_types) = self.args.split_at(MAX_SEPARATE_ARGS);
+            let (values, rest_values) = args.split_at(MAX_SEPARATE_ARGS);
+            for (t, arg) in types.iter().zip(values) {
+                encoded.push(t.encode(arg)?);
+            }
+            encoded.push(encode_tuple(rest_types, rest_values)?);
+        }
+        Ok(encoded)
+    }
+
+    /// Decodes the return value from the logs of the method's application call,
+    /// where it is the last entry, prefixed with `RETURN_PREFIX`.
+    ///
+    /// Returns `None` for methods returning `void`.
+    pub fn decode_return(&self, logs: &[Vec<u8>]) -> Result<Option<AbiValue>, AbiError> {
+        let returns = match &self.returns {
+            Some(returns) => returns,
+            None => return Ok(None),
+        };
+        let encoded = logs
+            .last()
+            .and_then(|log| log.strip_prefix(&RETURN_PREFIX[..]))
+            .ok_or(AbiError::MissingReturn)?;
+        returns.decode(encoded).map(Some)
+    }
+}
+
+impl FromStr for Method {
+    type Err = AbiError;
+
+    fn from_str(s: &str) -> Result<Method, AbiError> {
+        Method::from_signature(s)
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.signature())
+    }
+}
+
+impl FromStr for AbiType {
+    type Err = AbiError;
+
+    /// Parses a type as written in method signatures, e.g. `(uint64,address)[]`.
+    fn from_str(s: &str) -> Result<AbiType, AbiError> {
+        parse_type(s).ok_or_else(|| AbiError::InvalidType(s.to_owned()))
+    }
+}
+
+fn parse_type(s: &str) -> Option<AbiType> {
+    if let Some(prefix) = s.strip_suffix(']') {
+        let (elem, len) = prefix.rsplit_once('[')?;
+        let elem = Box::new(parse_type(elem)?);
+        return if len.is_empty() {
+            Some(AbiType::DynamicArray(elem))
+        } else if len.bytes().all(|b| b.is_ascii_digit()) {
+            let len = len.parse().ok().filter(|&len| len <= MAX_STATIC_LEN)?;
+            let array = AbiType::StaticArray(elem, len);
+            // Static types must have an encoding whose length can be computed.
+            (array.is_dynamic() || array.static_len().is_some()).then_some(array)
+        } else {
+            None
+        };
+    }
+    if let Some(inner) = s.strip_prefix('(') {
+        let inner = inner.strip_suffix(')')?;
+        return split_tuple(inner)?
+            .into_iter()
+            .map(parse_type)
+            .collect::<Option<_>>()
+            .map(AbiType::Tuple);
+    }
+    match s {
+        "byte" => Some(AbiType::Byte),
+        "bool" => Some(AbiType::Bool),
+        "address" => Some(AbiType::Address),
+        "string" => Some(AbiType::String),
+        _ => {
+            let digits = s.strip_prefix("uint")?;
+            let bits = digits.parse::<u16>().ok()?;
+            // Rejects leading zeros and signs, which the canonical form has not.
+            let canonical = bits.to_string() == digits;
+            (canonical && bits % 8 == 0 && (8..=512).contains(&bits)).then_some(AbiType::Uint(bits))
+        }
+    }
+}
+
+/// Splits the components of a tuple type at its top-level commas,
+/// returning `None` if the parentheses are unbalanced.
+fn split_tuple(inner: &str) -> Option<Vec<&str>> {
+    if inner.is_empty() {
+        return Some(Vec::new());
+    }
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                parts.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return None;
+    }
+    parts.push(&inner[start..]);
+    Some(parts)
+}
+
+impl fmt::Display for AbiType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AbiType::Uint(bits) => write!(f, "uint{}", bits),
+            AbiType::Byte => f.write_str("byte"),
+            AbiType::Bool => f.write_str("bool"),
+            AbiType::Address => f.write_str("address"),
+            AbiType::String => f.write_str("string"),
+            AbiType::StaticArray(elem, len) => write!(f, "{}[{}]", elem, len),
+            AbiType::DynamicArray(elem) => write!(f, "{}[]", elem),
+            AbiType::Tuple(elems) => {
+                f.write_str("(")?;
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", elem)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl From<u64> for AbiValue {
+    fn from(n: u64) -> Self {
+        AbiValue::Uint(n.into())
+    }
+}
+
+impl From<bool> for AbiValue {
+    fn from(b: bool) -> Self {
+        AbiValue::Bool(b)
+    }
+}
+
+impl From<Address> for AbiValue {
+    fn from(addr: Address) -> Self {
+        AbiValue::Address(addr)
+    }
+}
+
+impl From<&str> for AbiValue {
+    fn from(s: &str) -> Self {
+        AbiValue::String(s.to_owned())
+    }
+}
+
+/// Converts bytes into a value of type `byte[]` or `byte[N]`.
+impl From<&[u8]> for AbiValue {
+    fn from(bytes: &[u8]) -> Self {
+        AbiValue::Array(bytes.iter().map(|&b| AbiValue::Byte(b)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(s: &str) -> AbiType {
+        s.parse().unwrap()
+    }
+
+    fn round_trip(t: &AbiType, value: AbiValue, encoded: &[u8]) {
+        assert_eq!(t.encode(&value).unwrap(), encoded, "encoding {}", t);
+        assert_eq!(t.decode(encoded).unwrap(), value, "decoding {}", t);
+    }
+
+    #[test]
+    fn parse_types() {
+        for s in [
+            "uint8",
+            "uint512",
+            "byte",
+            "bool",
+            "address",
+            "string",
+            "byte[]",
+            "uint8[4]",
+            "(uint64,address)",
+            "()",
+            "(uint64,(address,bool[]),byte[4])[]",
+            "bool[2][][3]",
+            "byte[65535]",
+        ] {
+            assert_eq!(t(s).to_string(), s);
+        }
+        assert_eq!(
+            t("(uint64,byte[4])[]"),
+            AbiType::DynamicArray(Box::new(AbiType::Tuple(vec![
+                AbiType::Uint(64),
+                AbiType::StaticArray(Box::new(AbiType::Byte), 4)
+            ])))
+        );
+
+        for s in [
+            "",
+            "uint",
+            "uint0",
+            "uint7",
+            "uint520",
+            "uint064",
+            "int64",
+            "(uint64",
+            "uint64)",
+            "(uint64,)",
+            "uint64[",
+            "uint64[x]",
+            "uint64[-1]",
+            "byte[65536]",
+            "byte[99999999999]",
+            "uint512[65535][65535][65535][65535]",
+            "((bool)",
+            "Bool",
+        ] {
+            assert_eq!(
+                s.parse::<AbiType>(),
+                Err(AbiError::InvalidType(s.to_owned())),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn uints() {
+        round_trip(&t("uint8"), AbiValue::Uint(255), &[0xff]);
+        round_trip(&t("uint64"), 1.into(), &[0, 0, 0, 0, 0, 0, 0, 1]);
+        round_trip(&t("uint128"), AbiValue::Uint(u128::MAX), &[0xff; 16]);
+        let mut wide = [0; 64];
+        wide[63] = 7;
+        round_trip(&t("uint512"), AbiValue::Uint(7), &wide);
+
+        assert_eq!(
+            t("uint8").encode(&AbiValue::Uint(256)),
+            Err(AbiError::ValueTooLarge(AbiType::Uint(8)))
+        );
+        wide[0] = 1;
+        assert_eq!(
+            t("uint512").decode(&wide),
+            Err(AbiError::ValueTooLarge(AbiType::Uint(512)))
+        );
+        assert_eq!(
+            t("uint64").decode(&[0; 7]),
+            Err(AbiError::InvalidEncoding(AbiType::Uint(64)))
+        );
+    }
+
+    #[test]
+    fn simple_types() {
+        round_trip(&t("bool"), true.into(), &[0x80]);
+        round_trip(&t("bool"), false.into(), &[0]);
+        round_trip(&t("byte"), AbiValue::Byte(7), &[7]);
+        round_trip(&t("address"), Address([9; 32]).into(), &[9; 32]);
+        round_trip(&t("string"), "asdf".into(), b"\x00\x04asdf");
+        round_trip(&t("byte[]"), b"ab"[..].into(), b"\x00\x02ab");
+        round_trip(&t("byte[2]"), b"ab"[..].into(), b"ab");
+
+        assert_eq!(
+            t("bool").decode(&[1]),
+            Err(AbiError::InvalidEncoding(AbiType::Bool))
+        );
+        assert_eq!(
+            t("string").decode(b"\x00\x05asdf"),
+            Err(AbiError::InvalidEncoding(AbiType::String))
+        );
+        assert_eq!(
+            t("address").encode(&"asdf".into()),
+            Err(AbiError::TypeMismatch(AbiType::Address))
+        );
+    }
+
+    #[test]
+    fn packed_bools() {
+        let bools = |bs: &[bool]| AbiValue::Array(bs.iter().map(|&b| b.into()).collect());
+        round_trip(&t("bool[3]"), bools(&[true, true, false]), &[0xc0]);
+        round_trip(&t("bool[]"), bools(&[true, false, true]), &[0, 3, 0xa0]);
+        round_trip(&t("bool[10]"), bools(&[true; 10]), &[0xff, 0xc0]);
+        round_trip(
+            &t("(bool,bool,uint8,bool)"),
+            AbiValue::Tuple(vec![
+                true.into(),
+                true.into(),
+                AbiValue::Uint(1),
+                true.into(),
+            ]),
+            &[0xc0, 1, 0x80],
+        );
+    }
+
+    #[test]
+    fn tuples() {
+        // Heads: offset of the first string, the bool, offset of the second string.
+        round_trip(
+            &t("(string,bool,string)"),
+            AbiValue::Tuple(vec!["AB".into(), true.into(), "C".into()]),
+            &[0, 5, 0x80, 0, 9, 0, 2, b'A', b'B', 0, 1, b'C'],
+        );
+        round_trip(
+            &t("(uint16,(byte,string))"),
+            AbiValue::Tuple(vec![
+                AbiValue::Uint(3),
+                AbiValue::Tuple(vec![AbiValue::Byte(4), "x".into()]),
+            ]),
+            &[0, 3, 0, 4, 4, 0, 3, 0, 1, b'x'],
+        );
+        round_trip(
+            &t("uint16[][2]"),
+            AbiValue::Array(vec![
+                AbiValue::Array(vec![AbiValue::Uint(1)]),
+                AbiValue::Array(vec![]),
+            ]),
+            &[0, 4, 0, 8, 0, 1, 0, 1, 0, 0],
+        );
+        round_trip(&t("()"), AbiValue::Tuple(vec![]), &[]);
+
+        let tuple = t("(string,bool,string)");
+        for invalid in [
+            &[0, 5, 0x80, 0, 9, 0, 2, b'A', b'B', 0, 1, b'C', 0][..],
+            &[0, 6, 0x80, 0, 9, 0, 2, b'A', b'B', 0, 1, b'C'],
+            &[0, 5, 0x80, 0, 4, 0, 2, b'A', b'B', 0, 1, b'C'],
+            &[0, 5, 0x80],
+        ] {
+            assert_eq!(
+                tuple.decode(invalid),
+                Err(AbiError::InvalidEncoding(tuple.clone())),
+                "{:?}",
+                invalid
+            );
+        }
+
+        assert_eq!(
+            t("(uint8,bool)").encode(&AbiValue::Tuple(vec![AbiValue::Uint(1)])),
+            Err(AbiError::TypeMismatch(t("(uint8,bool)")))
+        );
+        assert_eq!(
+            t("uint8[2]").encode(&AbiValue::Array(vec![AbiValue::Uint(1)])),
+            Err(AbiError::TypeMismatch(t("uint8[2]")))
+        );
+    }
+
+    #[test]
+    fn oversized_static_array() {
+        // types built by hand are not bounded by parsing
+        let huge = AbiType::StaticArray(Box::new(AbiType::Byte), usize::MAX);
+        let invalid = AbiError::InvalidType(huge.to_string());
+        assert_eq!(
+            huge.encode(&AbiValue::Array(Vec::new())),
+            Err(invalid.clone())
+        );
+        assert_eq!(huge.decode(&[0; 4]), Err(invalid));
+
+        let nested = AbiType::Tuple(vec![t("uint512[65535][65535][65535]"); 1 << 12]);
+        assert!(nested.decode(&[0; 4]).is_err());
+    }
+
+    #[test]
+    fn method_signature() {
+        let method = Method::from_signature("add(uint64,uint64)uint128").unwrap();
+        assert_eq!(method.name, "add");
+        assert_eq!(method.args, [AbiType::Uint(64), AbiType::Uint(64)]);
+        assert_eq!(method.returns, Some(AbiType::Uint(128)));
+        assert_eq!(method.selector(), [0x8a, 0xa3, 0xb6, 0x1f]);
+
+        for s in [
+            "add(uint64,uint64)uint128",
+            "noop()void",
+            "f((uint64,bool[]),string)(address,byte[4])",
+        ] {
+            assert_eq!(s.parse::<Method>().unwrap().to_string(), s);
+        }
+        assert_eq!(Method::from_signature("noop()void").unwrap().returns, None);
+
+        for s in [
+            "add",
+            "(uint64)void",
+            "add(uint64",
+            "add(uint64)",
+            "add(uint7)void",
+        ] {
+            assert_eq!(
+                Method::from_signature(s),
+                Err(AbiError::InvalidSignature(s.to_owned())),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn method_args() {
+        let method = Method::from_signature("greet(string,uint8)void").unwrap();
+        let args = method.encode_args(&["hi".into(), 3.into()]).unwrap();
+        assert_eq!(
+            args,
+            [method.selector().to_vec(), b"\x00\x02hi".to_vec(), vec![3]]
+        );
+
+        assert_eq!(
+            method.encode_args(&["hi".into()]),
+            Err(AbiError::WrongArgCount {
+                expected: 2,
+                got: 1
+            })
+        );
+        assert_eq!(
+            method.encode_args(&["hi".into(), true.into()]),
+            Err(AbiError::TypeMismatch(AbiType::Uint(8)))
+        );
+
+        // The 15th and 16th arg are passed together in the last application arg.
+        let signature = format!("many({})void", ["uint8"; 16].join(","));
+        let method = Method::from_signature(&signature).unwrap();
+        let values: Vec<AbiValue> = (0..16).map(AbiValue::from).collect();
+        let args = method.encode_args(&values).unwrap();
+        assert_eq!(args.len(), 16);
+        assert_eq!(args[14], [13]);
+        assert_eq!(args[15], [14, 15]);
+    }
+
+    #[test]
+    fn method_return() {
+        let method = Method::from_signature("add(uint64,uint64)uint64").unwrap();
+        let logs = vec![
+            b"unrelated".to_vec(),
+            [&RETURN_PREFIX[..], &[0, 0, 0, 0, 0, 0, 0, 5]].concat(),
+        ];
+        assert_eq!(method.decode_return(&logs), Ok(Some(AbiValue::Uint(5))));
+
+        assert_eq!(
+            method.decode_return(&logs[..1]),
+            Err(AbiError::MissingReturn)
+        );
+        assert_eq!(method.decode_return(&[]), Err(AbiError::MissingReturn));
+        assert_eq!(
+            method.decode_return(&[[&RETURN_PREFIX[..], &[5]].concat()]),
+            Err(AbiError::InvalidEncoding(AbiType::Uint(64)))
+        );
+
+        let void = Method::from_signature("noop()void").unwrap();
+        assert_eq!(void.decode_return(&[]), Ok(None));
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Algorand accounts backed by a single ed25519 key pair.
+
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer as _};
+#[cfg(all(
+    feature = "std",
+    any(
+        feature = "wasm",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    )
+))]
+use rand_core::OsRng;
+use sha2::{Digest as _, Sha512_256};
+
+use crate::signer::{signed_tx, SignError, Signer};
+use crate::types::{Address, MasterDerivationKey, Signature, SignedTx, Transaction};
+
+/// An account that can sign transactions with its own ed25519 key.
+pub struct Account {
+    keypair: Keypair,
+}
+
+impl Account {
+    /// Generates a new account from a random key pair, drawn from the operating system's CSPRNG.
+    ///
+    /// On `wasm32-unknown-unknown` this requires the `wasm` feature, which uses the browser's CSPRNG.
+    #[cfg(all(
+        feature = "std",
+        any(
+            feature = "wasm",
+            not(all(target_arch = "wasm32", target_os = "unknown"))
+        )
+    ))]
+    pub fn generate() -> Account {
+        Account {
+            keypair: Keypair::generate(&mut OsRng),
+        }
+    }
+
+    /// Restores an account from its 32 byte ed25519 private key seed.
+    pub fn from_seed(seed: &[u8; 32]) -> Account {
+        let secret = SecretKey::from_bytes(seed).expect("seed has correct length");
+        let public = PublicKey::from(&secret);
+        Account {
+            keypair: Keypair { secret, public },
+        }
+    }
+
+    /// Returns the address of this account.
+    pub fn address(&self) -> Address {
+        Address::from(self.keypair.public)
+    }
+
+    /// Returns the public key of this account.
+    pub fn public_key(&self) -> PublicKey {
+        self.keypair.public
+    }
+
+    /// Returns the key pair of this account.
+    pub fn keypair(&self) -> &Keypair {
+        &self.keypair
+    }
+
+    /// Signs a transaction with this account's key.
+    ///
+    /// If the transaction's sender is a different (rekeyed) account,
+    /// this account is recorded as the authorizing address.
+    pub fn sign_transaction(&self, tx: &Transaction) -> SignedTx {
+        let sig = Signature(self.keypair.sign(&tx.bytes_to_sign()));
+        signed_tx(tx, self.address(), sig, None, None)
+    }
+}
+
+/// Derives the account at `index` from a master derivation key.
+///
+/// The key's seed is the SHA512/256 hash of the master derivation key followed by the
+/// big-endian index. This is modeled on kmd's `extractKeyWithIndex`, but it hasn't been
+/// checked against keys exported from kmd, so don't rely on it to recover a kmd wallet.
+pub fn derive_account(mdk: &MasterDerivationKey, index: u64) -> Account {
+    let mut hasher = Sha512_256::new();
+    hasher.update(mdk.0);
+    hasher.update(index.to_be_bytes());
+    Account::from_seed(&hasher.finalize().into())
+}
+
+impl Signer for Account {
+    fn sign_transaction(&self, tx: &Transaction) -> Result<SignedTx, SignError> {
+        Ok(Account::sign_transaction(self, tx))
+    }
+
+    fn address(&self) -> Address {
+        Account::address(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(
+        feature = "wasm",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    #[test]
+    fn generate() {
+        let account = Account::generate();
+        assert_eq!(account.address(), Address::from(account.public_key()));
+        assert_ne!(account.address(), Account::generate().address());
+
+        let restored = Account::from_seed(account.keypair().secret.as_bytes());
+        assert_eq!(restored.address(), account.address());
+    }
+
+    #[test]
+    fn derive_from_mdk() {
+        let mdk = MasterDerivationKey([7; 32]);
+        let first = derive_account(&mdk, 0);
+        let second = derive_account(&mdk, 1);
+        assert_ne!(first.address(), second.address());
+        assert_eq!(derive_account(&mdk, 1).address(), second.address());
+        assert_ne!(
+            derive_account(&MasterDerivationKey([8; 32]), 0).address(),
+            first.address()
+        );
+
+        let mut input = [0; 40];
+        input[..32].copy_from_slice(&mdk.0);
+        input[39] = 1;
+        let seed: [u8; 32] = Sha512_256::digest(input).into();
+        assert_eq!(second.address(), Account::from_seed(&seed).address());
+    }
+}
@@ -0,0 +1,1023 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Client for the v2 REST API of an algod node.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::dryrun::{DryrunRequest, DryrunResponse, RawDryrunResponse};
+#[cfg(feature = "reqwest")]
+use super::transport::ReqwestTransport;
+use super::transport::{HttpRequest, HttpTransport};
+use super::{
+    decode_base64, eval_delta, execute, execute_msgpack, send, ClientError, RawAccount,
+    RawAccountStateDelta, RawApp, RawAssetParams, RawKeyValue, API_TOKEN_HEADER, CONTENT_TYPE,
+};
+use crate::encoding::{byte_vecs, digest, DecodeError};
+use crate::types::{
+    AccountData, Address, AppIndex, AppParams, ApplyData, AssetIndex, AssetParams, Block, Digest,
+    EvalDelta, GroupError, MicroAlgos, Round, SignedTx, SignedTxWithAD, SuggestedParams,
+};
+
+/// Number of rounds for which transactions built from the suggested params are valid.
+const DEFAULT_VALIDITY_ROUNDS: u64 = 1000;
+
+/// Client for an algod node.
+pub struct AlgodClient<H> {
+    url: String,
+    token: String,
+    transport: H,
+}
+
+/// Response of `GET /v2/transactions/params`.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct TransactionParams {
+    consensus_version: String,
+    fee: MicroAlgos,
+    #[serde(with = "digest")]
+    genesis_hash: Digest,
+    genesis_id: String,
+    last_round: Round,
+    min_fee: MicroAlgos,
+}
+
+/// A transaction in the pool or recently confirmed, with the effects of its execution.
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "RawPendingTxn")]
+pub struct PendingTxnResponse {
+    /// The signed transaction.
+    pub txn: SignedTx,
+
+    /// Round in which the transaction was confirmed, if it was.
+    pub confirmed_round: Option<Round>,
+
+    /// Why the transaction was removed from the pool, if it was. Empty otherwise.
+    pub pool_error: String,
+
+    /// ID of the asset created by this transaction.
+    pub asset_index: Option<AssetIndex>,
+
+    /// ID of the application created by this transaction.
+    pub application_index: Option<AppIndex>,
+
+    pub closing_amount: MicroAlgos,
+    pub asset_closing_amount: u64,
+    pub sender_rewards: MicroAlgos,
+    pub receiver_rewards: MicroAlgos,
+    pub close_rewards: MicroAlgos,
+
+    /// Logs, state changes, and inner transactions of an application call.
+    pub eval_delta: EvalDelta,
+}
+
+/// Pending transaction as encoded by algod, with state deltas as lists of
+/// base64 encoded key-value pairs and inner transactions as nested responses.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawPendingTxn {
+    txn: SignedTx,
+    #[serde(default)]
+    confirmed_round: Option<Round>,
+    #[serde(default)]
+    pool_error: String,
+    #[serde(default)]
+    asset_index: Option<AssetIndex>,
+    #[serde(default)]
+    application_index: Option<AppIndex>,
+    #[serde(default)]
+    closing_amount: MicroAlgos,
+    #[serde(default)]
+    asset_closing_amount: u64,
+    #[serde(default)]
+    sender_rewards: MicroAlgos,
+    #[serde(default)]
+    receiver_rewards: MicroAlgos,
+    #[serde(default)]
+    close_rewards: MicroAlgos,
+    #[serde(default, with = "byte_vecs")]
+    logs: Vec<Vec<u8>>,
+    #[serde(default)]
+    global_state_delta: Vec<RawKeyValue>,
+    #[serde(default)]
+    local_state_delta: Vec<RawAccountStateDelta>,
+    #[serde(default)]
+    inner_txns: Vec<RawPendingTxn>,
+}
+
+impl TryFrom<RawPendingTxn> for PendingTxnResponse {
+    type Error = DecodeError;
+
+    fn try_from(raw: RawPendingTxn) -> Result<Self, DecodeError> {
+        let eval_delta = raw.eval_delta()?;
+        Ok(PendingTxnResponse {
+            txn: raw.txn,
+            confirmed_round: raw.confirmed_round,
+            pool_error: raw.pool_error,
+            asset_index: raw.asset_index,
+            application_index: raw.application_index,
+            closing_amount: raw.closing_amount,
+            asset_closing_amount: raw.asset_closing_amount,
+            sender_rewards: raw.sender_rewards,
+            receiver_rewards: raw.receiver_rewards,
+            close_rewards: raw.close_rewards,
+            eval_delta,
+        })
+    }
+}
+
+impl RawPendingTxn {
+    /// Converts the execution results into their representation in blocks.
+    fn eval_delta(&self) -> Result<EvalDelta, DecodeError> {
+        let inner_txs = self
+            .inner_txns
+            .iter()
+            .map(RawPendingTxn::signed_tx_with_ad)
+            .collect::<Result<_, _>>()?;
+        eval_delta(
+            &self.txn.tx,
+            &self.global_state_delta,
+            &self.local_state_delta,
+            self.logs.clone(),
+            inner_txs,
+        )
+    }
+
+    fn signed_tx_with_ad(&self) -> Result<SignedTxWithAD, DecodeError> {
+        Ok(SignedTxWithAD {
+            tx: self.txn.clone(),
+            ad: ApplyData {
+                closing_amount: self.closing_amount,
+                asset_closing_amount: self.asset_closing_amount,
+                sender_rewards: self.sender_rewards,
+                receiver_rewards: self.receiver_rewards,
+                close_rewards: self.close_rewards,
+                eval_delta: self.eval_delta()?,
+                config_asset: self.asset_index.unwrap_or_default(),
+                application_id: self.application_index.unwrap_or_default(),
+            },
+        })
+    }
+}
+
+/// Response of `GET /v2/status` and `GET /v2/status/wait-for-block-after/{round}`,
+/// reduced to the fields used by the client.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct NodeStatus {
+    last_round: Round,
+}
+
+/// A TEAL program compiled by algod.
+#[derive(Clone)]
+pub struct CompiledProgram {
+    /// The assembled program.
+    pub program: Vec<u8>,
+
+    /// Address of the contract account controlled by the program.
+    pub hash: Address,
+}
+
+/// Response of `POST /v2/teal/compile`.
+#[derive(Deserialize)]
+struct CompileResponse {
+    hash: Address,
+    result: String,
+}
+
+/// Extracts the encoded block from the response of `GET /v2/blocks/{round}`, which wraps it,
+/// so that `Block::from_msgpack` can check its header strictly.
+fn block_field(body: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let value = rmpv::decode::read_value(&mut &body[..])
+        .map_err(|e| DecodeError::InvalidMsgpack(e.to_string()))?;
+    let block = value
+        .as_map()
+        .and_then(|entries| {
+            entries
+                .iter()
+                .find(|(key, _)| key.as_str() == Some("block"))
+        })
+        .map(|(_, block)| block)
+        .ok_or_else(|| DecodeError::InvalidValue("response contains no block".to_owned()))?;
+    let mut bytes = Vec::new();
+    rmpv::encode::write_value(&mut bytes, block).expect("writing to a Vec never fails");
+    Ok(bytes)
+}
+
+/// Response of `GET /v2/assets/{asset-id}`, which wraps the asset parameters.
+#[derive(Deserialize)]
+struct AssetResponse {
+    params: RawAssetParams,
+}
+
+/// Response of `POST /v2/transactions`.
+#[derive(Deserialize)]
+struct PostTransactionsResponse {
+    #[serde(rename = "txId")]
+    tx_id: String,
+}
+
+#[cfg(feature = "reqwest")]
+impl AlgodClient<ReqwestTransport> {
+    /// Creates a client for the algod node at `url`, authenticating with the API `token`.
+    pub fn new(url: &str, token: &str) -> Self {
+        Self::with_transport(url, token, ReqwestTransport::default())
+    }
+}
+
+impl<H: HttpTransport> AlgodClient<H> {
+    /// Creates a client for the algod node at `url` that sends its requests over `transport`.
+    pub fn with_transport(url: &str, token: &str, transport: H) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_owned(),
+            token: token.to_owned(),
+            transport,
+        }
+    }
+
+    /// Fetches the parameters for building transactions that are valid from the latest round on.
+    pub async fn suggested_params(&self) -> Result<SuggestedParams, ClientError> {
+        let params: TransactionParams = self.get("/v2/transactions/params").await?;
+        Ok(SuggestedParams {
+            fee: params.fee,
+            genesis_id: params.genesis_id,
+            genesis_hash: params.genesis_hash,
+            first_round_valid: params.last_round,
+            last_round_valid: params.last_round.saturating_add(DEFAULT_VALIDITY_ROUNDS),
+            consensus_version: params.consensus_version,
+            flat_fee: false,
+            min_fee: params.min_fee,
+        })
+    }
+
+    /// Submits the signed transaction to the network, returning its transaction ID.
+    pub async fn send_raw_transaction(&self, stx: &SignedTx) -> Result<String, ClientError> {
+        let request = HttpRequest::post(format!("{}/v2/transactions", self.url))
+            .header(API_TOKEN_HEADER, &self.token)
+            .header(CONTENT_TYPE, "application/x-binary")
+            .body(stx.encode());
+        let response: PostTransactionsResponse = execute(&self.transport, request).await?;
+        Ok(response.tx_id)
+    }
+
+    /// Submits the signed transactions of an atomic group to the network,
+    /// returning the transaction ID of the first one.
+    ///
+    /// All transactions must share the same group ID, see `assign_group_ids`.
+    pub async fn send_transaction_group(&self, stxs: &[SignedTx]) -> Result<String, ClientError> {
+        let first = stxs.first().ok_or(GroupError::EmptyGroup)?;
+        if stxs.len() > 1
+            && (first.tx.header.group == Digest::default()
+                || stxs
+                    .iter()
+                    .any(|stx| stx.tx.header.group != first.tx.header.group))
+        {
+            return Err(GroupError::MixedGroups.into());
+        }
+
+        // algod expects the encodings back to back, not wrapped in an array.
+        let body: Vec<u8> = stxs.iter().flat_map(SignedTx::encode).collect();
+        let request = HttpRequest::post(format!("{}/v2/transactions", self.url))
+            .header(API_TOKEN_HEADER, &self.token)
+            .header(CONTENT_TYPE, "application/x-binary")
+            .body(body);
+        let response: PostTransactionsResponse = execute(&self.transport, request).await?;
+        Ok(response.tx_id)
+    }
+
+    /// Compiles TEAL source code into a program, e.g. for use as an application's approval program.
+    ///
+    /// Requires the node to have the developer API enabled.
+    pub async fn compile_teal(&self, source: &str) -> Result<CompiledProgram, ClientError> {
+        let request = HttpRequest::post(format!("{}/v2/teal/compile", self.url))
+            .header(API_TOKEN_HEADER, &self.token)
+            .header(CONTENT_TYPE, "text/plain")
+            .body(source.to_owned());
+        let response: CompileResponse = execute(&self.transport, request).await?;
+        Ok(CompiledProgram {
+            program: decode_base64(&response.result)?,
+            hash: response.hash,
+        })
+    }
+
+    /// Evaluates the programs of the transactions against the ledger state in the request,
+    /// returning their traces and effects without submitting the transactions.
+    ///
+    /// Requires the node to have the developer API enabled.
+    pub async fn dryrun(&self, request: &DryrunRequest) -> Result<DryrunResponse, ClientError> {
+        let http_request = HttpRequest::post(format!("{}/v2/teal/dryrun", self.url))
+            .header(API_TOKEN_HEADER, &self.token)
+            .header(CONTENT_TYPE, "application/msgpack")
+            .body(request.encode());
+        let response: RawDryrunResponse = execute(&self.transport, http_request).await?;
+        Ok(DryrunResponse::from_raw(response, request)?)
+    }
+
+    /// Waits until the transaction is confirmed, returning its execution results.
+    ///
+    /// Fails if the transaction is rejected by the pool,
+    /// or not confirmed within `max_rounds` rounds from the node's latest round.
+    pub async fn wait_for_confirmation(
+        &self,
+        tx_id: &str,
+        max_rounds: u64,
+    ) -> Result<PendingTxnResponse, ClientError> {
+        let status: NodeStatus = self.get("/v2/status").await?;
+        let last_round = status.last_round.saturating_add(max_rounds);
+        let mut round = status.last_round;
+        loop {
+            let pending = self.pending_transaction(tx_id).await?;
+            if pending.confirmed_round.is_some() {
+                return Ok(pending);
+            } else if !pending.pool_error.is_empty() {
+                return Err(ClientError::Rejected(pending.pool_error));
+            } else if round >= last_round {
+                return Err(ClientError::NotConfirmed(max_rounds));
+            }
+
+            let path = format!("/v2/status/wait-for-block-after/{}", round);
+            let status: NodeStatus = self.get(&path).await?;
+            round = status.last_round;
+        }
+    }
+
+    /// Fetches the transaction from the pool, or the recently confirmed transactions.
+    pub async fn pending_transaction(
+        &self,
+        tx_id: &str,
+    ) -> Result<PendingTxnResponse, ClientError> {
+        let request = HttpRequest::get(format!(
+            "{}/v2/transactions/pending/{}?format=msgpack",
+            self.url, tx_id
+        ))
+        .header(API_TOKEN_HEADER, &self.token);
+        execute_msgpack(&self.transport, request).await
+    }
+
+    /// Fetches the block of the given round, with all of its transactions.
+    pub async fn block(&self, round: Round) -> Result<Block, ClientError> {
+        let request = HttpRequest::get(format!("{}/v2/blocks/{}?format=msgpack", self.url, round))
+            .header(API_TOKEN_HEADER, &self.token);
+        let body = send(&self.transport, request).await?;
+        Ok(Block::from_msgpack(&block_field(&body)?)?)
+    }
+
+    /// Looks up the current balance, asset holdings, and application state of an account.
+    pub async fn account_information(&self, addr: &Address) -> Result<AccountData, ClientError> {
+        let raw: RawAccount = self.get(&format!("/v2/accounts/{}", addr)).await?;
+        Ok(raw.try_into()?)
+    }
+
+    /// Looks up the parameters of an asset.
+    pub async fn asset(&self, id: AssetIndex) -> Result<AssetParams, ClientError> {
+        let response: AssetResponse = self.get(&format!("/v2/assets/{}", id)).await?;
+        Ok(response.params.try_into()?)
+    }
+
+    /// Looks up the programs, state schemas, and global state of an application.
+    pub async fn application(&self, id: AppIndex) -> Result<AppParams, ClientError> {
+        let app: RawApp = self.get(&format!("/v2/applications/{}", id)).await?;
+        Ok(app.params.try_into()?)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ClientError> {
+        let request =
+            HttpRequest::get(format!("{}{}", self.url, path)).header(API_TOKEN_HEADER, &self.token);
+        execute(&self.transport, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use data_encoding::BASE64;
+    use rmpv::Value;
+
+    use crate::account::Account;
+    use crate::client::dryrun::DryrunApp;
+    use crate::client::mock;
+    use crate::client::transport::MockTransport;
+    use crate::encoding;
+    use crate::types::{
+        assign_group_ids, AccountStatus, AssetHolding, BlockHeader, DeltaAction, LogicSig, Payset,
+        SignedTxInBlock, StateSchema, Transaction,
+    };
+
+    fn signed_payment() -> SignedTx {
+        let account = Account::from_seed(&[1; 32]);
+        let params = SuggestedParams {
+            first_round_valid: Round(1),
+            last_round_valid: Round(1001),
+            min_fee: MicroAlgos(1000),
+            ..Default::default()
+        };
+        let tx = Transaction::payment(
+            account.address(),
+            Address([2; 32]),
+            MicroAlgos(5),
+            None,
+            &params,
+        )
+        .unwrap();
+        account.sign_transaction(&tx)
+    }
+
+    /// Encodes a pending transaction response for `stx` with the given additional fields.
+    fn pending(stx: &SignedTx, fields: Vec<(&str, Value)>) -> Vec<u8> {
+        let stx = encoding::to_msgpack(stx);
+        let mut entries = vec![(
+            Value::from("txn"),
+            rmpv::decode::read_value(&mut &stx[..]).unwrap(),
+        )];
+        entries.extend(fields.into_iter().map(|(k, v)| (Value::from(k), v)));
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &Value::Map(entries)).unwrap();
+        bytes
+    }
+
+    fn status(round: Round) -> (u16, Vec<u8>) {
+        (200, format!(r#"{{"last-round": {}}}"#, round).into())
+    }
+
+    #[tokio::test]
+    async fn suggested_params() {
+        let body = r#"{
+            "consensus-version": "https://github.com/algorandfoundation/specs/tree/abc",
+            "fee": 0,
+            "genesis-hash": "SGO1GKSzyE7IEPItTxCByw9x8FmnrCDexi9/cOUJOiI=",
+            "genesis-id": "testnet-v1.0",
+            "last-round": 21000000,
+            "min-fee": 1000
+        }"#;
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let params = client.suggested_params().await.unwrap();
+        assert_eq!(params.genesis_id, "testnet-v1.0");
+        assert_eq!(params.genesis_hash[..3], [0x48, 0x63, 0xb5]);
+        assert_eq!(params.first_round_valid, Round(21_000_000));
+        assert_eq!(params.last_round_valid, Round(21_001_000));
+        assert_eq!(params.fee, MicroAlgos(0));
+        assert_eq!(params.min_fee, MicroAlgos(1000));
+        assert!(!params.flat_fee);
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "GET /v2/transactions/params");
+        assert_eq!(requests[0].header_value(API_TOKEN_HEADER), Some("secret"));
+    }
+
+    #[tokio::test]
+    async fn suggested_params_at_last_round() {
+        let body = format!(
+            r#"{{
+            "consensus-version": "https://github.com/algorandfoundation/specs/tree/abc",
+            "fee": 0,
+            "genesis-hash": "SGO1GKSzyE7IEPItTxCByw9x8FmnrCDexi9/cOUJOiI=",
+            "genesis-id": "testnet-v1.0",
+            "last-round": {},
+            "min-fee": 1000
+        }}"#,
+            u64::MAX - 1
+        );
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport);
+        let params = client.suggested_params().await.unwrap();
+        assert_eq!(params.first_round_valid, Round(u64::MAX - 1));
+        assert_eq!(params.last_round_valid, Round(u64::MAX));
+    }
+
+    #[tokio::test]
+    async fn send_raw_transaction() {
+        let stx = signed_payment();
+        let body = format!(r#"{{"txId": "{}"}}"#, stx.tx.id());
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let tx_id = client.send_raw_transaction(&stx).await.unwrap();
+        assert_eq!(tx_id, stx.tx.id());
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "POST /v2/transactions");
+        assert_eq!(
+            requests[0].header_value("content-type"),
+            Some("application/x-binary")
+        );
+        assert_eq!(requests[0].body, stx.encode());
+    }
+
+    #[tokio::test]
+    async fn send_raw_transaction_rejected() {
+        let body = r#"{"message": "TransactionPool.Remember: transaction already in ledger"}"#;
+        let transport = MockTransport::new(vec![(400, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport);
+        let result = client.send_raw_transaction(&signed_payment()).await;
+        assert!(matches!(
+            result,
+            Err(ClientError::Api { status: 400, message })
+                if message == "TransactionPool.Remember: transaction already in ledger"
+        ));
+    }
+
+    #[tokio::test]
+    async fn send_transaction_group() {
+        let account = Account::from_seed(&[1; 32]);
+        let mut txs = vec![signed_payment().tx, signed_payment().tx];
+        txs[1].header.note = b"second".to_vec();
+        assign_group_ids(&mut txs).unwrap();
+        let stxs: Vec<_> = txs.iter().map(|tx| account.sign_transaction(tx)).collect();
+
+        let body = format!(r#"{{"txId": "{}"}}"#, txs[0].id());
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let tx_id = client.send_transaction_group(&stxs).await.unwrap();
+        assert_eq!(tx_id, txs[0].id());
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "POST /v2/transactions");
+        assert_eq!(
+            requests[0].body,
+            [stxs[0].encode(), stxs[1].encode()].concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn send_transaction_group_mixed() {
+        let client = AlgodClient::with_transport(mock::URL, "secret", MockTransport::default());
+        let result = client.send_transaction_group(&[]).await;
+        assert!(matches!(
+            result,
+            Err(ClientError::InvalidGroup(GroupError::EmptyGroup))
+        ));
+
+        let mut stxs = vec![signed_payment(), signed_payment()];
+        let result = client.send_transaction_group(&stxs).await;
+        assert!(matches!(
+            result,
+            Err(ClientError::InvalidGroup(GroupError::MixedGroups))
+        ));
+
+        stxs[0].tx.header.group = [1; 32];
+        stxs[1].tx.header.group = [2; 32];
+        let result = client.send_transaction_group(&stxs).await;
+        assert!(matches!(
+            result,
+            Err(ClientError::InvalidGroup(GroupError::MixedGroups))
+        ));
+    }
+
+    #[tokio::test]
+    async fn compile_teal() {
+        let body = r#"{
+            "hash": "6Z3C3LDVWGMX23BMSYMANACQOSINPFIRF77H7N3AWJZYV6OH6GWTJKVMXY",
+            "result": "ASABASI="
+        }"#;
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let source = "#pragma version 1\nint 1\n";
+        let compiled = client.compile_teal(source).await.unwrap();
+        assert_eq!(compiled.program, [0x01, 0x20, 0x01, 0x01, 0x22]);
+        let lsig = LogicSig {
+            logic: compiled.program,
+            sig: Default::default(),
+            msig: Default::default(),
+            args: Vec::new(),
+        };
+        assert_eq!(compiled.hash, lsig.address());
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "POST /v2/teal/compile");
+        assert_eq!(requests[0].header_value("content-type"), Some("text/plain"));
+        assert_eq!(requests[0].body, source.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn compile_teal_error() {
+        let body = r#"{"message": "1 error: 1: unknown opcode: nit"}"#;
+        let transport = MockTransport::new(vec![(400, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport);
+        let result = client.compile_teal("nit 1").await;
+        assert!(matches!(
+            result,
+            Err(ClientError::Api { status: 400, message }) if message.contains("unknown opcode")
+        ));
+    }
+
+    #[tokio::test]
+    async fn dryrun() {
+        let sender = Account::from_seed(&[1; 32]);
+        let other = Address([3; 32]);
+        let fields = crate::types::AppCallFields {
+            application_id: 5,
+            accounts: vec![other],
+            ..Default::default()
+        };
+        let params = SuggestedParams {
+            last_round_valid: Round(1000),
+            ..Default::default()
+        };
+        let call = Transaction::app_call(sender.address(), fields, &params).unwrap();
+        let stx = sender.sign_transaction(&call);
+
+        let mut request = DryrunRequest {
+            txns: vec![stx.clone()],
+            apps: vec![DryrunApp {
+                id: 5,
+                creator: other,
+                params: AppParams {
+                    approval_program: vec![0x05, 0x81, 0x01],
+                    clear_state_program: vec![0x05, 0x81, 0x01],
+                    ..Default::default()
+                },
+            }],
+            ..Default::default()
+        };
+        request.accounts.insert(
+            sender.address(),
+            AccountData {
+                micro_algos: MicroAlgos(1_000_000),
+                ..Default::default()
+            },
+        );
+
+        let body = r##"{
+            "error": "",
+            "protocol-version": "future",
+            "txns": [{
+                "disassembly": ["#pragma version 5", "intc_0 // 1", ""],
+                "app-call-messages": ["PASS"],
+                "app-call-trace": [
+                    {"line": 1, "pc": 1, "stack": [], "scratch": []},
+                    {"line": 2, "pc": 2, "stack": [{"type": 2, "bytes": "", "uint": 1}]}
+                ],
+                "global-delta": [
+                    {"key": "dG90YWw=", "value": {"action": 2, "uint": 13}}
+                ],
+                "local-deltas": [{
+                    "address": "AMBQGAYDAMBQGAYDAMBQGAYDAMBQGAYDAMBQGAYDAMBQGAYDAMB5DBBASI",
+                    "delta": [{"key": "bmFtZQ==", "value": {"action": 1, "bytes": "Ym9i"}}]
+                }],
+                "logs": ["aGVsbG8="],
+                "budget-consumed": 2,
+                "budget-added": 700
+            }]
+        }"##;
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let response = client.dryrun(&request).await.unwrap();
+        assert_eq!(response.error, "");
+        let result = &response.txns[0];
+        assert_eq!(result.app_call_messages, ["PASS"]);
+        assert_eq!(result.app_call_trace.len(), 2);
+        assert_eq!(result.app_call_trace[1].stack[0].uint, 1);
+        assert_eq!(result.budget_consumed, 2);
+        assert_eq!(result.eval_delta.logs, [b"hello"]);
+        assert_eq!(result.eval_delta.global_delta[&b"total"[..]].uint, 13);
+        assert_eq!(
+            result.eval_delta.local_deltas[&1][&b"name"[..]].bytes,
+            b"bob"
+        );
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "POST /v2/teal/dryrun");
+        assert_eq!(
+            requests[0].header_value("content-type"),
+            Some("application/msgpack")
+        );
+        let sent = rmpv::decode::read_value(&mut &requests[0].body[..]).unwrap();
+        let field = |name: &str| {
+            sent.as_map()
+                .unwrap()
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(name))
+                .map(|(_, v)| v.clone())
+                .unwrap()
+        };
+        let mut txn = Vec::new();
+        rmpv::encode::write_value(&mut txn, &field("txns")[0]).unwrap();
+        assert_eq!(txn, stx.encode());
+        assert_eq!(
+            field("accounts")[0]["address"].as_str(),
+            Some(sender.address().to_string().as_str())
+        );
+        assert_eq!(
+            field("apps")[0]["params"]["approval-program"].as_slice(),
+            Some(&[0x05, 0x81, 0x01][..])
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_for_confirmation() {
+        let stx = signed_payment();
+        let transport = MockTransport::new(vec![
+            status(Round(10)),
+            (200, pending(&stx, vec![])),
+            status(Round(11)),
+            (
+                200,
+                pending(&stx, vec![("confirmed-round", Value::from(11))]),
+            ),
+        ]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let confirmed = client.wait_for_confirmation(&stx.tx.id(), 5).await.unwrap();
+        assert_eq!(confirmed.confirmed_round, Some(Round(11)));
+        assert!(confirmed.txn == stx);
+
+        let paths: Vec<_> = transport.requests().iter().map(|r| r.line()).collect();
+        let pending_path = format!(
+            "GET /v2/transactions/pending/{}?format=msgpack",
+            stx.tx.id()
+        );
+        assert_eq!(
+            paths,
+            [
+                "GET /v2/status".to_owned(),
+                pending_path.clone(),
+                "GET /v2/status/wait-for-block-after/10".to_owned(),
+                pending_path,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_for_confirmation_rejected() {
+        let stx = signed_payment();
+        let pool_error = Value::from("overspend");
+        let transport = MockTransport::new(vec![
+            status(Round(10)),
+            (200, pending(&stx, vec![("pool-error", pool_error)])),
+        ]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport);
+        let result = client.wait_for_confirmation(&stx.tx.id(), 5).await;
+        assert!(matches!(result, Err(ClientError::Rejected(e)) if e == "overspend"));
+    }
+
+    #[tokio::test]
+    async fn wait_for_confirmation_timeout() {
+        let stx = signed_payment();
+        let transport = MockTransport::new(vec![
+            status(Round(10)),
+            (200, pending(&stx, vec![])),
+            status(Round(11)),
+            (200, pending(&stx, vec![])),
+        ]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport);
+        let result = client.wait_for_confirmation(&stx.tx.id(), 1).await;
+        assert!(matches!(result, Err(ClientError::NotConfirmed(1))));
+    }
+
+    #[tokio::test]
+    async fn block() {
+        let stx = signed_payment();
+        let header = BlockHeader {
+            round: Round(5),
+            genesis_id: "testnet-v1.0".to_owned(),
+            ..Default::default()
+        };
+        let block = Block {
+            header: header.clone(),
+            payset: Payset(vec![SignedTxInBlock {
+                sig_txad: SignedTxWithAD {
+                    tx: stx.clone(),
+                    ad: ApplyData::default(),
+                },
+                has_genesis_id: false,
+                has_genesis_hash: false,
+            }]),
+        };
+        let block_value = rmpv::decode::read_value(&mut &encoding::to_msgpack(&block)[..]).unwrap();
+        let body = Value::Map(vec![
+            (Value::from("block"), block_value),
+            (Value::from("cert"), Value::Map(Vec::new())),
+        ]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &body).unwrap();
+        let transport = MockTransport::new(vec![(200, bytes)]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let fetched = client.block(Round(5)).await.unwrap();
+        assert_eq!(fetched.header.round, Round(5));
+        assert_eq!(fetched.hash(), header.hash());
+        assert_eq!(fetched.transactions().collect::<Vec<_>>(), [stx]);
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "GET /v2/blocks/5?format=msgpack");
+    }
+
+    #[tokio::test]
+    async fn account_information() {
+        let addr = Address([2; 32]);
+        let body = r#"{
+            "amount": 5000,
+            "amount-without-pending-rewards": 5000,
+            "status": "Online",
+            "assets": [{"amount": 10, "asset-id": 7, "is-frozen": true}],
+            "round": 42
+        }"#;
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let account = client.account_information(&addr).await.unwrap();
+        assert_eq!(account.status, AccountStatus::Online);
+        assert_eq!(account.micro_algos, MicroAlgos(5000));
+        assert_eq!(
+            account.assets[&7],
+            AssetHolding {
+                amount: 10,
+                frozen: true,
+            }
+        );
+        assert!(account.app_local_states.is_empty());
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), format!("GET /v2/accounts/{}", addr));
+    }
+
+    #[tokio::test]
+    async fn asset() {
+        let manager = Address([2; 32]);
+        let body = format!(
+            r#"{{
+                "index": 7,
+                "params": {{
+                    "creator": "{manager}",
+                    "total": 1000,
+                    "decimals": 2,
+                    "unit-name": "TST",
+                    "name": "Test",
+                    "metadata-hash": "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=",
+                    "manager": "{manager}"
+                }}
+            }}"#
+        );
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let params = client.asset(7).await.unwrap();
+        assert_eq!(
+            params,
+            AssetParams {
+                total: 1000,
+                decimals: 2,
+                unit_name: "TST".to_owned(),
+                asset_name: "Test".to_owned(),
+                metadata_hash: [1; 32],
+                manager,
+                ..Default::default()
+            }
+        );
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "GET /v2/assets/7");
+    }
+
+    #[tokio::test]
+    async fn application() {
+        let body = r#"{
+            "id": 9,
+            "params": {
+                "approval-program": "BoEBQw==",
+                "clear-state-program": "BoEBQw==",
+                "global-state": [{"key": "Y291bnQ=", "value": {"type": 2, "uint": 3}}],
+                "global-state-schema": {"num-uint": 1, "num-byte-slice": 0},
+                "local-state-schema": {"num-uint": 0, "num-byte-slice": 2}
+            }
+        }"#;
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let params = client.application(9).await.unwrap();
+        assert_eq!(params.approval_program, [0x06, 0x81, 0x01, 0x43]);
+        assert_eq!(params.clear_state_program, [0x06, 0x81, 0x01, 0x43]);
+        assert_eq!(
+            params.global_state_schema,
+            StateSchema {
+                num_uint: 1,
+                num_byte_slice: 0,
+            }
+        );
+        assert_eq!(params.local_state_schema.num_byte_slice, 2);
+        assert_eq!(params.global_state[&b"count"[..]].uint, 3);
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "GET /v2/applications/9");
+    }
+
+    #[tokio::test]
+    async fn pending_transaction_with_inner_txns() {
+        let sender = Account::from_seed(&[1; 32]);
+        let other = Address([3; 32]);
+        let shared = Address([4; 32]);
+        let app_call = Transaction::app_call(
+            sender.address(),
+            crate::types::AppCallFields {
+                application_id: 5,
+                accounts: vec![other],
+                ..Default::default()
+            },
+            &SuggestedParams {
+                last_round_valid: Round(1000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let stx = sender.sign_transaction(&app_call);
+        let inner = signed_payment();
+
+        let kv = |key: &str, action: u64, value: (&str, Value)| {
+            Value::Map(vec![
+                (
+                    Value::from("key"),
+                    Value::from(BASE64.encode(key.as_bytes())),
+                ),
+                (
+                    Value::from("value"),
+                    Value::Map(vec![
+                        (Value::from("action"), Value::from(action)),
+                        (Value::from(value.0), value.1),
+                    ]),
+                ),
+            ])
+        };
+        let inner_pending = pending(&inner, vec![("closing-amount", Value::from(7))]);
+        let body = pending(
+            &stx,
+            vec![
+                ("confirmed-round", Value::from(20)),
+                (
+                    "logs",
+                    Value::Array(vec![Value::from(&[0x15, 0x1f, 0xff][..])]),
+                ),
+                (
+                    "global-state-delta",
+                    Value::Array(vec![kv("counter", 2, ("uint", Value::from(3)))]),
+                ),
+                (
+                    "local-state-delta",
+                    Value::Array(vec![
+                        Value::Map(vec![
+                            (Value::from("address"), Value::from(other.to_string())),
+                            (
+                                Value::from("delta"),
+                                Value::Array(vec![kv(
+                                    "name",
+                                    1,
+                                    ("bytes", Value::from(BASE64.encode(b"bob"))),
+                                )]),
+                            ),
+                        ]),
+                        Value::Map(vec![
+                            (Value::from("address"), Value::from(shared.to_string())),
+                            (
+                                Value::from("delta"),
+                                Value::Array(vec![kv("level", 2, ("uint", Value::from(4)))]),
+                            ),
+                        ]),
+                    ]),
+                ),
+                (
+                    "inner-txns",
+                    Value::Array(vec![
+                        rmpv::decode::read_value(&mut &inner_pending[..]).unwrap()
+                    ]),
+                ),
+            ],
+        );
+        let transport = MockTransport::new(vec![(200, body)]);
+
+        let client = AlgodClient::with_transport(mock::URL, "secret", transport.clone());
+        let response = client.pending_transaction(&stx.tx.id()).await.unwrap();
+        assert_eq!(response.confirmed_round, Some(Round(20)));
+
+        let delta = &response.eval_delta;
+        assert_eq!(delta.logs, [vec![0x15, 0x1f, 0xff]]);
+        assert!(delta.global_delta[&b"counter"[..]].action == DeltaAction::SetUint);
+        assert_eq!(delta.global_delta[&b"counter"[..]].uint, 3);
+        assert!(delta.local_deltas[&1][&b"name"[..]].action == DeltaAction::SetBytes);
+        assert_eq!(delta.local_deltas[&1][&b"name"[..]].bytes, b"bob");
+        assert_eq!(delta.shared_accounts, [shared]);
+        assert_eq!(delta.local_deltas[&2][&b"level"[..]].uint, 4);
+        assert_eq!(delta.inner_txs.len(), 1);
+        assert!(delta.inner_txs[0].tx == inner);
+        assert_eq!(delta.inner_txs[0].ad.closing_amount, MicroAlgos(7));
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].line(),
+            format!(
+                "GET /v2/transactions/pending/{}?format=msgpack",
+                stx.tx.id()
+            )
+        );
+    }
+}
@@ -0,0 +1,336 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Types for dry-running transactions against a simulated ledger state with algod.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    decode_base64, eval_delta, raw_teal_key_value, RawAccountStateDelta, RawKeyValue,
+    RawStateSchema, RawTealKeyValue, RawTealValue,
+};
+use crate::encoding::{self, DecodeError};
+use crate::types::{
+    AccountData, AccountStatus, Address, AppIndex, AppParams, EvalDelta, Round, SignedTx, TealValue,
+};
+
+/// Transactions to evaluate, and the ledger state to evaluate them against.
+#[derive(Clone, Default)]
+pub struct DryrunRequest {
+    pub txns: Vec<SignedTx>,
+
+    /// Balance records of accounts used by the transactions.
+    pub accounts: HashMap<Address, AccountData>,
+
+    /// Applications called by the transactions, with their creators.
+    pub apps: Vec<DryrunApp>,
+
+    /// Round and timestamp of the simulated latest block, or the node's if zero.
+    pub round: Round,
+    pub latest_timestamp: u64,
+
+    /// Consensus protocol to evaluate with, or the node's current one if empty.
+    pub protocol_version: String,
+}
+
+/// An application in the ledger state of a dryrun.
+#[derive(Clone, Default)]
+pub struct DryrunApp {
+    pub id: AppIndex,
+    pub creator: Address,
+    pub params: AppParams,
+}
+
+/// Results of a dryrun.
+#[derive(Clone)]
+pub struct DryrunResponse {
+    /// Why the request as a whole could not be evaluated, if it could not. Empty otherwise.
+    pub error: String,
+    pub protocol_version: String,
+
+    /// Results of the transactions, in the order of the request.
+    pub txns: Vec<DryrunTxResult>,
+}
+
+/// Results of evaluating the programs of a single transaction.
+#[derive(Clone)]
+pub struct DryrunTxResult {
+    /// Disassembly of the approval or clear state program of an application call.
+    pub disassembly: Vec<String>,
+    pub app_call_messages: Vec<String>,
+    pub app_call_trace: Vec<DryrunState>,
+
+    /// Disassembly of the logic signature's program.
+    pub logic_sig_disassembly: Vec<String>,
+    pub logic_sig_messages: Vec<String>,
+    pub logic_sig_trace: Vec<DryrunState>,
+
+    /// Logs and state changes of an application call.
+    pub eval_delta: EvalDelta,
+
+    /// Opcode budget consumed by the application call, and added by inner transactions.
+    pub budget_consumed: u64,
+    pub budget_added: u64,
+}
+
+/// State of the program after executing a line.
+#[derive(Clone)]
+pub struct DryrunState {
+    /// Line of the disassembly.
+    pub line: u64,
+    pub pc: u64,
+    pub stack: Vec<TealValue>,
+    pub scratch: Vec<TealValue>,
+
+    /// Error raised by executing the line, if any.
+    pub error: String,
+}
+
+/// Request as encoded in msgpack, following the models of the REST API.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawDryrunRequest<'a> {
+    txns: &'a [SignedTx],
+    accounts: Vec<RawAccount>,
+    apps: Vec<RawApp>,
+    round: Round,
+    latest_timestamp: u64,
+    protocol_version: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAccount {
+    address: String,
+    amount: u64,
+    amount_without_pending_rewards: u64,
+    assets: Vec<RawAssetHolding>,
+    apps_local_state: Vec<RawAppLocalState>,
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAssetHolding {
+    asset_id: u64,
+    amount: u64,
+    is_frozen: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAppLocalState {
+    id: AppIndex,
+    schema: RawStateSchema,
+    key_value: Vec<RawTealKeyValue>,
+}
+
+#[derive(Serialize)]
+struct RawApp {
+    id: AppIndex,
+    params: RawAppParams,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAppParams {
+    creator: String,
+    #[serde(with = "serde_bytes")]
+    approval_program: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    clear_state_program: Vec<u8>,
+    global_state: Vec<RawTealKeyValue>,
+    global_state_schema: RawStateSchema,
+    local_state_schema: RawStateSchema,
+    extra_program_pages: u32,
+}
+
+/// Response as encoded in JSON.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(super) struct RawDryrunResponse {
+    #[serde(default)]
+    error: String,
+    #[serde(default)]
+    protocol_version: String,
+    #[serde(default)]
+    txns: Vec<RawDryrunTxResult>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawDryrunTxResult {
+    #[serde(default)]
+    disassembly: Vec<String>,
+    #[serde(default)]
+    app_call_messages: Vec<String>,
+    #[serde(default)]
+    app_call_trace: Vec<RawDryrunState>,
+    #[serde(default)]
+    logic_sig_disassembly: Vec<String>,
+    #[serde(default)]
+    logic_sig_messages: Vec<String>,
+    #[serde(default)]
+    logic_sig_trace: Vec<RawDryrunState>,
+    #[serde(default)]
+    global_delta: Vec<RawKeyValue>,
+    #[serde(default)]
+    local_deltas: Vec<RawAccountStateDelta>,
+    #[serde(default)]
+    logs: Vec<String>,
+    #[serde(default)]
+    budget_consumed: u64,
+    #[serde(default)]
+    budget_added: u64,
+}
+
+#[derive(Deserialize)]
+struct RawDryrunState {
+    line: u64,
+    pc: u64,
+    #[serde(default)]
+    stack: Vec<RawTealValue>,
+    #[serde(default)]
+    scratch: Vec<RawTealValue>,
+    #[serde(default)]
+    error: String,
+}
+
+impl DryrunRequest {
+    /// Encodes the request into msgpack, keeping the canonical encoding of the transactions.
+    pub(super) fn encode(&self) -> Vec<u8> {
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|(address, data)| RawAccount {
+                address: address.to_string(),
+                amount: data.micro_algos.0,
+                amount_without_pending_rewards: data.micro_algos.0,
+                assets: data
+                    .assets
+                    .iter()
+                    .map(|(id, holding)| RawAssetHolding {
+                        asset_id: *id,
+                        amount: holding.amount,
+                        is_frozen: holding.frozen,
+                    })
+                    .collect(),
+                apps_local_state: data
+                    .app_local_states
+                    .iter()
+                    .map(|(id, state)| RawAppLocalState {
+                        id: *id,
+                        schema: (&state.schema).into(),
+                        key_value: raw_teal_key_value(&state.key_value),
+                    })
+                    .collect(),
+                status: match data.status {
+                    AccountStatus::Offline => "Offline",
+                    AccountStatus::Online => "Online",
+                    AccountStatus::NotParticipating => "NotParticipating",
+                },
+            })
+            .collect();
+        let apps = self
+            .apps
+            .iter()
+            .map(|app| RawApp {
+                id: app.id,
+                params: RawAppParams {
+                    creator: app.creator.to_string(),
+                    approval_program: app.params.approval_program.clone(),
+                    clear_state_program: app.params.clear_state_program.clone(),
+                    global_state: raw_teal_key_value(&app.params.global_state),
+                    global_state_schema: (&app.params.global_state_schema).into(),
+                    local_state_schema: (&app.params.local_state_schema).into(),
+                    extra_program_pages: app.params.extra_program_pages,
+                },
+            })
+            .collect();
+
+        encoding::to_msgpack(&RawDryrunRequest {
+            txns: &self.txns,
+            accounts,
+            apps,
+            round: self.round,
+            latest_timestamp: self.latest_timestamp,
+            protocol_version: &self.protocol_version,
+        })
+    }
+}
+
+impl DryrunResponse {
+    /// Converts the response to `request`, whose transactions
+    /// the accounts of local state deltas are looked up in.
+    pub(super) fn from_raw(
+        raw: RawDryrunResponse,
+        request: &DryrunRequest,
+    ) -> Result<Self, DecodeError> {
+        if raw.txns.len() > request.txns.len() {
+            return Err(DecodeError::InvalidValue(format!(
+                "{} results for {} transactions",
+                raw.txns.len(),
+                request.txns.len()
+            )));
+        }
+        let txns = raw
+            .txns
+            .into_iter()
+            .zip(&request.txns)
+            .map(|(result, stx)| {
+                let logs = result
+                    .logs
+                    .iter()
+                    .map(|log| decode_base64(log))
+                    .collect::<Result<_, _>>()?;
+                Ok(DryrunTxResult {
+                    disassembly: result.disassembly,
+                    app_call_messages: result.app_call_messages,
+                    app_call_trace: states(result.app_call_trace)?,
+                    logic_sig_disassembly: result.logic_sig_disassembly,
+                    logic_sig_messages: result.logic_sig_messages,
+                    logic_sig_trace: states(result.logic_sig_trace)?,
+                    eval_delta: eval_delta(
+                        &stx.tx,
+                        &result.global_delta,
+                        &result.local_deltas,
+                        logs,
+                        Vec::new(),
+                    )?,
+                    budget_consumed: result.budget_consumed,
+                    budget_added: result.budget_added,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?;
+
+        Ok(DryrunResponse {
+            error: raw.error,
+            protocol_version: raw.protocol_version,
+            txns,
+        })
+    }
+}
+
+fn states(raw: Vec<RawDryrunState>) -> Result<Vec<DryrunState>, DecodeError> {
+    raw.into_iter()
+        .map(|state| {
+            Ok(DryrunState {
+                line: state.line,
+                pc: state.pc,
+                stack: state
+                    .stack
+                    .iter()
+                    .map(RawTealValue::decode)
+                    .collect::<Result<_, _>>()?,
+                scratch: state
+                    .scratch
+                    .iter()
+                    .map(RawTealValue::decode)
+                    .collect::<Result<_, _>>()?,
+                error: state.error,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,1063 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Client for the v2 REST API of the indexer.
+
+use data_encoding::BASE64;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use ed25519_dalek::PublicKey;
+
+#[cfg(feature = "reqwest")]
+use super::transport::ReqwestTransport;
+use super::transport::{HttpRequest, HttpTransport};
+use super::{
+    decode_base64, decode_digest, eval_delta, execute, ClientError, RawAccount,
+    RawAccountStateDelta, RawAssetParams, RawKeyValue, RawStateSchema, API_TOKEN_HEADER,
+};
+use crate::encoding::DecodeError;
+use crate::types::{
+    AccountData, Address, AppCallFields, AppIndex, ApplyData, AssetConfigFields, AssetFreezeFields,
+    AssetIndex, AssetParams, AssetTransferFields, BoxRef, Header, KeyregFields, LogicSig,
+    MicroAlgos, MultisigSignature, MultisigSubsig, OnCompletion, PaymentFields, Round, Signature,
+    SignedTx, SignedTxWithAD, Transaction, TxFields, VotePK, VrfPK,
+};
+
+/// Client for an indexer instance.
+pub struct IndexerClient<H> {
+    url: String,
+    token: String,
+    transport: H,
+}
+
+/// Response of `GET /v2/accounts/{address}`.
+#[derive(Deserialize)]
+struct AccountResponse {
+    account: RawAccount,
+}
+
+/// Filters for searching transactions, mapping to the query parameters of `GET /v2/transactions`.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionQuery {
+    address: Option<Address>,
+    asset_id: Option<AssetIndex>,
+    application_id: Option<AppIndex>,
+    min_round: Option<Round>,
+    max_round: Option<Round>,
+    tx_id: Option<String>,
+    limit: Option<u64>,
+    next: Option<String>,
+}
+
+impl TransactionQuery {
+    /// Creates a query matching all transactions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only matches transactions sending to or from `address`, or otherwise involving it.
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Only matches transactions of the asset.
+    pub fn asset_id(mut self, asset_id: AssetIndex) -> Self {
+        self.asset_id = Some(asset_id);
+        self
+    }
+
+    /// Only matches calls of the application.
+    pub fn application_id(mut self, application_id: AppIndex) -> Self {
+        self.application_id = Some(application_id);
+        self
+    }
+
+    /// Only matches transactions confirmed in `round` or later.
+    pub fn min_round(mut self, round: Round) -> Self {
+        self.min_round = Some(round);
+        self
+    }
+
+    /// Only matches transactions confirmed in `round` or earlier.
+    pub fn max_round(mut self, round: Round) -> Self {
+        self.max_round = Some(round);
+        self
+    }
+
+    /// Only matches the transaction with the given ID.
+    pub fn tx_id(mut self, tx_id: &str) -> Self {
+        self.tx_id = Some(tx_id.to_owned());
+        self
+    }
+
+    /// Limits the number of transactions per page.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Continues the search after the page that returned `next_token`.
+    pub fn next(mut self, next_token: &str) -> Self {
+        self.next = Some(next_token.to_owned());
+        self
+    }
+
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(address) = self.address {
+            params.push(("address", address.to_string()));
+        }
+        let numbers = [
+            ("asset-id", self.asset_id),
+            ("application-id", self.application_id),
+            ("min-round", self.min_round.map(u64::from)),
+            ("max-round", self.max_round.map(u64::from)),
+            ("limit", self.limit),
+        ];
+        for (name, value) in numbers {
+            if let Some(value) = value {
+                params.push((name, value.to_string()));
+            }
+        }
+        if let Some(tx_id) = &self.tx_id {
+            params.push(("txid", tx_id.clone()));
+        }
+        if let Some(next) = &self.next {
+            params.push(("next", next.clone()));
+        }
+        params
+    }
+}
+
+/// A page of the transactions matching a search.
+#[derive(Clone)]
+pub struct TransactionsPage {
+    /// Round of the indexer's latest data when answering the search.
+    pub current_round: Round,
+
+    /// Token for fetching the next page with `TransactionQuery::next`, if there may be one.
+    pub next_token: Option<String>,
+
+    pub transactions: Vec<ConfirmedTx>,
+}
+
+/// A transaction found by the indexer, with the metadata of its confirmation.
+#[derive(Clone)]
+pub struct ConfirmedTx {
+    pub id: String,
+    pub confirmed_round: Round,
+
+    /// Time of the confirming block, in seconds since the Unix epoch.
+    pub round_time: u64,
+
+    /// Position of the transaction within the confirming block.
+    pub intra_round_offset: u64,
+
+    pub stx: SignedTxWithAD,
+}
+
+/// Response of `GET /v2/transactions`.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct TransactionsResponse {
+    current_round: Round,
+    #[serde(default)]
+    next_token: Option<String>,
+    transactions: Vec<RawTransaction>,
+}
+
+/// A page of the accounts holding an asset.
+#[derive(Clone, Debug)]
+pub struct AssetBalancesPage {
+    /// Round of the indexer's latest data when answering the lookup.
+    pub current_round: Round,
+
+    /// Token for fetching the next page with `IndexerClient::asset_balances`, if there may be one.
+    pub next_token: Option<String>,
+
+    pub balances: Vec<AssetBalance>,
+}
+
+/// The holdings of an asset by a single account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AssetBalance {
+    pub address: Address,
+    pub amount: u64,
+    pub frozen: bool,
+}
+
+/// Response of `GET /v2/assets/{id}/balances`.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct AssetBalancesResponse {
+    current_round: Round,
+    #[serde(default)]
+    next_token: Option<String>,
+    #[serde(default)]
+    balances: Vec<RawAssetBalance>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAssetBalance {
+    address: Address,
+    amount: u64,
+    #[serde(default)]
+    is_frozen: bool,
+}
+
+/// A box of an application, a named blob of storage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppBox {
+    pub name: Vec<u8>,
+    pub value: Vec<u8>,
+
+    /// Round of the indexer's data the value is from.
+    pub round: Round,
+}
+
+/// Response of `GET /v2/applications/{id}/box`.
+#[derive(Deserialize)]
+struct BoxResponse {
+    name: String,
+    value: String,
+    #[serde(default)]
+    round: Round,
+}
+
+/// Response of `GET /v2/applications/{id}/boxes`.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct BoxesResponse {
+    #[serde(default)]
+    boxes: Vec<RawBoxDescriptor>,
+    #[serde(default)]
+    next_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawBoxDescriptor {
+    name: String,
+}
+
+/// Transaction as encoded in the JSON of the indexer, together with its apply data.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawTransaction {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    confirmed_round: Round,
+    #[serde(default)]
+    round_time: u64,
+    #[serde(default)]
+    intra_round_offset: u64,
+
+    tx_type: String,
+    sender: Address,
+    fee: MicroAlgos,
+    first_valid: Round,
+    last_valid: Round,
+    #[serde(default)]
+    note: String,
+    #[serde(default)]
+    genesis_id: String,
+    #[serde(default)]
+    genesis_hash: String,
+    #[serde(default)]
+    group: String,
+    #[serde(default)]
+    lease: String,
+    #[serde(default)]
+    rekey_to: Option<Address>,
+    #[serde(default)]
+    auth_addr: Option<Address>,
+    #[serde(default)]
+    signature: RawSignature,
+
+    #[serde(default)]
+    payment_transaction: Option<RawPayment>,
+    #[serde(default)]
+    keyreg_transaction: Option<RawKeyreg>,
+    #[serde(default)]
+    asset_config_transaction: Option<RawAssetConfig>,
+    #[serde(default)]
+    asset_transfer_transaction: Option<RawAssetTransfer>,
+    #[serde(default)]
+    asset_freeze_transaction: Option<RawAssetFreeze>,
+    #[serde(default)]
+    application_transaction: Option<RawAppCall>,
+
+    #[serde(default)]
+    closing_amount: MicroAlgos,
+    #[serde(default)]
+    sender_rewards: MicroAlgos,
+    #[serde(default)]
+    receiver_rewards: MicroAlgos,
+    #[serde(default)]
+    close_rewards: MicroAlgos,
+    #[serde(default)]
+    created_asset_index: Option<AssetIndex>,
+    #[serde(default)]
+    created_application_index: Option<AppIndex>,
+    #[serde(default)]
+    logs: Vec<String>,
+    #[serde(default)]
+    global_state_delta: Vec<RawKeyValue>,
+    #[serde(default)]
+    local_state_delta: Vec<RawAccountStateDelta>,
+    #[serde(default)]
+    inner_txns: Vec<RawTransaction>,
+}
+
+#[derive(Default, Deserialize)]
+struct RawSignature {
+    #[serde(default)]
+    sig: Option<String>,
+    #[serde(default)]
+    multisig: Option<RawMultisig>,
+    #[serde(default)]
+    logicsig: Option<RawLogicSig>,
+}
+
+#[derive(Deserialize)]
+struct RawMultisig {
+    version: u8,
+    threshold: u8,
+    subsignature: Vec<RawSubsig>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawSubsig {
+    public_key: String,
+    #[serde(default)]
+    signature: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawLogicSig {
+    logic: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    signature: Option<String>,
+    #[serde(default)]
+    multisig_signature: Option<RawMultisig>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawPayment {
+    receiver: Address,
+    amount: MicroAlgos,
+    #[serde(default)]
+    close_remainder_to: Option<Address>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawKeyreg {
+    #[serde(default)]
+    vote_participation_key: Option<String>,
+    #[serde(default)]
+    selection_participation_key: Option<String>,
+    #[serde(default)]
+    vote_first_valid: Round,
+    #[serde(default)]
+    vote_last_valid: Round,
+    #[serde(default)]
+    vote_key_dilution: u64,
+    #[serde(default)]
+    non_participation: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAssetConfig {
+    #[serde(default)]
+    asset_id: AssetIndex,
+    #[serde(default)]
+    params: Option<RawAssetParams>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAssetTransfer {
+    asset_id: AssetIndex,
+    #[serde(default)]
+    amount: u64,
+    receiver: Address,
+    /// The account whose holdings are clawed back.
+    #[serde(default)]
+    sender: Option<Address>,
+    #[serde(default)]
+    close_to: Option<Address>,
+    #[serde(default)]
+    close_amount: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAssetFreeze {
+    address: Address,
+    asset_id: AssetIndex,
+    new_freeze_status: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAppCall {
+    #[serde(default)]
+    application_id: AppIndex,
+    on_completion: String,
+    #[serde(default)]
+    application_args: Vec<String>,
+    #[serde(default)]
+    accounts: Vec<Address>,
+    #[serde(default)]
+    foreign_apps: Vec<AppIndex>,
+    #[serde(default)]
+    foreign_assets: Vec<AssetIndex>,
+    #[serde(default)]
+    box_references: Vec<RawBoxReference>,
+    #[serde(default)]
+    approval_program: String,
+    #[serde(default)]
+    clear_state_program: String,
+    #[serde(default)]
+    global_state_schema: RawStateSchema,
+    #[serde(default)]
+    local_state_schema: RawStateSchema,
+    #[serde(default)]
+    extra_program_pages: u32,
+}
+
+#[derive(Deserialize)]
+struct RawBoxReference {
+    /// ID of the box's application, or zero for the called one.
+    #[serde(default)]
+    app: AppIndex,
+    #[serde(default)]
+    name: String,
+}
+
+impl TryFrom<RawTransaction> for ConfirmedTx {
+    type Error = DecodeError;
+
+    fn try_from(raw: RawTransaction) -> Result<Self, DecodeError> {
+        Ok(ConfirmedTx {
+            id: raw.id.clone(),
+            confirmed_round: raw.confirmed_round,
+            round_time: raw.round_time,
+            intra_round_offset: raw.intra_round_offset,
+            stx: raw.signed_tx_with_ad()?,
+        })
+    }
+}
+
+impl RawTransaction {
+    fn signed_tx_with_ad(self) -> Result<SignedTxWithAD, DecodeError> {
+        let mut asset_closing_amount = 0;
+        let fields = match self.tx_type.as_str() {
+            "pay" => {
+                let pay = required(self.payment_transaction, "payment-transaction")?;
+                TxFields::Payment(PaymentFields {
+                    receiver: pay.receiver,
+                    amount: pay.amount,
+                    close_remainder_to: pay.close_remainder_to,
+                })
+            }
+            "keyreg" => {
+                let keyreg = required(self.keyreg_transaction, "keyreg-transaction")?;
+                let vote_pk = match keyreg.vote_participation_key {
+                    Some(key) => VotePK(decode_public_key(&key)?),
+                    None => VotePK::default(),
+                };
+                let selection_pk = match keyreg.selection_participation_key {
+                    Some(key) => VrfPK(decode_digest(&key)?),
+                    None => VrfPK::default(),
+                };
+                TxFields::Keyreg(KeyregFields {
+                    vote_pk,
+                    selection_pk,
+                    vote_first: keyreg.vote_first_valid,
+                    vote_last: keyreg.vote_last_valid,
+                    vote_key_dilution: keyreg.vote_key_dilution,
+                    nonparticipation: keyreg.non_participation,
+                })
+            }
+            "acfg" => {
+                let config = required(self.asset_config_transaction, "asset-config-transaction")?;
+                TxFields::AssetConfig(AssetConfigFields {
+                    config_asset: config.asset_id,
+                    asset_params: match config.params {
+                        Some(params) => params.try_into()?,
+                        None => AssetParams::default(),
+                    },
+                })
+            }
+            "axfer" => {
+                let transfer = required(
+                    self.asset_transfer_transaction,
+                    "asset-transfer-transaction",
+                )?;
+                asset_closing_amount = transfer.close_amount;
+                TxFields::AssetTransfer(AssetTransferFields {
+                    transfer_asset: transfer.asset_id,
+                    asset_amount: transfer.amount,
+                    asset_sender: transfer.sender.unwrap_or_default(),
+                    asset_receiver: transfer.receiver,
+                    asset_close_to: transfer.close_to.unwrap_or_default(),
+                })
+            }
+            "afrz" => {
+                let freeze = required(self.asset_freeze_transaction, "asset-freeze-transaction")?;
+                TxFields::AssetFreeze(AssetFreezeFields {
+                    freeze_account: freeze.address,
+                    freeze_asset: freeze.asset_id,
+                    asset_frozen: freeze.new_freeze_status,
+                })
+            }
+            "appl" => {
+                let call = required(self.application_transaction, "application-transaction")?;
+                TxFields::AppCall(call.try_into()?)
+            }
+            other => {
+                return Err(DecodeError::InvalidValue(format!(
+                    "unsupported transaction type {}",
+                    other
+                )))
+            }
+        };
+
+        let tx = Transaction {
+            header: Header {
+                sender: self.sender,
+                fee: self.fee,
+                first_valid: self.first_valid,
+                last_valid: self.last_valid,
+                note: decode_base64(&self.note)?,
+                genesis_id: self.genesis_id,
+                genesis_hash: decode_digest(&self.genesis_hash)?,
+                group: decode_digest(&self.group)?,
+                lease: decode_digest(&self.lease)?,
+                rekey_to: self.rekey_to.unwrap_or_default(),
+            },
+            fields,
+        };
+        let sig = match self.signature.sig {
+            Some(sig) => decode_signature(&sig)?,
+            None => Signature::default(),
+        };
+        let msig = self.signature.multisig.map(multisig).transpose()?;
+        let lsig = self.signature.logicsig.map(logic_sig).transpose()?;
+
+        let logs = self
+            .logs
+            .iter()
+            .map(|log| decode_base64(log))
+            .collect::<Result<_, _>>()?;
+        let inner_txs = self
+            .inner_txns
+            .into_iter()
+            .map(RawTransaction::signed_tx_with_ad)
+            .collect::<Result<_, _>>()?;
+        let eval_delta = eval_delta(
+            &tx,
+            &self.global_state_delta,
+            &self.local_state_delta,
+            logs,
+            inner_txs,
+        )?;
+
+        Ok(SignedTxWithAD {
+            tx: SignedTx {
+                sig,
+                msig,
+                lsig,
+                tx,
+                auth_addr: self.auth_addr.unwrap_or_default(),
+            },
+            ad: ApplyData {
+                closing_amount: self.closing_amount,
+                asset_closing_amount,
+                sender_rewards: self.sender_rewards,
+                receiver_rewards: self.receiver_rewards,
+                close_rewards: self.close_rewards,
+                eval_delta,
+                config_asset: self.created_asset_index.unwrap_or_default(),
+                application_id: self.created_application_index.unwrap_or_default(),
+            },
+        })
+    }
+}
+
+impl TryFrom<RawAppCall> for AppCallFields {
+    type Error = DecodeError;
+
+    fn try_from(raw: RawAppCall) -> Result<Self, DecodeError> {
+        let on_completion = match raw.on_completion.as_str() {
+            "noop" => OnCompletion::NoOpOC,
+            "optin" => OnCompletion::OptInOC,
+            "closeout" => OnCompletion::CloseOutOC,
+            "clear" => OnCompletion::ClearStateOC,
+            "update" => OnCompletion::UpdateApplicationOC,
+            "delete" => OnCompletion::DeleteApplicationOC,
+            other => {
+                return Err(DecodeError::InvalidValue(format!(
+                    "unknown on-completion {}",
+                    other
+                )))
+            }
+        };
+        // transactions refer to applications of boxes by their index into the foreign apps
+        let boxes = raw
+            .box_references
+            .iter()
+            .map(|reference| {
+                let app_index = if reference.app == 0 || reference.app == raw.application_id {
+                    0
+                } else {
+                    let position = raw
+                        .foreign_apps
+                        .iter()
+                        .position(|&id| id == reference.app)
+                        .ok_or_else(|| {
+                            DecodeError::InvalidValue(format!(
+                                "box reference of unreferenced application {}",
+                                reference.app
+                            ))
+                        })?;
+                    position as u64 + 1
+                };
+                Ok(BoxRef {
+                    app_index,
+                    name: decode_base64(&reference.name)?,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?;
+        Ok(AppCallFields {
+            application_id: raw.application_id,
+            on_completion,
+            application_args: raw
+                .application_args
+                .iter()
+                .map(|arg| decode_base64(arg))
+                .collect::<Result<_, _>>()?,
+            accounts: raw.accounts,
+            foreign_apps: raw.foreign_apps,
+            foreign_assets: raw.foreign_assets,
+            boxes,
+            local_state_schema: raw.local_state_schema.into(),
+            global_state_schema: raw.global_state_schema.into(),
+            approval_program: decode_base64(&raw.approval_program)?,
+            clear_state_program: decode_base64(&raw.clear_state_program)?,
+            extra_program_pages: raw.extra_program_pages,
+        })
+    }
+}
+
+fn multisig(raw: RawMultisig) -> Result<MultisigSignature, DecodeError> {
+    let subsigs = raw
+        .subsignature
+        .into_iter()
+        .map(|subsig| {
+            Ok(MultisigSubsig {
+                key: decode_public_key(&subsig.public_key)?,
+                sig: subsig.signature.map(|s| decode_signature(&s)).transpose()?,
+            })
+        })
+        .collect::<Result<_, DecodeError>>()?;
+    Ok(MultisigSignature {
+        version: raw.version,
+        threshold: raw.threshold,
+        subsigs,
+    })
+}
+
+fn logic_sig(raw: RawLogicSig) -> Result<LogicSig, DecodeError> {
+    Ok(LogicSig {
+        logic: decode_base64(&raw.logic)?,
+        sig: match raw.signature {
+            Some(sig) => decode_signature(&sig)?,
+            None => Signature::default(),
+        },
+        msig: raw
+            .multisig_signature
+            .map(multisig)
+            .transpose()?
+            .unwrap_or_default(),
+        args: raw
+            .args
+            .iter()
+            .map(|arg| decode_base64(arg))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Unwraps the type specific fields, which must be present for the transaction's type.
+fn required<T>(fields: Option<T>, name: &str) -> Result<T, DecodeError> {
+    fields.ok_or_else(|| DecodeError::InvalidValue(format!("missing {}", name)))
+}
+
+fn decode_public_key(s: &str) -> Result<PublicKey, DecodeError> {
+    PublicKey::from_bytes(&decode_base64(s)?)
+        .map_err(|_| DecodeError::InvalidValue(format!("invalid public key {}", s)))
+}
+
+fn decode_signature(s: &str) -> Result<Signature, DecodeError> {
+    ed25519::Signature::from_bytes(&decode_base64(s)?)
+        .map(Signature)
+        .map_err(|_| DecodeError::InvalidValue(format!("invalid signature {}", s)))
+}
+
+#[cfg(feature = "reqwest")]
+impl IndexerClient<ReqwestTransport> {
+    /// Creates a client for the indexer at `url`, authenticating with the API `token`.
+    pub fn new(url: &str, token: &str) -> Self {
+        Self::with_transport(url, token, ReqwestTransport::default())
+    }
+}
+
+impl<H: HttpTransport> IndexerClient<H> {
+    /// Creates a client for the indexer at `url` that sends its requests over `transport`.
+    pub fn with_transport(url: &str, token: &str, transport: H) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_owned(),
+            token: token.to_owned(),
+            transport,
+        }
+    }
+
+    /// Looks up the balances, asset holdings, and application state of an account.
+    pub async fn account(&self, addr: &Address) -> Result<AccountData, ClientError> {
+        let response: AccountResponse = self.get(&format!("/v2/accounts/{}", addr)).await?;
+        Ok(response.account.try_into()?)
+    }
+
+    /// Searches for transactions matching the query, returning one page of results.
+    pub async fn search_transactions(
+        &self,
+        query: &TransactionQuery,
+    ) -> Result<TransactionsPage, ClientError> {
+        let response: TransactionsResponse =
+            self.get_with("/v2/transactions", &query.params()).await?;
+        Ok(TransactionsPage {
+            current_round: response.current_round,
+            next_token: response.next_token,
+            transactions: response
+                .transactions
+                .into_iter()
+                .map(ConfirmedTx::try_from)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Looks up the accounts holding the asset, returning one page of them.
+    ///
+    /// Pass the `next_token` of a page to continue after it, or `None` to start.
+    pub async fn asset_balances(
+        &self,
+        asset_id: AssetIndex,
+        next: Option<&str>,
+    ) -> Result<AssetBalancesPage, ClientError> {
+        let params: Vec<_> = next
+            .map(|next| ("next", next.to_owned()))
+            .into_iter()
+            .collect();
+        let path = format!("/v2/assets/{}/balances", asset_id);
+        let response: AssetBalancesResponse = self.get_with(&path, &params).await?;
+        Ok(AssetBalancesPage {
+            current_round: response.current_round,
+            next_token: response.next_token,
+            balances: response
+                .balances
+                .into_iter()
+                .map(|balance| AssetBalance {
+                    address: balance.address,
+                    amount: balance.amount,
+                    frozen: balance.is_frozen,
+                })
+                .collect(),
+        })
+    }
+
+    /// Looks up the box of the application with the given name.
+    pub async fn application_box(
+        &self,
+        app_id: AppIndex,
+        name: &[u8],
+    ) -> Result<AppBox, ClientError> {
+        let path = format!("/v2/applications/{}/box", app_id);
+        let params = [("name", format!("b64:{}", BASE64.encode(name)))];
+        let response: BoxResponse = self.get_with(&path, &params).await?;
+        Ok(AppBox {
+            name: decode_base64(&response.name)?,
+            value: decode_base64(&response.value)?,
+            round: response.round,
+        })
+    }
+
+    /// Lists the names of all boxes of the application, fetching as many pages as needed.
+    pub async fn application_boxes(&self, app_id: AppIndex) -> Result<Vec<Vec<u8>>, ClientError> {
+        let path = format!("/v2/applications/{}/boxes", app_id);
+        let mut names = Vec::new();
+        let mut params = Vec::new();
+        loop {
+            let response: BoxesResponse = self.get_with(&path, &params).await?;
+            for descriptor in &response.boxes {
+                names.push(decode_base64(&descriptor.name)?);
+            }
+            match response.next_token {
+                // an empty page ends the listing, even if it carries a token
+                Some(next) if !response.boxes.is_empty() => params = vec![("next", next)],
+                _ => return Ok(names),
+            }
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ClientError> {
+        self.get_with(path, &[]).await
+    }
+
+    async fn get_with<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        params: &[(&str, String)],
+    ) -> Result<T, ClientError> {
+        let request = HttpRequest::get(format!("{}{}", self.url, path))
+            .query(params)
+            .header(API_TOKEN_HEADER, &self.token);
+        execute(&self.transport, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::client::mock;
+    use crate::client::transport::MockTransport;
+    use crate::types::{AccountStatus, AssetHolding, TealType};
+
+    /// Response of an indexer for an account holding an asset,
+    /// opted into one application and having created another.
+    const ACCOUNT_FIXTURE: &str = include_str!("../../data/indexer_account.json");
+
+    /// Response of an indexer for a payment, and an application call with an inner transaction.
+    const TRANSACTIONS_FIXTURE: &str = include_str!("../../data/indexer_transactions.json");
+
+    #[tokio::test]
+    async fn account() {
+        let transport = MockTransport::new(vec![(200, ACCOUNT_FIXTURE.into())]);
+        let addr = Address::from_str("6Z3C3LDVWGMX23BMSYMANACQOSINPFIRF77H7N3AWJZYV6OH6GWTJKVMXY")
+            .unwrap();
+
+        let client = IndexerClient::with_transport(mock::URL, "secret", transport.clone());
+        let account = client.account(&addr).await.unwrap();
+        assert_eq!(account.micro_algos, MicroAlgos(5_002_000));
+        assert_eq!(account.status, AccountStatus::Offline);
+        assert_eq!(
+            account.assets[&31566704],
+            AssetHolding {
+                amount: 2_500_000,
+                frozen: false
+            }
+        );
+
+        let local = &account.app_local_states[&57];
+        assert!(local.schema.num_uint == 1 && local.schema.num_byte_slice == 1);
+        assert_eq!(local.key_value[&b"counter"[..]].teal_type, TealType::Uint);
+        assert_eq!(local.key_value[&b"counter"[..]].uint, 7);
+        assert_eq!(local.key_value[&b"name"[..]].teal_type, TealType::Bytes);
+        assert_eq!(local.key_value[&b"name"[..]].bytes, b"alice");
+
+        let params = &account.app_params[&60];
+        assert_eq!(params.approval_program, [0x05, 0x81, 0x01]);
+        assert_eq!(params.clear_state_program, [0x05, 0x81, 0x01]);
+        assert_eq!(params.global_state[&b"total"[..]].uint, 12);
+        // keys and values are arbitrary bytes, not necessarily UTF-8
+        assert_eq!(params.global_state[&[0xff, 0x00][..]].bytes, [0x80, 0xff]);
+        assert_eq!(params.global_state_schema.num_uint, 1);
+        assert_eq!(params.local_state_schema.num_byte_slice, 1);
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), format!("GET /v2/accounts/{}", addr));
+        assert_eq!(requests[0].header_value("X-Algo-API-Token"), Some("secret"));
+    }
+
+    #[tokio::test]
+    async fn account_not_found() {
+        let body = br#"{"message":"no accounts found for address"}"#;
+        let transport = MockTransport::new(vec![(404, body.to_vec())]);
+
+        let client = IndexerClient::with_transport(mock::URL, "", transport.clone());
+        let result = client.account(&Address([1; 32])).await;
+        assert!(matches!(
+            result,
+            Err(ClientError::Api { status: 404, ref message }) if message == "no accounts found for address"
+        ));
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].line(),
+            format!("GET /v2/accounts/{}", Address([1; 32]))
+        );
+    }
+
+    #[tokio::test]
+    async fn search_transactions() {
+        let transport = MockTransport::new(vec![(200, TRANSACTIONS_FIXTURE.into())]);
+        let addr = Address::from_str("QE4XODVIPULV6VVDKRTMGTD6ZTFY3CURWTXDPIS56YHVXD6JWOKORTLPBU")
+            .unwrap();
+
+        let client = IndexerClient::with_transport(mock::URL, "secret", transport.clone());
+        let query = TransactionQuery::new()
+            .address(addr)
+            .min_round(Round(16460000))
+            .limit(2);
+        let page = client.search_transactions(&query).await.unwrap();
+        assert_eq!(page.current_round, Round(16460100));
+        assert_eq!(page.next_token.as_deref(), Some("5nb7AAAAAAAAAAAA"));
+        assert_eq!(page.transactions.len(), 2);
+
+        // the decoded transactions are identical to the signed ones
+        for confirmed in &page.transactions {
+            assert_eq!(confirmed.stx.tx.tx.id(), confirmed.id);
+            assert_eq!(confirmed.stx.tx.verify(), Ok(()));
+        }
+        let pay = &page.transactions[0];
+        assert_eq!(pay.confirmed_round, Round(16460003));
+        assert_eq!(pay.intra_round_offset, 2);
+        assert_eq!(pay.stx.tx.tx.header.note, b"hello");
+
+        let call = &page.transactions[1].stx;
+        let delta = &call.ad.eval_delta;
+        assert_eq!(
+            delta.logs,
+            [[0x15, 0x1f, 0x7c, 0x75, 0, 0, 0, 0, 0, 0, 0, 3]]
+        );
+        assert_eq!(delta.global_delta[&b"total"[..]].uint, 13);
+        assert_eq!(delta.local_deltas[&1][&b"counter"[..]].uint, 8);
+        assert_eq!(delta.inner_txs.len(), 1);
+        match &delta.inner_txs[0].tx.tx.fields {
+            TxFields::AssetTransfer(transfer) => {
+                assert_eq!(transfer.transfer_asset, 31566704);
+                assert_eq!(transfer.asset_amount, 10);
+            }
+            _ => panic!("inner transaction is not an asset transfer"),
+        }
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].line(),
+            format!(
+                "GET /v2/transactions?address={}&min-round=16460000&limit=2",
+                addr
+            )
+        );
+
+        let next = query.next(page.next_token.as_deref().unwrap());
+        assert_eq!(
+            next.params().last().unwrap(),
+            &("next", "5nb7AAAAAAAAAAAA".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn asset_balances() {
+        let alice = Address([1; 32]);
+        let bob = Address([2; 32]);
+        let first = format!(
+            r#"{{
+                "balances": [
+                    {{"address": "{}", "amount": 100, "is-frozen": false, "deleted": false}},
+                    {{"address": "{}", "amount": 0, "is-frozen": true, "deleted": false}}
+                ],
+                "current-round": 16460100,
+                "next-token": "AAAAAAABAAAAAAAA"
+            }}"#,
+            alice, bob
+        );
+        let last = r#"{"balances": [], "current-round": 16460101}"#;
+        let transport = MockTransport::new(vec![(200, first.into()), (200, last.into())]);
+
+        let client = IndexerClient::with_transport(mock::URL, "secret", transport.clone());
+        let page = client.asset_balances(31566704, None).await.unwrap();
+        assert_eq!(page.current_round, Round(16460100));
+        assert_eq!(
+            page.balances,
+            [
+                AssetBalance {
+                    address: alice,
+                    amount: 100,
+                    frozen: false
+                },
+                AssetBalance {
+                    address: bob,
+                    amount: 0,
+                    frozen: true
+                }
+            ]
+        );
+
+        let next = page.next_token.as_deref();
+        assert_eq!(next, Some("AAAAAAABAAAAAAAA"));
+        let page = client.asset_balances(31566704, next).await.unwrap();
+        assert!(page.balances.is_empty() && page.next_token.is_none());
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "GET /v2/assets/31566704/balances");
+        assert_eq!(
+            requests[1].line(),
+            "GET /v2/assets/31566704/balances?next=AAAAAAABAAAAAAAA"
+        );
+    }
+
+    #[tokio::test]
+    async fn application_box() {
+        let body = r#"{"name": "Ym94+w==", "round": 16460100, "value": "AAAAAAAAAAc="}"#;
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = IndexerClient::with_transport(mock::URL, "secret", transport.clone());
+        let app_box = client.application_box(60, b"box\xfb").await.unwrap();
+        assert_eq!(
+            app_box,
+            AppBox {
+                name: b"box\xfb".to_vec(),
+                value: vec![0, 0, 0, 0, 0, 0, 0, 7],
+                round: Round(16460100)
+            }
+        );
+
+        // the name is base64 encoded, and then escaped for the query string
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].line(),
+            "GET /v2/applications/60/box?name=b64%3AYm94%2Bw%3D%3D"
+        );
+    }
+
+    #[tokio::test]
+    async fn application_boxes() {
+        let first = r#"{"application-id": 60, "boxes": [{"name": "YQ=="}, {"name": "Yg=="}], "next-token": "Yg=="}"#;
+        let last = r#"{"application-id": 60, "boxes": [{"name": "Yw=="}]}"#;
+        let transport = MockTransport::new(vec![(200, first.into()), (200, last.into())]);
+
+        let client = IndexerClient::with_transport(mock::URL, "secret", transport.clone());
+        let names = client.application_boxes(60).await.unwrap();
+        assert_eq!(names, [b"a", b"b", b"c"]);
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "GET /v2/applications/60/boxes");
+        assert_eq!(
+            requests[1].line(),
+            "GET /v2/applications/60/boxes?next=Yg%3D%3D"
+        );
+    }
+}
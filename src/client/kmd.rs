@@ -0,0 +1,282 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Client for the v1 REST API of kmd, the key management daemon that keeps keys in wallets.
+
+use data_encoding::BASE64;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "reqwest")]
+use super::transport::ReqwestTransport;
+use super::transport::{HttpRequest, HttpTransport};
+use super::{execute, ClientError, CONTENT_TYPE};
+use crate::encoding;
+use crate::types::{Address, MasterDerivationKey, SignedTx, Transaction};
+
+/// Header carrying the API token of kmd.
+const KMD_TOKEN_HEADER: &str = "X-KMD-API-Token";
+
+/// Client for a kmd instance.
+pub struct KmdClient<H> {
+    url: String,
+    token: String,
+    transport: H,
+}
+
+#[derive(Serialize)]
+struct InitWalletHandleRequest<'a> {
+    wallet_id: &'a str,
+    wallet_password: &'a str,
+}
+
+#[derive(Deserialize)]
+struct InitWalletHandleResponse {
+    wallet_handle_token: String,
+}
+
+#[derive(Serialize)]
+struct ListKeysRequest<'a> {
+    wallet_handle_token: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ListKeysResponse {
+    #[serde(default)]
+    addresses: Vec<Address>,
+}
+
+#[derive(Serialize)]
+struct ExportMasterKeyRequest<'a> {
+    wallet_handle_token: &'a str,
+    wallet_password: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ExportMasterKeyResponse {
+    master_derivation_key: MasterDerivationKey,
+}
+
+#[derive(Serialize)]
+struct SignTransactionRequest<'a> {
+    wallet_handle_token: &'a str,
+    wallet_password: &'a str,
+    /// Base64 of the msgpack encoded transaction.
+    transaction: String,
+}
+
+#[derive(Deserialize)]
+struct SignTransactionResponse {
+    /// Base64 of the msgpack encoded signed transaction.
+    signed_transaction: String,
+}
+
+#[cfg(feature = "reqwest")]
+impl KmdClient<ReqwestTransport> {
+    /// Creates a client for the kmd instance at `url`, authenticating with the API `token`.
+    pub fn new(url: &str, token: &str) -> Self {
+        Self::with_transport(url, token, ReqwestTransport::default())
+    }
+}
+
+impl<H: HttpTransport> KmdClient<H> {
+    /// Creates a client for the kmd instance at `url` that sends its requests over `transport`.
+    pub fn with_transport(url: &str, token: &str, transport: H) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_owned(),
+            token: token.to_owned(),
+            transport,
+        }
+    }
+
+    /// Unlocks the wallet with the given ID, returning a handle for accessing its keys.
+    ///
+    /// Handles expire after a while of not being used.
+    pub async fn init_wallet_handle(
+        &self,
+        wallet_id: &str,
+        password: &str,
+    ) -> Result<String, ClientError> {
+        let request = InitWalletHandleRequest {
+            wallet_id,
+            wallet_password: password,
+        };
+        let response: InitWalletHandleResponse = self.post("/v1/wallet/init", &request).await?;
+        Ok(response.wallet_handle_token)
+    }
+
+    /// Lists the addresses of all keys in the wallet.
+    pub async fn list_keys(&self, handle: &str) -> Result<Vec<Address>, ClientError> {
+        let request = ListKeysRequest {
+            wallet_handle_token: handle,
+        };
+        let response: ListKeysResponse = self.post("/v1/key/list", &request).await?;
+        Ok(response.addresses)
+    }
+
+    /// Exports the wallet's master derivation key, from which all of its keys can be restored.
+    pub async fn export_master_derivation_key(
+        &self,
+        handle: &str,
+        password: &str,
+    ) -> Result<MasterDerivationKey, ClientError> {
+        let request = ExportMasterKeyRequest {
+            wallet_handle_token: handle,
+            wallet_password: password,
+        };
+        let response: ExportMasterKeyResponse =
+            self.post("/v1/master-key/export", &request).await?;
+        Ok(response.master_derivation_key)
+    }
+
+    /// Signs the transaction with the wallet's key for the transaction's sender.
+    pub async fn sign_transaction(
+        &self,
+        handle: &str,
+        password: &str,
+        tx: &Transaction,
+    ) -> Result<SignedTx, ClientError> {
+        let request = SignTransactionRequest {
+            wallet_handle_token: handle,
+            wallet_password: password,
+            transaction: BASE64.encode(&encoding::to_msgpack(tx)),
+        };
+        let response: SignTransactionResponse = self.post("/v1/transaction/sign", &request).await?;
+        Ok(SignedTx::from_base64(&response.signed_transaction)?)
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, ClientError> {
+        let request = HttpRequest::post(format!("{}{}", self.url, path))
+            .header(KMD_TOKEN_HEADER, &self.token)
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(body).expect("requests are always serializable"));
+        execute(&self.transport, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::account::Account;
+    use crate::client::mock;
+    use crate::client::transport::MockTransport;
+    use crate::types::{MicroAlgos, Round, SuggestedParams};
+
+    #[tokio::test]
+    async fn init_wallet_handle() {
+        let body = r#"{"wallet_handle_token": "b7ac9b3e9a1e6d2c"}"#;
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = KmdClient::with_transport(mock::URL, "secret", transport.clone());
+        let handle = client
+            .init_wallet_handle("wallet", "hunter2")
+            .await
+            .unwrap();
+        assert_eq!(handle, "b7ac9b3e9a1e6d2c");
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "POST /v1/wallet/init");
+        assert_eq!(requests[0].header_value(KMD_TOKEN_HEADER), Some("secret"));
+        let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(
+            sent,
+            serde_json::json!({"wallet_id": "wallet", "wallet_password": "hunter2"})
+        );
+    }
+
+    #[tokio::test]
+    async fn init_wallet_handle_wrong_password() {
+        let body = r#"{"error": true, "message": "wrong password"}"#;
+        let transport = MockTransport::new(vec![(401, body.into())]);
+
+        let client = KmdClient::with_transport(mock::URL, "secret", transport);
+        let result = client.init_wallet_handle("wallet", "hunter3").await;
+        assert!(matches!(
+            result,
+            Err(ClientError::Api { status: 401, message }) if message == "wrong password"
+        ));
+    }
+
+    #[tokio::test]
+    async fn list_keys() {
+        let alice = Account::from_seed(&[1; 32]).address();
+        let bob = Account::from_seed(&[2; 32]).address();
+        let body = format!(r#"{{"addresses": ["{}", "{}"]}}"#, alice, bob);
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = KmdClient::with_transport(mock::URL, "secret", transport.clone());
+        let keys = client.list_keys("handle").await.unwrap();
+        assert_eq!(keys, [alice, bob]);
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "POST /v1/key/list");
+        let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(sent, serde_json::json!({"wallet_handle_token": "handle"}));
+    }
+
+    #[tokio::test]
+    async fn export_master_derivation_key() {
+        let body = r#"{"master_derivation_key": "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8="}"#;
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = KmdClient::with_transport(mock::URL, "secret", transport.clone());
+        let mdk = client
+            .export_master_derivation_key("handle", "hunter2")
+            .await
+            .unwrap();
+        assert_eq!(mdk.0, core::array::from_fn(|i| i as u8));
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "POST /v1/master-key/export");
+        let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(
+            sent,
+            serde_json::json!({"wallet_handle_token": "handle", "wallet_password": "hunter2"})
+        );
+    }
+
+    #[tokio::test]
+    async fn sign_transaction() {
+        let account = Account::from_seed(&[1; 32]);
+        let params = SuggestedParams {
+            first_round_valid: Round(1),
+            last_round_valid: Round(1001),
+            min_fee: MicroAlgos(1000),
+            ..Default::default()
+        };
+        let tx = Transaction::payment(
+            account.address(),
+            Address([2; 32]),
+            MicroAlgos(5),
+            None,
+            &params,
+        )
+        .unwrap();
+        let stx = account.sign_transaction(&tx);
+        let body = format!(r#"{{"signed_transaction": "{}"}}"#, stx.to_base64());
+        let transport = MockTransport::new(vec![(200, body.into())]);
+
+        let client = KmdClient::with_transport(mock::URL, "secret", transport.clone());
+        let signed = client
+            .sign_transaction("handle", "hunter2", &tx)
+            .await
+            .unwrap();
+        assert!(signed == stx);
+        assert_eq!(signed.verify(), Ok(()));
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].line(), "POST /v1/transaction/sign");
+        let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(sent["wallet_handle_token"], "handle");
+        assert_eq!(sent["wallet_password"], "hunter2");
+        let sent_tx = BASE64
+            .decode(sent["transaction"].as_str().unwrap().as_bytes())
+            .unwrap();
+        assert_eq!(sent_tx, encoding::to_msgpack(&tx));
+    }
+}
@@ -0,0 +1,464 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Clients for the REST APIs of algod, the indexer, and kmd.
+
+use std::collections::BTreeMap;
+
+use data_encoding::BASE64;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::encoding::{self, DecodeError};
+use crate::types::{
+    AccountData, AccountStatus, Address, AppIndex, AppLocalState, AppParams, AssetHolding,
+    AssetIndex, AssetParams, DeltaAction, Digest, EvalDelta, GroupError, MicroAlgos,
+    SignedTxWithAD, StateDelta, StateSchema, TealKeyValue, TealType, TealValue, Transaction,
+    TxFields, ValueDelta,
+};
+
+pub mod algod;
+pub mod dryrun;
+pub mod indexer;
+pub mod kmd;
+pub mod transport;
+
+use transport::{HttpRequest, HttpTransport};
+
+/// Header carrying the API token, accepted by both algod and the indexer.
+const API_TOKEN_HEADER: &str = "X-Algo-API-Token";
+
+const CONTENT_TYPE: &str = "Content-Type";
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// The transport failed to send the request or to receive the response.
+    #[error("request failed: {0}")]
+    Request(Box<dyn std::error::Error + Send + Sync>),
+    #[error("server responded with status {status}: {message}")]
+    Api { status: u16, message: String },
+    #[error("invalid response: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("transaction was rejected: {0}")]
+    Rejected(String),
+    #[error("transaction not confirmed after {0} rounds")]
+    NotConfirmed(u64),
+    #[error("invalid transaction group: {0}")]
+    InvalidGroup(#[from] GroupError),
+}
+
+/// Body of an error response.
+#[derive(Deserialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+/// Account as encoded in the JSON of the REST APIs.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAccount {
+    amount: MicroAlgos,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    assets: Vec<RawAssetHolding>,
+    #[serde(default)]
+    apps_local_state: Vec<RawAppLocalState>,
+    #[serde(default)]
+    created_apps: Vec<RawApp>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAssetHolding {
+    asset_id: AssetIndex,
+    amount: u64,
+    is_frozen: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAppLocalState {
+    id: AppIndex,
+    schema: RawStateSchema,
+    #[serde(default)]
+    key_value: Vec<RawTealKeyValue>,
+}
+
+#[derive(Deserialize)]
+struct RawApp {
+    id: AppIndex,
+    params: RawAppParams,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAppParams {
+    approval_program: String,
+    clear_state_program: String,
+    #[serde(default)]
+    global_state: Vec<RawTealKeyValue>,
+    #[serde(default)]
+    local_state_schema: RawStateSchema,
+    #[serde(default)]
+    global_state_schema: RawStateSchema,
+    #[serde(default)]
+    extra_program_pages: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawAssetParams {
+    #[serde(default)]
+    total: u64,
+    #[serde(default)]
+    decimals: u32,
+    #[serde(default)]
+    default_frozen: bool,
+    #[serde(default)]
+    unit_name: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    metadata_hash: String,
+    #[serde(default)]
+    manager: Option<Address>,
+    #[serde(default)]
+    reserve: Option<Address>,
+    #[serde(default)]
+    freeze: Option<Address>,
+    #[serde(default)]
+    clawback: Option<Address>,
+}
+
+/// Key-value pair of a state delta, as encoded by algod and the indexer.
+#[derive(Deserialize)]
+struct RawKeyValue {
+    key: String,
+    value: RawValueDelta,
+}
+
+#[derive(Deserialize)]
+struct RawValueDelta {
+    action: u64,
+    #[serde(default)]
+    bytes: Option<String>,
+    #[serde(default)]
+    uint: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct RawAccountStateDelta {
+    address: Address,
+    delta: Vec<RawKeyValue>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawStateSchema {
+    num_uint: u64,
+    num_byte_slice: u64,
+}
+
+/// Key-value pair of a TEAL key/value store, with base64 encoded keys and byte values.
+#[derive(Serialize, Deserialize)]
+struct RawTealKeyValue {
+    key: String,
+    value: RawTealValue,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawTealValue {
+    #[serde(rename = "type")]
+    teal_type: TealType,
+    #[serde(default)]
+    bytes: String,
+    #[serde(default)]
+    uint: u64,
+}
+
+impl From<RawStateSchema> for StateSchema {
+    fn from(raw: RawStateSchema) -> Self {
+        StateSchema {
+            num_uint: raw.num_uint,
+            num_byte_slice: raw.num_byte_slice,
+        }
+    }
+}
+
+impl From<&StateSchema> for RawStateSchema {
+    fn from(schema: &StateSchema) -> Self {
+        RawStateSchema {
+            num_uint: schema.num_uint,
+            num_byte_slice: schema.num_byte_slice,
+        }
+    }
+}
+
+impl TryFrom<RawAccount> for AccountData {
+    type Error = DecodeError;
+
+    fn try_from(raw: RawAccount) -> Result<Self, DecodeError> {
+        let assets = raw
+            .assets
+            .into_iter()
+            .map(|a| {
+                let holding = AssetHolding {
+                    amount: a.amount,
+                    frozen: a.is_frozen,
+                };
+                (a.asset_id, holding)
+            })
+            .collect();
+        let app_local_states = raw
+            .apps_local_state
+            .into_iter()
+            .map(|app| {
+                let state = AppLocalState {
+                    schema: app.schema.into(),
+                    key_value: teal_key_value(&app.key_value)?,
+                };
+                Ok((app.id, state))
+            })
+            .collect::<Result<_, DecodeError>>()?;
+        let app_params = raw
+            .created_apps
+            .into_iter()
+            .map(|app| Ok((app.id, app.params.try_into()?)))
+            .collect::<Result<_, DecodeError>>()?;
+
+        let status = match raw.status.as_str() {
+            "" | "Offline" => AccountStatus::Offline,
+            "Online" => AccountStatus::Online,
+            "NotParticipating" => AccountStatus::NotParticipating,
+            other => {
+                return Err(DecodeError::InvalidValue(format!(
+                    "unknown account status {}",
+                    other
+                )))
+            }
+        };
+
+        Ok(AccountData {
+            status,
+            micro_algos: raw.amount,
+            assets,
+            app_local_states,
+            app_params,
+            ..Default::default()
+        })
+    }
+}
+
+impl TryFrom<RawAppParams> for AppParams {
+    type Error = DecodeError;
+
+    fn try_from(raw: RawAppParams) -> Result<Self, DecodeError> {
+        Ok(AppParams {
+            approval_program: decode_base64(&raw.approval_program)?,
+            clear_state_program: decode_base64(&raw.clear_state_program)?,
+            global_state: teal_key_value(&raw.global_state)?,
+            local_state_schema: raw.local_state_schema.into(),
+            global_state_schema: raw.global_state_schema.into(),
+            extra_program_pages: raw.extra_program_pages,
+        })
+    }
+}
+
+impl TryFrom<RawAssetParams> for AssetParams {
+    type Error = DecodeError;
+
+    fn try_from(raw: RawAssetParams) -> Result<Self, DecodeError> {
+        Ok(AssetParams {
+            total: raw.total,
+            decimals: raw.decimals,
+            default_frozen: raw.default_frozen,
+            unit_name: raw.unit_name,
+            asset_name: raw.name,
+            url: raw.url,
+            metadata_hash: decode_digest(&raw.metadata_hash)?,
+            manager: raw.manager.unwrap_or_default(),
+            reserve: raw.reserve.unwrap_or_default(),
+            freeze: raw.freeze.unwrap_or_default(),
+            clawback: raw.clawback.unwrap_or_default(),
+        })
+    }
+}
+
+impl RawTealValue {
+    fn decode(&self) -> Result<TealValue, DecodeError> {
+        Ok(TealValue {
+            teal_type: self.teal_type,
+            bytes: decode_base64(&self.bytes)?,
+            uint: self.uint,
+        })
+    }
+
+    fn encode(value: &TealValue) -> Self {
+        RawTealValue {
+            teal_type: value.teal_type,
+            bytes: BASE64.encode(&value.bytes),
+            uint: value.uint,
+        }
+    }
+}
+
+/// Converts a list of base64 encoded key-value pairs into a `TealKeyValue`.
+fn teal_key_value(pairs: &[RawTealKeyValue]) -> Result<TealKeyValue, DecodeError> {
+    pairs
+        .iter()
+        .map(|pair| Ok((decode_base64(&pair.key)?, pair.value.decode()?)))
+        .collect()
+}
+
+/// Converts a `TealKeyValue` into a list of base64 encoded key-value pairs.
+fn raw_teal_key_value(kv: &TealKeyValue) -> Vec<RawTealKeyValue> {
+    kv.iter()
+        .map(|(key, value)| RawTealKeyValue {
+            key: BASE64.encode(key),
+            value: RawTealValue::encode(value),
+        })
+        .collect()
+}
+
+/// Converts the execution results of `tx` into their representation in blocks.
+fn eval_delta(
+    tx: &Transaction,
+    global_delta: &[RawKeyValue],
+    local_deltas: &[RawAccountStateDelta],
+    logs: Vec<Vec<u8>>,
+    inner_txs: Vec<SignedTxWithAD>,
+) -> Result<EvalDelta, DecodeError> {
+    // in blocks, accounts are referred to by their offset into `[sender, accounts...]`,
+    // followed by the accounts that the transaction doesn't list itself
+    let sender = tx.header.sender;
+    let accounts = match &tx.fields {
+        TxFields::AppCall(fields) => &fields.accounts[..],
+        _ => &[],
+    };
+    let mut offset_deltas = BTreeMap::new();
+    let mut shared_accounts = Vec::new();
+    for account_delta in local_deltas {
+        let address = account_delta.address;
+        let offset = match std::iter::once(&sender)
+            .chain(accounts)
+            .chain(&shared_accounts)
+            .position(|a| *a == address)
+        {
+            Some(offset) => offset,
+            None => {
+                shared_accounts.push(address);
+                accounts.len() + shared_accounts.len()
+            }
+        };
+        offset_deltas.insert(offset as u64, state_delta(&account_delta.delta)?);
+    }
+
+    Ok(EvalDelta {
+        global_delta: state_delta(global_delta)?,
+        local_deltas: offset_deltas,
+        shared_accounts,
+        logs,
+        inner_txs,
+    })
+}
+
+/// Converts a list of base64 encoded key-value pairs into a `StateDelta`.
+fn state_delta(pairs: &[RawKeyValue]) -> Result<StateDelta, DecodeError> {
+    pairs
+        .iter()
+        .map(|pair| {
+            let action = match pair.value.action {
+                1 => DeltaAction::SetBytes,
+                2 => DeltaAction::SetUint,
+                3 => DeltaAction::Delete,
+                _ => DeltaAction::Invalid,
+            };
+            let value = ValueDelta {
+                action,
+                bytes: decode_base64(pair.value.bytes.as_deref().unwrap_or_default())?,
+                uint: pair.value.uint.unwrap_or_default(),
+            };
+            Ok((decode_base64(&pair.key)?, value))
+        })
+        .collect()
+}
+
+/// Decodes a base64 encoded byte string of the JSON APIs.
+fn decode_base64(s: &str) -> Result<Vec<u8>, DecodeError> {
+    BASE64
+        .decode(s.as_bytes())
+        .map_err(|_| DecodeError::InvalidBase64)
+}
+
+/// Decodes a base64 encoded 32 byte value, where an empty string is all zeros.
+fn decode_digest(s: &str) -> Result<Digest, DecodeError> {
+    if s.is_empty() {
+        return Ok(Digest::default());
+    }
+    let bytes = decode_base64(s)?;
+    bytes
+        .try_into()
+        .map_err(|_| DecodeError::InvalidValue(format!("{} is not 32 bytes long", s)))
+}
+
+/// Sends the request and decodes the JSON response.
+async fn execute<T: DeserializeOwned>(
+    transport: &impl HttpTransport,
+    request: HttpRequest,
+) -> Result<T, ClientError> {
+    let body = send(transport, request).await?;
+    serde_json::from_slice(&body)
+        .map_err(|e| DecodeError::InvalidValue(format!("invalid JSON: {}", e)).into())
+}
+
+/// Sends the request and decodes the msgpack response.
+async fn execute_msgpack<T: DeserializeOwned>(
+    transport: &impl HttpTransport,
+    request: HttpRequest,
+) -> Result<T, ClientError> {
+    let body = send(transport, request).await?;
+    Ok(encoding::from_msgpack(&body)?)
+}
+
+/// Sends the request, turning error responses into `ClientError::Api`.
+async fn send(
+    transport: &impl HttpTransport,
+    request: HttpRequest,
+) -> Result<Vec<u8>, ClientError> {
+    let response = transport
+        .request(request)
+        .await
+        .map_err(|e| ClientError::Request(Box::new(e)))?;
+    if !(200..300).contains(&response.status) {
+        let message = match serde_json::from_slice::<ErrorResponse>(&response.body) {
+            Ok(error) => error.message,
+            Err(_) => String::from_utf8_lossy(&response.body).into_owned(),
+        };
+        return Err(ClientError::Api {
+            status: response.status,
+            message,
+        });
+    }
+    Ok(response.body)
+}
+
+/// Helpers for testing the clients against a `MockTransport`.
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::transport::HttpRequest;
+
+    /// Base URL of the clients under test.
+    pub const URL: &str = "http://localhost:4001";
+
+    impl HttpRequest {
+        /// Returns the method and the URL relative to `URL`, e.g. `GET /v2/status`.
+        pub fn line(&self) -> String {
+            let path = self.url.strip_prefix(URL).expect("request to the mock URL");
+            format!("{} {}", self.method, path)
+        }
+    }
+}
@@ -0,0 +1,309 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Pluggable HTTP backends for the clients.
+//!
+//! The clients only build `HttpRequest`s and decode `HttpResponse`s,
+//! sending them is left to an `HttpTransport`.
+//! `ReqwestTransport` is available with the `reqwest` feature, which is enabled by default.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use thiserror::Error;
+
+/// Method of an HTTP request, only the ones used by the clients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+/// A request to be sent by an `HttpTransport`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: Method,
+    /// The full URL, including the query string.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// The status and body of a response to an `HttpRequest`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+/// Sends HTTP requests on behalf of the clients.
+///
+/// Implement this to use another HTTP library, or to run without network access.
+// Transports on WASM return futures that are not `Send`, so no bound is imposed on them.
+#[allow(async_fn_in_trait)]
+pub trait HttpTransport {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Sends the request, returning the response regardless of its status.
+    async fn request(&self, request: HttpRequest) -> Result<HttpResponse, Self::Error>;
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Method::Get => write!(f, "GET"),
+            Method::Post => write!(f, "POST"),
+        }
+    }
+}
+
+impl HttpRequest {
+    /// Creates a GET request for `url`, without headers.
+    pub fn get(url: String) -> Self {
+        HttpRequest {
+            method: Method::Get,
+            url,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Creates a POST request for `url`, without headers or body.
+    pub fn post(url: String) -> Self {
+        HttpRequest {
+            method: Method::Post,
+            ..Self::get(url)
+        }
+    }
+
+    /// Adds a header to the request.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Sets the body of the request.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Appends the parameters to the URL's query string, percent-encoding them.
+    pub fn query(mut self, params: &[(&str, String)]) -> Self {
+        for (i, (name, value)) in params.iter().enumerate() {
+            let separator = if i == 0 && !self.url.contains('?') {
+                '?'
+            } else {
+                '&'
+            };
+            self.url.push(separator);
+            self.url.push_str(&encode_query_component(name));
+            self.url.push('=');
+            self.url.push_str(&encode_query_component(value));
+        }
+        self
+    }
+
+    /// Looks up the value of a header, ignoring the case of its name.
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Percent-encodes everything but unreserved characters, as in `application/x-www-form-urlencoded`.
+fn encode_query_component(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'*' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Transport backed by a `reqwest` client.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport(pub reqwest::Client);
+
+#[cfg(feature = "reqwest")]
+impl HttpTransport for ReqwestTransport {
+    type Error = reqwest::Error;
+
+    async fn request(&self, request: HttpRequest) -> Result<HttpResponse, reqwest::Error> {
+        let method = match request.method {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+        };
+        let mut builder = self.0.request(method, &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if request.method == Method::Post {
+            builder = builder.body(request.body);
+        }
+        let response = builder.send().await?;
+        Ok(HttpResponse {
+            status: response.status().as_u16(),
+            body: response.bytes().await?.to_vec(),
+        })
+    }
+}
+
+/// Transport answering with canned responses instead of sending requests, for tests.
+///
+/// Clones share the responses and the recorded requests,
+/// so a clone can be kept to inspect the requests a client made.
+#[derive(Clone, Debug, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: VecDeque<HttpResponse>,
+    requests: Vec<HttpRequest>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("no response left for {method} {url}")]
+pub struct MockError {
+    pub method: Method,
+    pub url: String,
+}
+
+impl MockTransport {
+    /// Creates a transport answering one request per `(status, body)` response, in order.
+    pub fn new(responses: Vec<(u16, Vec<u8>)>) -> Self {
+        let responses = responses
+            .into_iter()
+            .map(|(status, body)| HttpResponse { status, body })
+            .collect();
+        MockTransport {
+            state: Arc::new(Mutex::new(MockState {
+                responses,
+                requests: Vec::new(),
+            })),
+        }
+    }
+
+    /// Returns the requests received so far, in order.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl HttpTransport for MockTransport {
+    type Error = MockError;
+
+    async fn request(&self, request: HttpRequest) -> Result<HttpResponse, MockError> {
+        let mut state = self.state.lock().unwrap();
+        let response = state.responses.pop_front().ok_or_else(|| MockError {
+            method: request.method,
+            url: request.url.clone(),
+        })?;
+        state.requests.push(request);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query() {
+        let request = HttpRequest::get("http://localhost/v2/box".to_owned()).query(&[
+            ("name", "b64:Ym94+w==".to_owned()),
+            ("note", "a b".to_owned()),
+        ]);
+        assert_eq!(
+            request.url,
+            "http://localhost/v2/box?name=b64%3AYm94%2Bw%3D%3D&note=a+b"
+        );
+
+        let request = HttpRequest::get("http://localhost/v2/blocks/5?format=msgpack".to_owned())
+            .query(&[("next", "x".to_owned())]);
+        assert_eq!(
+            request.url,
+            "http://localhost/v2/blocks/5?format=msgpack&next=x"
+        );
+    }
+
+    #[tokio::test]
+    async fn mock_transport() {
+        let transport = MockTransport::new(vec![(200, b"ok".to_vec())]);
+        let request = HttpRequest::post("http://localhost/v1/test".to_owned())
+            .header("X-Test", "1")
+            .body("body");
+        let response = transport.clone().request(request.clone()).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"ok");
+        assert_eq!(transport.requests(), [request]);
+        assert_eq!(transport.requests()[0].header_value("x-test"), Some("1"));
+
+        let error = transport
+            .request(HttpRequest::get("http://localhost/v1/test".to_owned()))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "no response left for GET http://localhost/v1/test"
+        );
+    }
+
+    /// Sends a request to a one-shot HTTP server, returning the request as received.
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn reqwest_transport() {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+                head.push(line.trim_end().to_lowercase());
+            }
+            let len = head
+                .iter()
+                .find_map(|h| h.strip_prefix("content-length: "))
+                .map_or(0, |v| v.parse().unwrap());
+            let mut body = vec![0; len];
+            reader.read_exact(&mut body).unwrap();
+            let response =
+                "HTTP/1.1 404 Mock\r\nContent-Length: 5\r\nConnection: close\r\n\r\nnope!";
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            (head, body)
+        });
+
+        let request = HttpRequest::post(format!("{}/v2/test?a=1", url))
+            .header("X-Test", "secret")
+            .body("hello");
+        let response = ReqwestTransport::default().request(request).await.unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.body, b"nope!");
+
+        let (head, body) = server.join().unwrap();
+        assert_eq!(head[0], "post /v2/test?a=1 http/1.1");
+        assert!(head.contains(&"x-test: secret".to_owned()));
+        assert_eq!(body, b"hello");
+    }
+}
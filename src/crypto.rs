@@ -0,0 +1,115 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Domain separated hashing, as the protocol uses it for IDs, signatures, and commitments.
+//!
+//! Everything the protocol hashes or signs is prefixed with a string identifying its kind,
+//! so that no two different kinds of objects can have the same hash.
+//! These follow the prefixes in go-algorand's `protocol/hash.go`, whether this crate uses them or not.
+
+use serde::Serialize;
+use sha2::{Digest as _, Sha512_256};
+
+use crate::encoding;
+use crate::types::Digest;
+
+/// Prefix of the application ID, when deriving an application's address.
+pub const APP_INDEX_PREFIX: &[u8] = b"appID";
+
+/// Prefix of an auction bid, when signing it.
+pub const AUCTION_BID_PREFIX: &[u8] = b"aB";
+/// Prefix of an auction deposit.
+pub const AUCTION_DEPOSIT_PREFIX: &[u8] = b"aD";
+/// Prefix of the outcomes of an auction.
+pub const AUCTION_OUTCOMES_PREFIX: &[u8] = b"aO";
+/// Prefix of the parameters of an auction.
+pub const AUCTION_PARAMS_PREFIX: &[u8] = b"aP";
+/// Prefix of the settlement of an auction.
+pub const AUCTION_SETTLEMENT_PREFIX: &[u8] = b"aS";
+
+/// Prefix of a coin of a state proof, when choosing the signatures it reveals.
+pub const STATE_PROOF_COIN_PREFIX: &[u8] = b"spc";
+/// Prefix of the message a state proof attests to.
+pub const STATE_PROOF_MESSAGE_PREFIX: &[u8] = b"spm";
+/// Prefix of a participant of a state proof.
+pub const STATE_PROOF_PART_PREFIX: &[u8] = b"spp";
+/// Prefix of a signature of a state proof.
+pub const STATE_PROOF_SIG_PREFIX: &[u8] = b"sps";
+/// Prefix of the verification context of a state proof.
+pub const STATE_PROOF_VER_CTX_PREFIX: &[u8] = b"spv";
+
+/// Prefix of the agreement selector, when hashing it for sortition.
+pub const AGREEMENT_SELECTOR_PREFIX: &[u8] = b"AS";
+/// Prefix of a block header, when hashing it into the block hash.
+pub const BLOCK_HEADER_PREFIX: &[u8] = b"BH";
+/// Prefix of an account's balance record.
+pub const BALANCE_RECORD_PREFIX: &[u8] = b"BR";
+/// Prefix of a sortition credential.
+pub const CREDENTIAL_PREFIX: &[u8] = b"CR";
+/// Prefix of the genesis, when hashing it into the genesis hash.
+pub const GENESIS_PREFIX: &[u8] = b"GE";
+/// Prefix of the keys of the Merkle signature scheme, when committing to them.
+pub const KEYS_IN_MSS_PREFIX: &[u8] = b"KP";
+/// Prefix of an inner node of a Merkle tree, when hashing its children into it.
+pub const MERKLE_ARRAY_NODE_PREFIX: &[u8] = b"MA";
+/// Prefix of the leaves padding a vector commitment, which hash this prefix alone.
+pub const MERKLE_VECTOR_COMMITMENT_BOTTOM_LEAF_PREFIX: &[u8] = b"MB";
+/// Prefix of an arbitrary message.
+pub const MESSAGE_PREFIX: &[u8] = b"MX";
+/// Prefix of the parameters of a multisig account, when hashing them into its address.
+pub const MULTISIG_ADDR_PREFIX: &[u8] = b"MultisigAddr";
+/// Prefix of the response to a network priority challenge.
+pub const NET_PRIO_RESPONSE_PREFIX: &[u8] = b"NPR";
+/// Prefixes of the keys of the one-time signature scheme of participation keys.
+pub const ONE_TIME_SIG_KEY1_PREFIX: &[u8] = b"OT1";
+pub const ONE_TIME_SIG_KEY2_PREFIX: &[u8] = b"OT2";
+/// Prefix of a flat payset, when hashing it into the transaction commitment.
+pub const PAYSET_FLAT_PREFIX: &[u8] = b"PF";
+/// Prefix of a block proposal's payload.
+pub const PAYLOAD_PREFIX: &[u8] = b"PL";
+/// Prefix of a program, when signing it for delegation and hashing it into its address.
+pub const PROGRAM_PREFIX: &[u8] = b"Program";
+/// Prefix of data signed for a program's `ed25519verify`, together with the program's address.
+pub const PROGRAM_DATA_PREFIX: &[u8] = b"ProgData";
+/// Prefix of participation keys, when committing to them.
+pub const PARTICIPATION_KEYS_PREFIX: &[u8] = b"PK";
+/// Prefix of the seed a block proposer computes.
+pub const PROPOSER_SEED_PREFIX: &[u8] = b"PS";
+/// Prefix of the seed of a round.
+pub const SEED_PREFIX: &[u8] = b"SD";
+/// Prefix of the names of special addresses, like the fee sink and the rewards pool.
+pub const SPECIAL_ADDR_PREFIX: &[u8] = b"SpecialAddr";
+/// Prefix of a signed transaction in a block, when hashing it for the payset commitment.
+pub const SIGNED_TX_IN_BLOCK_PREFIX: &[u8] = b"STIB";
+/// Prefix of objects hashed in tests.
+pub const TEST_HASHABLE_PREFIX: &[u8] = b"TE";
+/// Prefix of a transaction group, when hashing it into the group ID.
+pub const TX_GROUP_PREFIX: &[u8] = b"TG";
+/// Prefix of a leaf of the Merkle tree of a payset.
+pub const TX_MERKLE_LEAF_PREFIX: &[u8] = b"TL";
+/// Prefix of a transaction, when signing it and hashing it into its ID.
+pub const TX_PREFIX: &[u8] = b"TX";
+/// Prefix of a vote of the agreement protocol.
+pub const VOTE_PREFIX: &[u8] = b"VO";
+
+/// Computes the SHA512/256 hash of the canonical msgpack encoding of `obj`, prefixed by `prefix`.
+pub fn hash_with_prefix(prefix: &[u8], obj: &impl Serialize) -> Digest {
+    let mut hasher = Sha512_256::new();
+    hasher.update(prefix);
+    hasher.update(encoding::to_msgpack(obj));
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_with_prefix() {
+        let expected: Digest = Sha512_256::digest(b"TE\x92\x01\x02").into();
+        assert_eq!(
+            super::hash_with_prefix(TEST_HASHABLE_PREFIX, &[1, 2]),
+            expected
+        );
+    }
+}
@@ -0,0 +1,1134 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Canonical msgpack encoding, as used by Algorand for hashing and signing.
+//!
+//! An encoding is canonical if all map keys are sorted, integers use their smallest
+//! representation, and empty values are omitted. The latter is handled by the types
+//! themselves through `skip_serializing_if = "is_default"` on every optional field.
+//!
+//! Encoding only needs `alloc`, so that e.g. hardware wallets can build the bytes they sign,
+//! while decoding requires the `std` feature.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use alloc::collections::BTreeSet;
+#[cfg(feature = "std")]
+use core::cmp::Ordering;
+
+#[cfg(feature = "std")]
+use data_encoding::BASE64;
+#[cfg(feature = "std")]
+use rmpv::Value;
+#[cfg(feature = "std")]
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum DecodeError {
+    #[error("invalid base64 encoding")]
+    InvalidBase64,
+    #[error("invalid msgpack: {0}")]
+    InvalidMsgpack(String),
+    #[error("invalid value: {0}")]
+    InvalidValue(String),
+    #[error("duplicate map key {0}")]
+    DuplicateKey(String),
+    #[error("non-canonical msgpack: {0}")]
+    NonCanonical(String),
+}
+
+/// Encodes a value into canonical msgpack.
+pub fn to_msgpack<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+    canonical::to_vec(value).expect("types are always serializable")
+}
+
+/// Decodes a value from msgpack.
+///
+/// Fields not known to the target type are ignored, for all types alike.
+/// Maps with duplicate keys are rejected, since decoders might disagree on which value counts.
+#[cfg(feature = "std")]
+pub fn from_msgpack<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, DecodeError> {
+    // Malformed msgpack is left for the typed decoder to report in more detail.
+    if let Ok(value) = rmpv::decode::read_value(&mut &bytes[..]) {
+        check_unique_keys(&value)?;
+    }
+    rmp_serde::from_slice(bytes).map_err(|e| DecodeError::InvalidMsgpack(e.to_string()))
+}
+
+/// Decodes a value from msgpack, requiring it to be canonically encoded, as consensus does.
+///
+/// Besides duplicate keys, this rejects unsorted keys, non-minimal encodings e.g. of integers,
+/// fields that are unknown or have their default value, and trailing bytes. Such encodings
+/// would hash differently than the decoded value, e.g. changing a transaction's ID.
+#[cfg(feature = "std")]
+pub fn from_msgpack_strict<T: Serialize + DeserializeOwned>(
+    bytes: &[u8],
+) -> Result<T, DecodeError> {
+    let decoded = from_msgpack(bytes)?;
+
+    let mut rest = bytes;
+    let value = rmpv::decode::read_value(&mut rest)
+        .map_err(|e| DecodeError::InvalidMsgpack(e.to_string()))?;
+    let non_canonical = |reason: String| Err(DecodeError::NonCanonical(reason));
+    if !rest.is_empty() {
+        return non_canonical(format!("{} trailing bytes", rest.len()));
+    }
+    check_sorted_keys(&value)?;
+    let mut minimal = Vec::with_capacity(bytes.len());
+    rmpv::encode::write_value(&mut minimal, &value).expect("writing to a Vec never fails");
+    if minimal != bytes {
+        return non_canonical("values are not minimally encoded".to_owned());
+    }
+
+    let canonical = to_msgpack(&decoded);
+    if canonical != bytes {
+        let reencoded =
+            rmpv::decode::read_value(&mut &canonical[..]).expect("encoder emits valid msgpack");
+        return match dropped_field(&value, &reencoded) {
+            Some((key, val)) if is_empty(val) => non_canonical(format!("empty field {}", key)),
+            Some((key, _)) => non_canonical(format!("unknown field {}", key)),
+            None => non_canonical("encoding differs from the canonical one".to_owned()),
+        };
+    }
+    Ok(decoded)
+}
+
+/// Decodes a value from msgpack encoded as standard (padded) base64.
+#[cfg(feature = "std")]
+pub fn from_base64_msgpack<T: DeserializeOwned>(s: &str) -> Result<T, DecodeError> {
+    let bytes = BASE64
+        .decode(s.as_bytes())
+        .map_err(|_| DecodeError::InvalidBase64)?;
+    from_msgpack(&bytes)
+}
+
+/// Recursively checks that no map contained in `value` has duplicate keys.
+///
+/// Keys are compared by their canonical encoding, so differently encoded integers are equal.
+#[cfg(feature = "std")]
+fn check_unique_keys(value: &Value) -> Result<(), DecodeError> {
+    match value {
+        Value::Map(entries) => {
+            let mut keys = BTreeSet::new();
+            for (key, val) in entries {
+                let mut encoded = Vec::new();
+                rmpv::encode::write_value(&mut encoded, key).expect("writing to a Vec never fails");
+                if !keys.insert(encoded) {
+                    return Err(DecodeError::DuplicateKey(key.to_string()));
+                }
+                check_unique_keys(key)?;
+                check_unique_keys(val)?;
+            }
+            Ok(())
+        }
+        Value::Array(items) => items.iter().try_for_each(check_unique_keys),
+        _ => Ok(()),
+    }
+}
+
+/// Recursively checks that the keys of all maps contained in `value` are sorted.
+#[cfg(feature = "std")]
+fn check_sorted_keys(value: &Value) -> Result<(), DecodeError> {
+    match value {
+        Value::Map(entries) => {
+            for pair in entries.windows(2) {
+                if compare_keys(&pair[0].0, &pair[1].0) == Ordering::Greater {
+                    return Err(DecodeError::NonCanonical(format!(
+                        "map key {} is not sorted before {}",
+                        pair[0].0, pair[1].0
+                    )));
+                }
+            }
+            entries
+                .iter()
+                .try_for_each(|(_, val)| check_sorted_keys(val))
+        }
+        Value::Array(items) => items.iter().try_for_each(check_sorted_keys),
+        _ => Ok(()),
+    }
+}
+
+/// Finds a map entry of `original` that is missing from its re-encoding `reencoded`,
+/// because the decoder ignored it.
+#[cfg(feature = "std")]
+fn dropped_field<'a>(original: &'a Value, reencoded: &Value) -> Option<(&'a Value, &'a Value)> {
+    match (original, reencoded) {
+        (Value::Map(entries), Value::Map(reentries)) => entries.iter().find_map(|(key, val)| {
+            match reentries.iter().find(|(rekey, _)| rekey == key) {
+                Some((_, reval)) => dropped_field(val, reval),
+                None => Some((key, val)),
+            }
+        }),
+        (Value::Array(items), Value::Array(reitems)) => items
+            .iter()
+            .zip(reitems)
+            .find_map(|(item, reitem)| dropped_field(item, reitem)),
+        _ => None,
+    }
+}
+
+/// Checks whether the value is the encoding of a default value, which canonical encodings omit.
+#[cfg(feature = "std")]
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Nil => true,
+        Value::Boolean(b) => !b,
+        Value::Integer(n) => n.as_u64() == Some(0),
+        Value::String(s) => s.as_bytes().is_empty(),
+        Value::Binary(b) => b.iter().all(|&byte| byte == 0),
+        Value::Array(items) => items.is_empty(),
+        Value::Map(entries) => entries.is_empty(),
+        _ => false,
+    }
+}
+
+/// Orders map keys: strings bytewise, integers numerically.
+#[cfg(feature = "std")]
+fn compare_keys(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.as_bytes().cmp(b.as_bytes()),
+        (Value::Integer(a), Value::Integer(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a.as_u64().cmp(&b.as_u64()),
+        },
+        (Value::Integer(_), _) => Ordering::Less,
+        (_, Value::Integer(_)) => Ordering::Greater,
+        _ => Ordering::Equal,
+    }
+}
+
+/// Serde helper for byte strings, encoding them as base64 in human-readable formats
+/// like JSON, and as msgpack bin otherwise.
+///
+/// Flattened fields are buffered by serde and always claim to be human-readable,
+/// even when decoding msgpack, so both representations are accepted in that case.
+/// The other helpers for byte-like types follow the same rule.
+pub(crate) mod bytes {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    use data_encoding::BASE64;
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::ser::Serializer;
+
+    pub fn serialize<S, T>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: AsRef<[u8]> + ?Sized,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&BASE64.encode(bytes.as_ref()))
+        } else {
+            serializer.serialize_bytes(bytes.as_ref())
+        }
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Vec<u8>>,
+    {
+        let bytes = if deserializer.is_human_readable() {
+            deserializer.deserialize_any(BytesVisitor)?
+        } else {
+            deserializer.deserialize_byte_buf(BytesVisitor)?
+        };
+        let len = bytes.len();
+        T::try_from(bytes)
+            .map_err(|_| de::Error::invalid_length(len, &"a byte string of fixed length"))
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a base64 string or bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<Vec<u8>, E> {
+            BASE64
+                .decode(s.as_bytes())
+                .map_err(|_| E::invalid_value(de::Unexpected::Str(s), &self))
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(bytes.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(bytes)
+        }
+    }
+}
+
+/// Serde helper for ed25519 public keys, encoding them like `bytes` does.
+///
+/// Unlike the serde implementation of `PublicKey`, this checks the length of the key
+/// before parsing it, so malformed keys from untrusted input are reported as such.
+pub(crate) mod public_key {
+    use ed25519_dalek::{PublicKey, PUBLIC_KEY_LENGTH};
+    use serde::de::{self, Deserializer};
+    use serde::ser::Serializer;
+
+    pub fn serialize<S: Serializer>(key: &PublicKey, serializer: S) -> Result<S::Ok, S::Error> {
+        super::bytes::serialize(key.as_bytes(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PublicKey, D::Error> {
+        let bytes: [u8; PUBLIC_KEY_LENGTH] = super::bytes::deserialize(deserializer)?;
+        PublicKey::from_bytes(&bytes).map_err(|_| {
+            de::Error::invalid_value(de::Unexpected::Bytes(&bytes), &"an ed25519 public key")
+        })
+    }
+}
+
+/// Serde helper for lists of byte strings, encoding each element like `bytes` does.
+pub(crate) mod byte_vecs {
+    use alloc::vec::Vec;
+
+    use serde::de::{Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+
+    pub fn serialize<S, T>(items: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: AsRef<[u8]>,
+    {
+        serializer.collect_seq(items.iter().map(|item| Bytes(item.as_ref())))
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Vec<u8>>,
+    {
+        Vec::<ByteBuf<T>>::deserialize(deserializer)
+            .map(|bufs| bufs.into_iter().map(|buf| buf.0).collect())
+    }
+
+    struct Bytes<'a>(&'a [u8]);
+
+    impl Serialize for Bytes<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::bytes::serialize(self.0, serializer)
+        }
+    }
+
+    struct ByteBuf<T>(T);
+
+    impl<'de, T: TryFrom<Vec<u8>>> Deserialize<'de> for ByteBuf<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            super::bytes::deserialize(deserializer).map(ByteBuf)
+        }
+    }
+}
+
+/// Serde helper for byte strings that go-algorand holds in Go strings, like TEAL values.
+///
+/// Go encodes these as msgpack str, even if they are not UTF-8, so both str and bin are accepted.
+/// Serde can only emit UTF-8 as a str, so other byte strings are encoded as bin.
+/// Unlike `bytes`, this does not depend on the format being human-readable,
+/// which flattened fields misreport, as a str could not be told apart from base64.
+pub(crate) mod str_bytes {
+    use alloc::vec::Vec;
+    use core::fmt;
+    use core::str;
+
+    use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        match str::from_utf8(bytes) {
+            Ok(s) => serializer.serialize_str(s),
+            Err(_) => serializer.serialize_bytes(bytes),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserializer.deserialize_any(StrBytesVisitor)
+    }
+
+    struct StrBytesVisitor;
+
+    impl<'de> Visitor<'de> for StrBytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a string or bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<Vec<u8>, E> {
+            Ok(s.as_bytes().to_vec())
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(bytes.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(bytes)
+        }
+
+        /// Bytes that are not UTF-8 end up as an array of integers in JSON.
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes = Vec::new();
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    /// A map key encoded like `str_bytes`, such as the keys of TEAL key/value stores.
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    pub(crate) struct Key(pub Vec<u8>);
+
+    impl Serialize for Key {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize(&self.0, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Key {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize(deserializer).map(Key)
+        }
+    }
+
+    /// Serde helper for maps keyed by byte strings encoded like `str_bytes`.
+    pub(crate) mod keys {
+        use alloc::collections::BTreeMap;
+        use alloc::vec::Vec;
+
+        use serde::de::{Deserialize, Deserializer};
+        use serde::ser::{Serialize, Serializer};
+
+        use super::Key;
+        use crate::encoding::bounded;
+
+        pub fn serialize<S, V>(map: &BTreeMap<Vec<u8>, V>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+            V: Serialize,
+        {
+            serializer.collect_map(map.iter().map(|(key, value)| (KeyRef(key), value)))
+        }
+
+        pub fn deserialize<'de, D, V>(deserializer: D) -> Result<BTreeMap<Vec<u8>, V>, D::Error>
+        where
+            D: Deserializer<'de>,
+            V: Deserialize<'de>,
+        {
+            BTreeMap::<Key, V>::deserialize(deserializer).map(unwrap_keys)
+        }
+
+        /// Deserializes the map like `bounded::map` does.
+        pub fn bounded<'de, D, V, const MAX: usize>(
+            deserializer: D,
+        ) -> Result<BTreeMap<Vec<u8>, V>, D::Error>
+        where
+            D: Deserializer<'de>,
+            V: Deserialize<'de>,
+        {
+            bounded::map::<_, Key, V, MAX>(deserializer).map(unwrap_keys)
+        }
+
+        fn unwrap_keys<V>(map: BTreeMap<Key, V>) -> BTreeMap<Vec<u8>, V> {
+            map.into_iter().map(|(key, value)| (key.0, value)).collect()
+        }
+
+        struct KeyRef<'a>(&'a [u8]);
+
+        impl Serialize for KeyRef<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                super::serialize(self.0, serializer)
+            }
+        }
+    }
+}
+
+/// Serde helper for digests, encoding them as base64 in human-readable formats
+/// like the JSON of the REST APIs, and as msgpack bin otherwise.
+pub(crate) mod digest {
+    use core::fmt;
+
+    use data_encoding::BASE64;
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::ser::Serializer;
+
+    use crate::types::Digest;
+
+    pub fn serialize<S: Serializer>(digest: &Digest, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&BASE64.encode(digest))
+        } else {
+            serializer.serialize_bytes(digest)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Digest, D::Error> {
+        // Accepts both representations if human-readable, see `bytes`.
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(DigestVisitor)
+        } else {
+            deserializer.deserialize_bytes(DigestVisitor)
+        }
+    }
+
+    struct DigestVisitor;
+
+    impl<'de> Visitor<'de> for DigestVisitor {
+        type Value = Digest;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a base64 string or bytes of a 32 byte digest")
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<Digest, E> {
+            let bytes = BASE64
+                .decode(s.as_bytes())
+                .map_err(|_| E::invalid_value(de::Unexpected::Str(s), &self))?;
+            self.visit_bytes(&bytes)
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Digest, E> {
+            bytes
+                .try_into()
+                .map_err(|_| E::invalid_length(bytes.len(), &self))
+        }
+    }
+}
+
+/// Serde helpers for collections with a bounded length, which reject longer ones
+/// from untrusted input based on their declared length, before allocating for them.
+pub(crate) mod bounded {
+    use alloc::collections::BTreeMap;
+    use alloc::vec::Vec;
+    use core::fmt;
+    use core::marker::PhantomData;
+    use core::mem;
+
+    use serde::de::{Deserialize, Deserializer, Error, MapAccess, SeqAccess, Visitor};
+
+    /// Most memory reserved up front for the elements of a sequence, like serde's own `cautious`,
+    /// as its declared length may still be a lie that only shows once the input runs out.
+    const MAX_PREALLOC_BYTES: usize = 1024 * 1024;
+
+    pub fn vec<'de, D, T, const MAX: usize>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        deserializer.deserialize_seq(VecVisitor::<T, MAX>(PhantomData))
+    }
+
+    pub fn map<'de, D, K, V, const MAX: usize>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+    where
+        D: Deserializer<'de>,
+        K: Deserialize<'de> + Ord,
+        V: Deserialize<'de>,
+    {
+        deserializer.deserialize_map(MapVisitor::<K, V, MAX>(PhantomData))
+    }
+
+    struct VecVisitor<T, const MAX: usize>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>, const MAX: usize> Visitor<'de> for VecVisitor<T, MAX> {
+        type Value = Vec<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a sequence of at most {} elements", MAX)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+            let len = seq.size_hint().unwrap_or(0);
+            if len > MAX {
+                return Err(A::Error::invalid_length(len, &self));
+            }
+            let mut items =
+                Vec::with_capacity(len.min(MAX_PREALLOC_BYTES / mem::size_of::<T>().max(1)));
+            while let Some(item) = seq.next_element()? {
+                if items.len() == MAX {
+                    return Err(A::Error::invalid_length(MAX + 1, &self));
+                }
+                items.push(item);
+            }
+            Ok(items)
+        }
+    }
+
+    struct MapVisitor<K, V, const MAX: usize>(PhantomData<(K, V)>);
+
+    impl<'de, K, V, const MAX: usize> Visitor<'de> for MapVisitor<K, V, MAX>
+    where
+        K: Deserialize<'de> + Ord,
+        V: Deserialize<'de>,
+    {
+        type Value = BTreeMap<K, V>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a map of at most {} entries", MAX)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<BTreeMap<K, V>, A::Error> {
+            let len = map.size_hint().unwrap_or(0);
+            if len > MAX {
+                return Err(A::Error::invalid_length(len, &self));
+            }
+            let mut entries = BTreeMap::new();
+            while let Some((key, value)) = map.next_entry()? {
+                if entries.len() == MAX {
+                    return Err(A::Error::invalid_length(MAX + 1, &self));
+                }
+                entries.insert(key, value);
+            }
+            Ok(entries)
+        }
+    }
+}
+
+/// Canonical msgpack encoder, which unlike `rmp_serde` only needs `alloc`.
+///
+/// Values are serialized into a tree first, so that map entries can be sorted before
+/// they are written. The encoding otherwise matches `rmp_serde::to_vec_named`.
+mod canonical {
+    use alloc::borrow::ToOwned;
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::cmp::Ordering;
+    use core::fmt::Display;
+
+    use rmp::encode::{self, ByteBuf};
+    use serde::ser::{self, Serialize};
+    use thiserror::Error;
+
+    #[derive(Debug, Error)]
+    #[error("{0}")]
+    pub struct Error(String);
+
+    impl ser::Error for Error {
+        fn custom<T: Display>(msg: T) -> Self {
+            Error(msg.to_string())
+        }
+    }
+
+    pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+        let mut value = value.serialize(ValueSerializer)?;
+        value.sort_keys();
+        let mut buf = ByteBuf::new();
+        value.write(&mut buf)?;
+        Ok(buf.into_vec())
+    }
+
+    enum Value {
+        Nil,
+        Bool(bool),
+        Uint(u64),
+        /// Only negative integers, so that every integer has one representation.
+        Int(i64),
+        F32(f32),
+        F64(f64),
+        Str(String),
+        Bin(Vec<u8>),
+        Array(Vec<Value>),
+        Map(Vec<(Value, Value)>),
+    }
+
+    impl Value {
+        fn int(n: i64) -> Value {
+            match u64::try_from(n) {
+                Ok(n) => Value::Uint(n),
+                Err(_) => Value::Int(n),
+            }
+        }
+
+        /// Recursively sorts the keys of all maps, in the order of `compare_keys`.
+        fn sort_keys(&mut self) {
+            match self {
+                Value::Map(entries) => {
+                    for (key, val) in entries.iter_mut() {
+                        key.sort_keys();
+                        val.sort_keys();
+                    }
+                    entries.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+                }
+                Value::Array(items) => items.iter_mut().for_each(Value::sort_keys),
+                _ => {}
+            }
+        }
+
+        fn write(&self, buf: &mut ByteBuf) -> Result<(), Error> {
+            // only lengths may not fit the format
+            const INFALLIBLE: &str = "writing to a ByteBuf never fails";
+            let len = |len: usize| u32::try_from(len).map_err(|_| Error("too long".to_owned()));
+            match self {
+                Value::Nil => encode::write_nil(buf).expect(INFALLIBLE),
+                Value::Bool(b) => encode::write_bool(buf, *b).expect(INFALLIBLE),
+                Value::Uint(n) => {
+                    encode::write_uint(buf, *n).expect(INFALLIBLE);
+                }
+                Value::Int(n) => {
+                    encode::write_sint(buf, *n).expect(INFALLIBLE);
+                }
+                Value::F32(f) => encode::write_f32(buf, *f).expect(INFALLIBLE),
+                Value::F64(f) => encode::write_f64(buf, *f).expect(INFALLIBLE),
+                Value::Str(s) => {
+                    len(s.len())?;
+                    encode::write_str(buf, s).expect(INFALLIBLE);
+                }
+                Value::Bin(b) => {
+                    len(b.len())?;
+                    encode::write_bin(buf, b).expect(INFALLIBLE);
+                }
+                Value::Array(items) => {
+                    encode::write_array_len(buf, len(items.len())?).expect(INFALLIBLE);
+                    for item in items {
+                        item.write(buf)?;
+                    }
+                }
+                Value::Map(entries) => {
+                    encode::write_map_len(buf, len(entries.len())?).expect(INFALLIBLE);
+                    for (key, val) in entries {
+                        key.write(buf)?;
+                        val.write(buf)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Orders map keys: strings bytewise, integers numerically and before anything else.
+    fn compare_keys(a: &Value, b: &Value) -> Ordering {
+        match (a, b) {
+            (Value::Str(a), Value::Str(b)) => a.as_bytes().cmp(b.as_bytes()),
+            (Value::Uint(a), Value::Uint(b)) => a.cmp(b),
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Int(_), Value::Uint(_)) => Ordering::Less,
+            (Value::Uint(_), Value::Int(_)) => Ordering::Greater,
+            (Value::Uint(_) | Value::Int(_), _) => Ordering::Less,
+            (_, Value::Uint(_) | Value::Int(_)) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+
+    struct ValueSerializer;
+
+    impl ser::Serializer for ValueSerializer {
+        type Ok = Value;
+        type Error = Error;
+        type SerializeSeq = SeqBuilder;
+        type SerializeTuple = SeqBuilder;
+        type SerializeTupleStruct = SeqBuilder;
+        type SerializeTupleVariant = VariantBuilder<SeqBuilder>;
+        type SerializeMap = MapBuilder;
+        type SerializeStruct = MapBuilder;
+        type SerializeStructVariant = VariantBuilder<MapBuilder>;
+
+        fn is_human_readable(&self) -> bool {
+            false
+        }
+
+        fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+            Ok(Value::Bool(v))
+        }
+
+        fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+            Ok(Value::int(v.into()))
+        }
+
+        fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+            Ok(Value::int(v.into()))
+        }
+
+        fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+            Ok(Value::int(v.into()))
+        }
+
+        fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+            Ok(Value::int(v))
+        }
+
+        fn serialize_i128(self, v: i128) -> Result<Value, Error> {
+            self.serialize_bytes(&v.to_be_bytes())
+        }
+
+        fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+            Ok(Value::Uint(v.into()))
+        }
+
+        fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+            Ok(Value::Uint(v.into()))
+        }
+
+        fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+            Ok(Value::Uint(v.into()))
+        }
+
+        fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+            Ok(Value::Uint(v))
+        }
+
+        fn serialize_u128(self, v: u128) -> Result<Value, Error> {
+            self.serialize_bytes(&v.to_be_bytes())
+        }
+
+        fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+            Ok(Value::F32(v))
+        }
+
+        fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+            Ok(Value::F64(v))
+        }
+
+        fn serialize_char(self, v: char) -> Result<Value, Error> {
+            Ok(Value::Str(v.to_string()))
+        }
+
+        fn serialize_str(self, v: &str) -> Result<Value, Error> {
+            Ok(Value::Str(v.to_owned()))
+        }
+
+        fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+            Ok(Value::Bin(v.to_vec()))
+        }
+
+        fn serialize_none(self) -> Result<Value, Error> {
+            Ok(Value::Nil)
+        }
+
+        fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Error> {
+            value.serialize(self)
+        }
+
+        fn serialize_unit(self) -> Result<Value, Error> {
+            Ok(Value::Nil)
+        }
+
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+            Ok(Value::Array(Vec::new()))
+        }
+
+        fn serialize_unit_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+        ) -> Result<Value, Error> {
+            self.serialize_str(variant)
+        }
+
+        fn serialize_newtype_struct<T: Serialize + ?Sized>(
+            self,
+            _name: &'static str,
+            value: &T,
+        ) -> Result<Value, Error> {
+            value.serialize(self)
+        }
+
+        fn serialize_newtype_variant<T: Serialize + ?Sized>(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+            value: &T,
+        ) -> Result<Value, Error> {
+            Ok(self::variant(variant, value.serialize(self)?))
+        }
+
+        fn serialize_seq(self, len: Option<usize>) -> Result<SeqBuilder, Error> {
+            Ok(SeqBuilder(Vec::with_capacity(len.unwrap_or(0))))
+        }
+
+        fn serialize_tuple(self, len: usize) -> Result<SeqBuilder, Error> {
+            self.serialize_seq(Some(len))
+        }
+
+        fn serialize_tuple_struct(
+            self,
+            _name: &'static str,
+            len: usize,
+        ) -> Result<SeqBuilder, Error> {
+            self.serialize_seq(Some(len))
+        }
+
+        fn serialize_tuple_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+            len: usize,
+        ) -> Result<VariantBuilder<SeqBuilder>, Error> {
+            Ok(VariantBuilder(variant, self.serialize_seq(Some(len))?))
+        }
+
+        fn serialize_map(self, len: Option<usize>) -> Result<MapBuilder, Error> {
+            Ok(MapBuilder {
+                entries: Vec::with_capacity(len.unwrap_or(0)),
+                key: None,
+            })
+        }
+
+        fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapBuilder, Error> {
+            self.serialize_map(Some(len))
+        }
+
+        fn serialize_struct_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+            len: usize,
+        ) -> Result<VariantBuilder<MapBuilder>, Error> {
+            Ok(VariantBuilder(variant, self.serialize_map(Some(len))?))
+        }
+    }
+
+    struct SeqBuilder(Vec<Value>);
+
+    impl ser::SerializeSeq for SeqBuilder {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+            self.0.push(value.serialize(ValueSerializer)?);
+            Ok(())
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            Ok(Value::Array(self.0))
+        }
+    }
+
+    impl ser::SerializeTuple for SeqBuilder {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+            ser::SerializeSeq::serialize_element(self, value)
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            ser::SerializeSeq::end(self)
+        }
+    }
+
+    impl ser::SerializeTupleStruct for SeqBuilder {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+            ser::SerializeSeq::serialize_element(self, value)
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            ser::SerializeSeq::end(self)
+        }
+    }
+
+    struct MapBuilder {
+        entries: Vec<(Value, Value)>,
+        key: Option<Value>,
+    }
+
+    impl ser::SerializeMap for MapBuilder {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+            self.key = Some(key.serialize(ValueSerializer)?);
+            Ok(())
+        }
+
+        fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+            let key = self
+                .key
+                .take()
+                .ok_or_else(|| Error("map value without a key".to_owned()))?;
+            self.entries.push((key, value.serialize(ValueSerializer)?));
+            Ok(())
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            Ok(Value::Map(self.entries))
+        }
+    }
+
+    impl ser::SerializeStruct for MapBuilder {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(
+            &mut self,
+            key: &'static str,
+            value: &T,
+        ) -> Result<(), Error> {
+            ser::SerializeMap::serialize_entry(self, key, value)
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            ser::SerializeMap::end(self)
+        }
+    }
+
+    /// Builds the contents of an enum variant, see `variant`.
+    struct VariantBuilder<B>(&'static str, B);
+
+    /// Wraps the contents of an enum variant in a map from the variant's name to them.
+    fn variant(name: &'static str, value: Value) -> Value {
+        Value::Map(vec![(Value::Str(name.to_owned()), value)])
+    }
+
+    impl ser::SerializeTupleVariant for VariantBuilder<SeqBuilder> {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+            ser::SerializeSeq::serialize_element(&mut self.1, value)
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            let value = ser::SerializeSeq::end(self.1)?;
+            Ok(variant(self.0, value))
+        }
+    }
+
+    impl ser::SerializeStructVariant for VariantBuilder<MapBuilder> {
+        type Ok = Value;
+        type Error = Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(
+            &mut self,
+            key: &'static str,
+            value: &T,
+        ) -> Result<(), Error> {
+            ser::SerializeMap::serialize_entry(&mut self.1, key, value)
+        }
+
+        fn end(self) -> Result<Value, Error> {
+            let value = ser::SerializeMap::end(self.1)?;
+            Ok(variant(self.0, value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn sorted_keys() {
+        let mut map = HashMap::new();
+        map.insert("snd", 1);
+        map.insert("amt", 2);
+        map.insert("fee", 300);
+        let encoded = to_msgpack(&map);
+        // fixmap(3), "amt": 2, "fee": uint16 300, "snd": 1
+        assert_eq!(
+            encoded,
+            [
+                0x83, 0xa3, b'a', b'm', b't', 0x02, 0xa3, b'f', b'e', b'e', 0xcd, 0x01, 0x2c, 0xa3,
+                b's', b'n', b'd', 0x01
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_rmp_serde() {
+        use std::collections::BTreeMap;
+
+        #[derive(Serialize)]
+        struct Unit;
+
+        #[derive(Serialize)]
+        enum Variants {
+            Unit,
+            Newtype(i8),
+            Tuple(u8, bool),
+            Struct { a: Option<u32> },
+        }
+
+        // fields and map keys in canonical order, which `rmp_serde` keeps
+        #[derive(Serialize)]
+        struct Shapes {
+            a: Vec<Variants>,
+            b: (char, f64, Unit),
+            #[serde(with = "serde_bytes")]
+            c: Vec<u8>,
+            d: Option<u64>,
+            e: BTreeMap<i64, &'static str>,
+            f: [i64; 4],
+        }
+        let shapes = Shapes {
+            a: vec![
+                Variants::Unit,
+                Variants::Newtype(-3),
+                Variants::Tuple(200, true),
+                Variants::Struct { a: None },
+            ],
+            b: ('x', 0.5, Unit),
+            c: vec![1, 2, 3],
+            d: Some(u64::MAX),
+            e: BTreeMap::from([(-70_000, "neg"), (0, "zero"), (300, "pos")]),
+            f: [-1, -33, i64::MIN, 127],
+        };
+
+        assert_eq!(
+            to_msgpack(&shapes),
+            rmp_serde::to_vec_named(&shapes).unwrap()
+        );
+    }
+
+    #[test]
+    fn digest_encodings() {
+        #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+        struct Hashes {
+            #[serde(with = "digest")]
+            gh: crate::types::Digest,
+        }
+        let hashes = Hashes { gh: [0xab; 32] };
+
+        let json = serde_json::to_string(&hashes).unwrap();
+        assert_eq!(
+            json,
+            format!("{{\"gh\":\"{}\"}}", BASE64.encode(&[0xab; 32]))
+        );
+        assert_eq!(serde_json::from_str::<Hashes>(&json).unwrap(), hashes);
+
+        let msgpack = to_msgpack(&hashes);
+        // fixmap(1), "gh", bin8 of length 32
+        assert_eq!(msgpack[..6], [0x81, 0xa2, b'g', b'h', 0xc4, 32]);
+        assert_eq!(from_msgpack::<Hashes>(&msgpack), Ok(hashes));
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        // fixmap(2), "a": 1, "a": 2
+        let flat = [0x82, 0xa1, b'a', 0x01, 0xa1, b'a', 0x02];
+        assert_eq!(
+            from_msgpack::<HashMap<String, u8>>(&flat),
+            Err(DecodeError::DuplicateKey("\"a\"".to_owned()))
+        );
+
+        // fixarray(1) of fixmap(2), 1: 1, uint16 1: 2
+        let nested = [0x91, 0x82, 0x01, 0x01, 0xcd, 0x00, 0x01, 0x02];
+        assert_eq!(
+            from_msgpack::<Vec<HashMap<u8, u8>>>(&nested),
+            Err(DecodeError::DuplicateKey("1".to_owned()))
+        );
+
+        // fixmap(2), "a": 1, "b": 1
+        let unique = [0x82, 0xa1, b'a', 0x01, 0xa1, b'b', 0x01];
+        let decoded = from_msgpack::<HashMap<String, u8>>(&unique).unwrap();
+        assert_eq!(decoded.len(), 2);
+    }
+}
@@ -1,6 +1,21 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "alloc"))]
+compile_error!("rs-algorand-sdk requires the `alloc` feature when `std` is disabled");
+
+extern crate alloc;
+
+pub mod abi;
+pub mod account;
+#[cfg(feature = "std")]
+pub mod client;
+pub mod crypto;
+pub mod encoding;
+pub mod merkle;
 pub mod mnemonic;
+pub mod signer;
 pub mod types;
 pub mod util;
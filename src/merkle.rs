@@ -0,0 +1,186 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Verification of Merkle proofs over Algorand's vector commitment.
+//!
+//! In a vector commitment the leaves are laid out in a complete binary tree
+//! whose depth is the length of the proof, with the element at index `i`
+//! stored at the leaf whose position is the bit-reversal of `i`.
+//! Internal nodes are the SHA512_256 hash of `"MA" || left || right`,
+//! and the leaves past the end of the vector hold the hash of `"MB"`.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use sha2::{Digest as _, Sha512_256};
+
+use crate::crypto::{MERKLE_ARRAY_NODE_PREFIX, MERKLE_VECTOR_COMMITMENT_BOTTOM_LEAF_PREFIX};
+use crate::types::{BlockHeader, Digest, SignedTxInBlock};
+
+/// Checks that `tx` is the transaction at `index` of a payset with the Merkle commitment `root`,
+/// without needing the rest of the payset.
+///
+/// As for `Payset::commit`, the transaction must carry its genesis ID and hash.
+/// Transactions in blocks usually omit them, use `verify_tx_proof_in` for those.
+pub fn verify_tx_proof(tx: &SignedTxInBlock, proof: &[Digest], index: u64, root: &Digest) -> bool {
+    let leaf = tx.merkle_leaf(&tx.sig_txad.tx.tx);
+    usize::try_from(index).is_ok_and(|index| verify_tx_inclusion(leaf, proof, index, *root))
+}
+
+/// Checks that `tx` is the transaction at `index` of the payset committed to by the `tx_root`
+/// of `header`, restoring the genesis ID and hash the transaction omits from the header.
+pub fn verify_tx_proof_in(
+    header: &BlockHeader,
+    tx: &SignedTxInBlock,
+    proof: &[Digest],
+    index: u64,
+) -> bool {
+    let leaf = tx.merkle_leaf(&tx.restored_tx(header));
+    usize::try_from(index)
+        .is_ok_and(|index| verify_tx_inclusion(leaf, proof, index, header.tx_root))
+}
+
+/// Checks that `leaf` is the element at `index` of the vector committed to by `root`.
+///
+/// The `proof` lists the sibling hashes from the leaf level up to (but excluding) the root.
+/// Returns false if `index` does not fit into a tree of the proof's depth.
+pub fn verify_tx_inclusion(leaf: Digest, proof: &[Digest], index: usize, root: Digest) -> bool {
+    let depth = proof.len();
+    if depth < usize::BITS as usize && index >> depth != 0 {
+        return false;
+    }
+
+    let mut pos = msb_to_lsb_index(index, depth);
+    let mut node = leaf;
+    for sibling in proof {
+        node = if pos & 1 == 0 {
+            hash_pair(&node, sibling)
+        } else {
+            hash_pair(sibling, &node)
+        };
+        pos >>= 1;
+    }
+
+    node == root
+}
+
+/// Computes the root of the vector commitment to `elems`.
+///
+/// The leaves are padded with the bottom leaf up to the next power of two,
+/// and the commitment to an empty vector is the zero digest.
+pub(crate) fn vector_commitment(elems: &[Digest]) -> Digest {
+    if elems.is_empty() {
+        return Digest::default();
+    }
+    build_layers(elems).last().unwrap()[0]
+}
+
+/// Builds all layers of a vector commitment tree, bottom layer first.
+fn build_layers(elems: &[Digest]) -> Vec<Vec<Digest>> {
+    let depth = elems.len().next_power_of_two().trailing_zeros() as usize;
+    let mut leaves = vec![bottom_leaf(); 1 << depth];
+    for (i, elem) in elems.iter().enumerate() {
+        leaves[msb_to_lsb_index(i, depth)] = *elem;
+    }
+
+    let mut layers = vec![leaves];
+    while layers.last().unwrap().len() > 1 {
+        let next = layers
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+        layers.push(next);
+    }
+    layers
+}
+
+/// Computes the leaf padding a vector commitment, the hash of an empty element.
+pub(crate) fn bottom_leaf() -> Digest {
+    Sha512_256::digest(MERKLE_VECTOR_COMMITMENT_BOTTOM_LEAF_PREFIX).into()
+}
+
+/// Hashes two child nodes into their parent node.
+fn hash_pair(left: &Digest, right: &Digest) -> Digest {
+    let mut hasher = Sha512_256::new();
+    hasher.update(MERKLE_ARRAY_NODE_PREFIX);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Reverses the lowest `depth` bits of `index`,
+/// mapping a vector index to its leaf position in the tree.
+fn msb_to_lsb_index(index: usize, depth: usize) -> usize {
+    if depth == 0 {
+        return index;
+    }
+    index.reverse_bits() >> (usize::BITS as usize - depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prove(layers: &[Vec<Digest>], index: usize) -> Vec<Digest> {
+        let depth = layers.len() - 1;
+        let mut pos = msb_to_lsb_index(index, depth);
+        let mut proof = Vec::new();
+        for layer in &layers[..depth] {
+            proof.push(layer[pos ^ 1]);
+            pos >>= 1;
+        }
+        proof
+    }
+
+    fn elems(n: u8) -> Vec<Digest> {
+        (0..n).map(|i| Sha512_256::digest([i]).into()).collect()
+    }
+
+    #[test]
+    fn valid_proofs() {
+        for n in 1..=9 {
+            let elems = elems(n);
+            let layers = build_layers(&elems);
+            let root = layers.last().unwrap()[0];
+            for (i, elem) in elems.iter().enumerate() {
+                let proof = prove(&layers, i);
+                assert!(verify_tx_inclusion(*elem, &proof, i, root));
+            }
+        }
+    }
+
+    #[test]
+    fn padding() {
+        let elems = elems(3);
+        let bottom: Digest = Sha512_256::digest(b"MB").into();
+        let left = hash_pair(&elems[0], &elems[2]);
+        let right = hash_pair(&elems[1], &bottom);
+        assert_eq!(vector_commitment(&elems), hash_pair(&left, &right));
+        assert_eq!(vector_commitment(&elems[..1]), elems[0]);
+        assert_eq!(vector_commitment(&[]), Digest::default());
+    }
+
+    #[test]
+    fn tampered_sibling() {
+        let elems = elems(5);
+        let layers = build_layers(&elems);
+        let root = layers.last().unwrap()[0];
+
+        let mut proof = prove(&layers, 3);
+        proof[1][0] ^= 1;
+        assert!(!verify_tx_inclusion(elems[3], &proof, 3, root));
+    }
+
+    #[test]
+    fn wrong_index() {
+        let elems = elems(4);
+        let layers = build_layers(&elems);
+        let root = layers.last().unwrap()[0];
+
+        let proof = prove(&layers, 1);
+        assert!(!verify_tx_inclusion(elems[1], &proof, 2, root));
+        assert!(!verify_tx_inclusion(elems[1], &proof, 5, root));
+    }
+}
@@ -3,68 +3,66 @@
 
 //! An implementation of BIP39 mnemonic seed phrases.
 
-use std::convert::TryInto;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryInto;
 
 use lazy_static::lazy_static;
 use sha2::{Digest, Sha512_256};
+use thiserror::Error;
 
 const BITS_PER_WORD: usize = 11;
 const MNEMONIC_LEN_WORDS: usize = 25;
 const KEY_LEN_BYTES: usize = 32;
 
+/// The 2048 words of the BIP39 English word list, one per line.
+const WORDLIST: &str = include_str!("../data/mnemonics_wordlist.txt");
+
 lazy_static! {
-    pub static ref WORDS: Vec<String> = load_mnemonic_file();
+    pub static ref WORDS: Vec<&'static str> = load_wordlist();
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum MnemonicError {
+    #[error("mnemonic must be {MNEMONIC_LEN_WORDS} words long but was {0}")]
     WrongMnemonicLen(usize),
+    #[error("invalid mnemonic: {0} is not in the word list")]
     InvalidWordInMnemonic(String),
+    #[error("failed to validate checksum")]
     WrongChecksum,
 }
 
-impl std::fmt::Display for MnemonicError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            Self::WrongMnemonicLen(len) => {
-                write!(
-                    f,
-                    "mnemonic must be {} words long but was {}",
-                    MNEMONIC_LEN_WORDS, len
-                )
-            }
-            Self::InvalidWordInMnemonic(word) => {
-                write!(f, "invalid mnemonic: {} is not in the word list", word)
-            }
-            Self::WrongChecksum => write!(f, "failed to validate checksum"),
-        }
-    }
+/// Converts a key into a mnemonic phrase of 25 space separated words.
+pub fn from_key(key: &[u8; KEY_LEN_BYTES]) -> String {
+    key_to_mnemonic(*key).join(" ")
 }
 
 /// Converts a key into a mnemonic word list.
 pub fn key_to_mnemonic(key: [u8; KEY_LEN_BYTES]) -> Vec<String> {
     let mut mnemonic: Vec<String> = to_base11(&key)
         .iter()
-        .map(|i| WORDS[*i as usize].clone())
+        .map(|i| WORDS[*i as usize].to_owned())
         .collect();
 
     let chk = checksum(&key);
-    mnemonic.push(WORDS[chk as usize].clone());
+    mnemonic.push(WORDS[chk as usize].to_owned());
     mnemonic
 }
 
 /// Converts a mnemonic phrase (whitespace separated string of words) into a key.
-pub fn phrase_to_key(phrase: &str) -> Result<[u8; KEY_LEN_BYTES], MnemonicError> {
-    let words: Vec<String> = phrase
-        .split_whitespace()
-        .into_iter()
-        .map(|s| s.to_owned())
-        .collect();
+/// Fails under the same conditions as `mnemonic_to_key`.
+pub fn to_key(phrase: &str) -> Result<[u8; KEY_LEN_BYTES], MnemonicError> {
+    let words: Vec<String> = phrase.split_whitespace().map(|s| s.to_owned()).collect();
     mnemonic_to_key(&words)
 }
 
+/// Converts a mnemonic phrase (whitespace separated string of words) into a key.
+#[deprecated(note = "renamed to `to_key`")]
+pub fn phrase_to_key(phrase: &str) -> Result<[u8; KEY_LEN_BYTES], MnemonicError> {
+    to_key(phrase)
+}
+
 /// Converts a mnemonic word list into a key.
 /// Returns an error if the mnemonic:
 ///   - has wrong length, or
@@ -99,7 +97,6 @@ pub fn mnemonic_to_key(mnemonic: &[String]) -> Result<[u8; KEY_LEN_BYTES], Mnemo
     }
 
     if bytes[KEY_LEN_BYTES] != 0 {
-        println!("bytes[KEY_LEN_BYTES] = {} != 0", bytes[KEY_LEN_BYTES]);
         return Err(MnemonicError::WrongChecksum);
     }
 
@@ -113,16 +110,8 @@ pub fn mnemonic_to_key(mnemonic: &[String]) -> Result<[u8; KEY_LEN_BYTES], Mnemo
     }
 }
 
-fn load_mnemonic_file() -> Vec<String> {
-    let mut wordlist = Vec::new();
-    let f = File::open("./data/mnemonics_wordlist.txt").unwrap();
-
-    for word in BufReader::new(f).lines() {
-        if word.is_err() {
-            continue;
-        }
-        wordlist.push(word.unwrap().clone());
-    }
+fn load_wordlist() -> Vec<&'static str> {
+    let wordlist: Vec<&str> = WORDLIST.lines().collect();
 
     if wordlist.len() != (1 << BITS_PER_WORD) {
         panic!(
@@ -207,6 +196,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn known_phrase() {
+        const PHRASE: &str = "advice pudding treat near rule blouse same whisper inner electric quit surface sunny dismiss leader blood seat clown cost exist hospital century reform able sponsor";
+        let key = to_key(PHRASE).unwrap();
+        assert_eq!(from_key(&key), PHRASE);
+
+        let corrupted = PHRASE.replace("sponsor", "abandon");
+        assert_eq!(to_key(&corrupted), Err(MnemonicError::WrongChecksum));
+
+        let unknown = PHRASE.replace("pudding", "puddin");
+        assert_eq!(
+            to_key(&unknown),
+            Err(MnemonicError::InvalidWordInMnemonic("puddin".to_owned()))
+        );
+
+        let short = PHRASE.rsplit_once(' ').unwrap().0;
+        assert_eq!(to_key(short), Err(MnemonicError::WrongMnemonicLen(24)));
+
+        #[allow(deprecated)]
+        let old_key = phrase_to_key(PHRASE).unwrap();
+        assert_eq!(old_key, key);
+    }
+
     #[test]
     fn generate_and_recover() {
         let mut rng = thread_rng();
@@ -223,6 +235,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::manual_repeat_n)] // `repeat_n` needs Rust 1.82
     fn wrong_mnemonic_length() {
         const BAD_LENGTHS: [usize; 4] = [0, MNEMONIC_LEN_WORDS - 1, MNEMONIC_LEN_WORDS + 1, 1000];
 
@@ -259,11 +272,11 @@ mod tests {
         let real_checksum_word = mnemonic.last().unwrap().clone();
 
         for word in WORDS.iter() {
-            if word == &real_checksum_word {
+            if *word == real_checksum_word {
                 continue;
             }
 
-            *mnemonic.last_mut().unwrap() = word.clone();
+            *mnemonic.last_mut().unwrap() = word.to_string();
 
             assert_eq!(
                 mnemonic_to_key(&mnemonic),
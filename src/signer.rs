@@ -0,0 +1,348 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! A common interface for everything that can authorize transactions.
+
+use alloc::vec::Vec;
+
+use thiserror::Error;
+
+use crate::account::Account;
+use crate::types::{
+    assign_group_ids, Address, GroupError, LogicSig, MsigError, MultisigSignature, Signature,
+    SignedTx, Transaction,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum SignError {
+    #[error("key is not part of the multisig")]
+    KeyNotInMultisig,
+    #[error("no signer for sender {0}")]
+    MissingSigner(Address),
+    #[error("invalid multisig: {0}")]
+    InvalidMultisig(#[from] MsigError),
+}
+
+/// Something that can produce a `SignedTx` authorizing a transaction.
+pub trait Signer {
+    /// Signs the transaction on behalf of the account at `self.address()`.
+    fn sign_transaction(&self, tx: &Transaction) -> Result<SignedTx, SignError>;
+
+    /// Returns the address of the account this signer authorizes transactions for.
+    fn address(&self) -> Address;
+}
+
+/// Signs transactions for a multisig account with some of its keys.
+pub struct MultisigSigner {
+    msig: MultisigSignature,
+    accounts: Vec<Account>,
+}
+
+impl MultisigSigner {
+    /// Creates a signer for the multisig account described by `msig`,
+    /// which signs with the keys of all `accounts`.
+    pub fn new(msig: MultisigSignature, accounts: Vec<Account>) -> Result<Self, SignError> {
+        msig.validate()?;
+        for account in &accounts {
+            if !msig.subsigs.iter().any(|s| s.key == account.public_key()) {
+                return Err(SignError::KeyNotInMultisig);
+            }
+        }
+        Ok(Self { msig, accounts })
+    }
+}
+
+impl Signer for MultisigSigner {
+    fn sign_transaction(&self, tx: &Transaction) -> Result<SignedTx, SignError> {
+        let mut msig = self.msig.clone();
+        for account in &self.accounts {
+            msig.sign(account.keypair(), tx)?;
+        }
+
+        Ok(signed_tx(
+            tx,
+            self.address(),
+            Default::default(),
+            Some(msig),
+            None,
+        ))
+    }
+
+    fn address(&self) -> Address {
+        self.msig.address()
+    }
+}
+
+/// Authorizes transactions with a logic signature.
+pub struct LogicSigSigner {
+    lsig: LogicSig,
+    address: Address,
+}
+
+impl LogicSigSigner {
+    /// Creates a signer from a logic signature, which authorizes transactions for `address`.
+    /// This is either the contract account's address (see `LogicSig::address`)
+    /// or the account that delegated to the logic.
+    pub fn new(lsig: LogicSig, address: Address) -> Self {
+        Self { lsig, address }
+    }
+}
+
+impl Signer for LogicSigSigner {
+    fn sign_transaction(&self, tx: &Transaction) -> Result<SignedTx, SignError> {
+        Ok(signed_tx(
+            tx,
+            self.address,
+            Default::default(),
+            None,
+            Some(self.lsig.clone()),
+        ))
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+}
+
+/// Signs each transaction with the signer whose address matches the transaction's sender.
+pub fn sign_group(
+    txs: &[Transaction],
+    signers: &[&dyn Signer],
+) -> Result<Vec<SignedTx>, SignError> {
+    txs.iter()
+        .map(|tx| {
+            let sender = tx.header.sender;
+            signers
+                .iter()
+                .find(|s| s.address() == sender)
+                .ok_or(SignError::MissingSigner(sender))?
+                .sign_transaction(tx)
+        })
+        .collect()
+}
+
+/// Assigns the group ID to the transactions and signs them with `signer`,
+/// returning the group ready to be submitted atomically.
+///
+/// All transactions must be sent by the signer's account.
+pub fn sign_transaction_group(
+    mut txns: Vec<Transaction>,
+    signer: &Account,
+) -> Result<Vec<SignedTx>, GroupError> {
+    if let Some(tx) = txns.iter().find(|tx| tx.header.sender != signer.address()) {
+        return Err(GroupError::MissingSigner(tx.header.sender));
+    }
+    assign_group_ids(&mut txns)?;
+    Ok(txns.iter().map(|tx| signer.sign_transaction(tx)).collect())
+}
+
+/// Assembles a `SignedTx`, recording `signer` as the authorizing address if it is not the sender.
+pub(crate) fn signed_tx(
+    tx: &Transaction,
+    signer: Address,
+    sig: Signature,
+    msig: Option<MultisigSignature>,
+    lsig: Option<LogicSig>,
+) -> SignedTx {
+    SignedTx {
+        sig,
+        msig,
+        lsig,
+        tx: tx.clone(),
+        auth_addr: if tx.header.sender == signer {
+            Address::default()
+        } else {
+            signer
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ed25519_dalek::{Signer as _, Verifier};
+
+    use crate::types::{compute_group_id, Header, MicroAlgos, PaymentFields, Round, TxFields};
+
+    fn payment(sender: Address) -> Transaction {
+        Transaction {
+            header: Header {
+                sender,
+                fee: MicroAlgos(1000),
+                first_valid: Round(1),
+                last_valid: Round(1001),
+                ..Default::default()
+            },
+            fields: TxFields::Payment(PaymentFields {
+                receiver: Address([7; 32]),
+                amount: MicroAlgos(5),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn account_signer() {
+        let account = Account::from_seed(&[1; 32]);
+        let tx = payment(account.address());
+        let stx = Signer::sign_transaction(&account, &tx).unwrap();
+
+        assert!(stx.msig.is_none() && stx.lsig.is_none());
+        assert!(stx.auth_addr.is_zero());
+        assert!(account
+            .public_key()
+            .verify(&tx.bytes_to_sign(), &stx.sig.0)
+            .is_ok());
+    }
+
+    #[test]
+    fn rekeyed_account_signer() {
+        let account = Account::from_seed(&[1; 32]);
+        let tx = payment(Address([9; 32]));
+        let stx = Signer::sign_transaction(&account, &tx).unwrap();
+        assert_eq!(stx.auth_addr, account.address());
+    }
+
+    #[test]
+    fn multisig_signer() {
+        let accounts: Vec<Account> = (1..=3).map(|i| Account::from_seed(&[i; 32])).collect();
+        let keys: Vec<_> = accounts.iter().map(|a| a.public_key()).collect();
+        let msig = MultisigSignature::new(1, 2, &keys);
+        let signer = MultisigSigner::new(
+            msig.clone(),
+            vec![Account::from_seed(&[1; 32]), Account::from_seed(&[3; 32])],
+        )
+        .unwrap();
+        assert_eq!(signer.address(), msig.address());
+
+        let tx = payment(signer.address());
+        let stx = signer.sign_transaction(&tx).unwrap();
+        assert!(stx.auth_addr.is_zero());
+
+        let subsigs = stx.msig.unwrap().subsigs;
+        assert!(subsigs[1].sig.is_none());
+        for i in [0, 2] {
+            let sig = subsigs[i].sig.unwrap();
+            assert!(subsigs[i].key.verify(&tx.bytes_to_sign(), &sig.0).is_ok());
+        }
+    }
+
+    #[test]
+    fn multisig_signer_foreign_key() {
+        let msig = MultisigSignature::new(1, 1, &[Account::from_seed(&[1; 32]).public_key()]);
+        let result = MultisigSigner::new(msig, vec![Account::from_seed(&[2; 32])]);
+        assert!(matches!(result, Err(SignError::KeyNotInMultisig)));
+    }
+
+    #[test]
+    fn multisig_signer_malformed() {
+        let account = Account::from_seed(&[1; 32]);
+        let msig = MultisigSignature::new(1, 2, &[account.public_key()]);
+        let result = MultisigSigner::new(msig, vec![account]);
+        assert!(matches!(
+            result,
+            Err(SignError::InvalidMultisig(
+                MsigError::InvalidThreshold { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn logic_sig_signer() {
+        let account = Account::from_seed(&[1; 32]);
+        let mut lsig = LogicSig {
+            logic: vec![0x01, 0x20, 0x01, 0x01, 0x22],
+            sig: Signature::default(),
+            msig: Default::default(),
+            args: Vec::new(),
+        };
+        lsig.sig = Signature(account.keypair().sign(&lsig.bytes_to_sign()));
+        let signer = LogicSigSigner::new(lsig.clone(), account.address());
+
+        let stx = signer
+            .sign_transaction(&payment(account.address()))
+            .unwrap();
+        assert_eq!(stx.sig, Signature::default());
+        assert!(stx.auth_addr.is_zero());
+        assert!(stx.lsig.as_ref() == Some(&lsig));
+        assert_eq!(stx.verify(), Ok(()));
+    }
+
+    #[test]
+    fn group_signer_dispatch() {
+        let alice = Account::from_seed(&[1; 32]);
+        let bob = Account::from_seed(&[2; 32]);
+        let txs = vec![payment(bob.address()), payment(alice.address())];
+
+        let stxs = sign_group(&txs, &[&alice, &bob]).unwrap();
+        assert!(bob
+            .public_key()
+            .verify(&txs[0].bytes_to_sign(), &stxs[0].sig.0)
+            .is_ok());
+        assert!(alice
+            .public_key()
+            .verify(&txs[1].bytes_to_sign(), &stxs[1].sig.0)
+            .is_ok());
+
+        let result = sign_group(&txs, &[&alice]);
+        assert!(matches!(result, Err(SignError::MissingSigner(a)) if a == bob.address()));
+    }
+
+    /// Stands in for a hardware wallet, which only hands out raw signature bytes.
+    struct ExternalSigner(ed25519_dalek::Keypair);
+
+    impl Signer for ExternalSigner {
+        fn sign_transaction(&self, tx: &Transaction) -> Result<SignedTx, SignError> {
+            let raw: [u8; 64] = self.0.sign(&tx.bytes_to_sign()).to_bytes();
+            Ok(SignedTx {
+                sig: Signature::from_bytes(&raw).unwrap(),
+                msig: None,
+                lsig: None,
+                tx: tx.clone(),
+                auth_addr: Address::default(),
+            })
+        }
+
+        fn address(&self) -> Address {
+            Address::from(self.0.public)
+        }
+    }
+
+    #[test]
+    fn external_signer() {
+        let keypair = Account::from_seed(&[3; 32]).keypair().to_bytes();
+        let signer = ExternalSigner(ed25519_dalek::Keypair::from_bytes(&keypair).unwrap());
+        let alice = Account::from_seed(&[1; 32]);
+        let txs = vec![payment(signer.address()), payment(alice.address())];
+
+        let stxs = sign_group(&txs, &[&alice, &signer]).unwrap();
+        assert_eq!(stxs[0].verify(), Ok(()));
+        assert_eq!(stxs[1].verify(), Ok(()));
+        assert_eq!(
+            Signature::from_bytes(&stxs[0].sig.to_bytes()).unwrap(),
+            stxs[0].sig
+        );
+    }
+
+    #[test]
+    fn sign_transaction_group() {
+        let alice = Account::from_seed(&[1; 32]);
+        let txs = vec![payment(alice.address()), payment(alice.address())];
+        let group = compute_group_id(&txs).unwrap();
+
+        let stxs = super::sign_transaction_group(txs, &alice).unwrap();
+        assert_eq!(stxs.len(), 2);
+        for stx in &stxs {
+            assert_eq!(stx.tx.header.group, group);
+            assert_eq!(stx.verify(), Ok(()));
+        }
+
+        let bob = Account::from_seed(&[2; 32]);
+        let txs = vec![payment(alice.address()), payment(bob.address())];
+        let result = super::sign_transaction_group(txs, &alice);
+        assert!(matches!(result, Err(GroupError::MissingSigner(a)) if a == bob.address()));
+        let result = super::sign_transaction_group(Vec::new(), &alice);
+        assert!(matches!(result, Err(GroupError::EmptyGroup)));
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use super::*;
+use crate::encoding::{digest, str_bytes};
+use crate::util::is_default;
+
+/// The balance record of an account: its Algos, asset holdings, and application state.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountData {
+    #[serde(rename = "onl", default, skip_serializing_if = "is_default")]
+    pub status: AccountStatus,
+    #[serde(rename = "algo", default, skip_serializing_if = "is_default")]
+    pub micro_algos: MicroAlgos,
+
+    /// Participation keys registered by the account, used while it is online.
+    #[serde(
+        rename = "vote",
+        default,
+        skip_serializing_if = "is_default",
+        with = "digest"
+    )]
+    pub vote_pk: [u8; 32],
+    #[serde(
+        rename = "sel",
+        default,
+        skip_serializing_if = "is_default",
+        with = "digest"
+    )]
+    pub selection_pk: [u8; 32],
+    #[serde(rename = "voteFst", default, skip_serializing_if = "is_default")]
+    pub vote_first: Round,
+    #[serde(rename = "voteLst", default, skip_serializing_if = "is_default")]
+    pub vote_last: Round,
+    #[serde(rename = "voteKD", default, skip_serializing_if = "is_default")]
+    pub vote_key_dilution: u64,
+
+    /// Assets held by this account, including ones it created.
+    #[serde(rename = "asset", default, skip_serializing_if = "is_default")]
+    pub assets: BTreeMap<AssetIndex, AssetHolding>,
+
+    /// Local state of the applications this account opted into.
+    #[serde(rename = "appl", default, skip_serializing_if = "is_default")]
+    pub app_local_states: BTreeMap<AppIndex, AppLocalState>,
+
+    /// Parameters and global state of the applications created by this account.
+    #[serde(rename = "appp", default, skip_serializing_if = "is_default")]
+    pub app_params: BTreeMap<AppIndex, AppParams>,
+}
+
+/// Whether an account participates in consensus.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum AccountStatus {
+    /// Registered no participation keys, or took them offline. Earns rewards.
+    #[default]
+    Offline = 0,
+    /// Participates in consensus with its registered keys. Earns rewards.
+    Online = 1,
+    /// Permanently neither participates nor earns rewards.
+    NotParticipating = 2,
+}
+
+/// An account's balance of a single asset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetHolding {
+    #[serde(rename = "a", default, skip_serializing_if = "is_default")]
+    pub amount: u64,
+    #[serde(rename = "f", default, skip_serializing_if = "is_default")]
+    pub frozen: bool,
+}
+
+/// The local state an account stores for an application it opted into.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppLocalState {
+    #[serde(rename = "hsch", default, skip_serializing_if = "is_default")]
+    pub schema: StateSchema,
+    #[serde(
+        rename = "tkv",
+        default,
+        skip_serializing_if = "is_default",
+        with = "str_bytes::keys"
+    )]
+    pub key_value: TealKeyValue,
+}
+
+/// The parameters of an application, stored in its creator's account.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppParams {
+    #[serde(
+        rename = "approv",
+        default,
+        skip_serializing_if = "is_default",
+        with = "serde_bytes"
+    )]
+    pub approval_program: Vec<u8>,
+    #[serde(
+        rename = "clearp",
+        default,
+        skip_serializing_if = "is_default",
+        with = "serde_bytes"
+    )]
+    pub clear_state_program: Vec<u8>,
+    #[serde(
+        rename = "gs",
+        default,
+        skip_serializing_if = "is_default",
+        with = "str_bytes::keys"
+    )]
+    pub global_state: TealKeyValue,
+    #[serde(rename = "lsch", default, skip_serializing_if = "is_default")]
+    pub local_state_schema: StateSchema,
+    #[serde(rename = "gsch", default, skip_serializing_if = "is_default")]
+    pub global_state_schema: StateSchema,
+
+    /// Number of pages the programs may use beyond the first.
+    #[serde(rename = "epp", default, skip_serializing_if = "is_default")]
+    pub extra_program_pages: u32,
+}
+
+/// A TEAL key/value store, whose keys are arbitrary bytes.
+pub type TealKeyValue = BTreeMap<Vec<u8>, TealValue>;
+
+/// A value in a TEAL key/value store, which is either a byte slice or an integer.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TealValue {
+    #[serde(rename = "tt", default, skip_serializing_if = "is_default")]
+    pub teal_type: TealType,
+    #[serde(
+        rename = "tb",
+        default,
+        skip_serializing_if = "is_default",
+        with = "str_bytes"
+    )]
+    pub bytes: Vec<u8>,
+    #[serde(rename = "ui", default, skip_serializing_if = "is_default")]
+    pub uint: u64,
+}
+
+/// The type of a `TealValue`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum TealType {
+    #[default]
+    Bytes = 1,
+    Uint = 2,
+}
@@ -1,12 +1,17 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
-use std::fmt;
-use std::str::FromStr;
-
-use data_encoding::BASE32_NOPAD;
-use serde::{Deserialize, Serialize};
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use data_encoding::{BASE32_NOPAD, HEXLOWER, HEXLOWER_PERMISSIVE};
+use ed25519_dalek::PublicKey;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use sha2::{Digest, Sha512_256};
+use subtle::{Choice, ConstantTimeEq};
 use thiserror::Error;
 
 const CHECKSUM_LEN: usize = 4;
@@ -16,29 +21,72 @@ const HASH_LEN: usize = 32;
 pub enum AddressError {
     #[error("invalid base32 encoding")]
     InvalidBase32,
+    #[error("invalid hex encoding")]
+    InvalidHex,
     #[error("wrong length for address")]
     WrongLength,
     #[error("invalid checksum")]
     InvalidChecksum,
+    #[error("address is not in canonical form")]
+    NonCanonical,
 }
 
-#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+/// The address of an account, the 32 bytes of its public key or the hash of its logic.
+///
+/// Serialized as the checksummed base32 string in human-readable formats like JSON,
+/// and as raw bytes otherwise.
+///
+/// Addresses are ordered by their bytes, e.g. for use as keys of sorted maps.
+///
+/// Comparisons against addresses derived from untrusted input while verifying signatures
+/// (authorizing addresses, multisig and logic sig addresses, and checksums) use
+/// `ConstantTimeEq`, so their timing does not reveal how many leading bytes matched.
+#[derive(Clone, Copy, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Address(pub [u8; HASH_LEN]);
 
 impl Address {
     /// Returns the checksum as Vec<u8>.
     /// Checksum in Algorand are the last 4 bytes of the shortAddress Hash. H(Address)[28..]
     fn checksum(&self) -> Vec<u8> {
-        let short_addr_hash = Sha512_256::digest(&self.0);
+        let short_addr_hash = Sha512_256::digest(self.0);
         short_addr_hash[short_addr_hash.len() - CHECKSUM_LEN..].to_vec()
     }
 
+    /// Parses an address from the hex encoding of its 32 bytes, in either case.
+    pub fn from_hex(hex: &str) -> Result<Self, AddressError> {
+        let bytes = HEXLOWER_PERMISSIVE
+            .decode(hex.as_bytes())
+            .map_err(|_| AddressError::InvalidHex)?;
+        bytes
+            .try_into()
+            .map(Address)
+            .map_err(|_| AddressError::WrongLength)
+    }
+
+    /// Returns the lowercase hex encoding of the address's 32 bytes, without checksum.
+    pub fn to_hex(&self) -> String {
+        HEXLOWER.encode(&self.0)
+    }
+
     /// Checks if an address is the zero value.
     pub fn is_zero(&self) -> bool {
         *self == Address([0; 32])
     }
 }
 
+impl ConstantTimeEq for Address {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl From<PublicKey> for Address {
+    /// Returns the address of the account controlled by the given key.
+    fn from(key: PublicKey) -> Self {
+        Address(key.to_bytes())
+    }
+}
+
 impl fmt::Display for Address {
     /// Returns a string representation of Address
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -49,6 +97,13 @@ impl fmt::Display for Address {
     }
 }
 
+/// Formats the address in its string representation, e.g. `Address(AMBQ...)`.
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Address({})", self)
+    }
+}
+
 impl FromStr for Address {
     type Err = AddressError;
 
@@ -64,28 +119,68 @@ impl FromStr for Address {
         };
 
         let mut short = Address([0; 32]);
-        if decoded.len() < short.0.len() {
+        if decoded.len() != HASH_LEN + CHECKSUM_LEN {
             return Err(AddressError::WrongLength);
         }
 
         short.0[..].copy_from_slice(&decoded[..32]);
         let incoming_checksum = &decoded[decoded.len() - CHECKSUM_LEN..];
         let calculated_checksum = short.checksum();
-        let is_valid = incoming_checksum == calculated_checksum;
-
-        if !is_valid {
+        if !bool::from(incoming_checksum.ct_eq(&calculated_checksum[..])) {
             return Err(AddressError::InvalidChecksum);
         }
 
         // Validate that we had a canonical string representation
         if short.to_string() != addr {
-            unreachable!();
+            return Err(AddressError::NonCanonical);
         }
 
         Ok(short)
     }
 }
 
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Accepts both representations if human-readable, see `encoding::bytes`.
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(AddressVisitor)
+        } else {
+            deserializer.deserialize_bytes(AddressVisitor)
+        }
+    }
+}
+
+struct AddressVisitor;
+
+impl<'de> de::Visitor<'de> for AddressVisitor {
+    type Value = Address;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a base32 address string or 32 bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Address, E> {
+        Address::from_str(s).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Address, E> {
+        bytes
+            .try_into()
+            .map(Address)
+            .map_err(|_| E::invalid_length(bytes.len(), &self))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -164,6 +259,14 @@ mod tests {
         assert_eq!(result, Err(AddressError::InvalidBase32));
     }
 
+    #[test]
+    fn from_public_key() {
+        let addr = Address::from_str("J5YDZLPOHWB5O6MVRHNFGY4JXIQAYYM6NUJWPBSYBBIXH5ENQ4Z5LTJELU")
+            .unwrap();
+        let key = PublicKey::from_bytes(&addr.0).unwrap();
+        assert_eq!(Address::from(key), addr);
+    }
+
     #[test]
     fn human_readable() {
         let s = "J5YDZLPOHWB5O6MVRHNFGY4JXIQAYYM6NUJWPBSYBBIXH5ENQ4Z5LTJELU";
@@ -176,10 +279,118 @@ mod tests {
         let addr = "J5YDZLPOHWB5O6MVRHNFGY4JXIQAYYM6NUJWPBSYBBIXH5ENQ4Z5LTJELU";
         let non_canonical = "J5YDZLPOHWB5O6MVRHNFGY4JXIQAYYM6NUJWPBSYBBIXH5ENQ4Z5LTJELV";
 
-        assert_eq!(Address::from_str(addr).is_ok(), true);
+        assert!(Address::from_str(addr).is_ok());
         assert_eq!(
             Address::from_str(non_canonical),
             Err(AddressError::InvalidBase32)
         );
     }
+
+    #[test]
+    fn trailing_bytes() {
+        // valid checksum, but with extra bytes between address and checksum
+        let addr = Address(Sha512_256::digest(b"randomString").into());
+        let mut bytes = addr.0.to_vec();
+        bytes.extend([0; 4]);
+        bytes.extend(addr.checksum());
+        let s = BASE32_NOPAD.encode(&bytes);
+        assert_eq!(Address::from_str(&s), Err(AddressError::WrongLength));
+    }
+
+    #[test]
+    fn altered_addresses() {
+        let addr = "J5YDZLPOHWB5O6MVRHNFGY4JXIQAYYM6NUJWPBSYBBIXH5ENQ4Z5LTJELU";
+        let replacements = "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567a18= ";
+        for i in 0..addr.len() {
+            for c in replacements.chars() {
+                let mut altered = addr.to_owned();
+                altered.replace_range(i..i + 1, &c.to_string());
+                if altered != addr {
+                    assert!(Address::from_str(&altered).is_err(), "{}", altered);
+                }
+
+                let mut inserted = addr.to_owned();
+                inserted.insert(i, c);
+                assert!(Address::from_str(&inserted).is_err(), "{}", inserted);
+            }
+
+            let mut removed = addr.to_owned();
+            removed.remove(i);
+            assert!(Address::from_str(&removed).is_err(), "{}", removed);
+        }
+    }
+
+    #[test]
+    fn serde_json() {
+        let s = "J5YDZLPOHWB5O6MVRHNFGY4JXIQAYYM6NUJWPBSYBBIXH5ENQ4Z5LTJELU";
+        let addr = Address::from_str(s).unwrap();
+        let json = serde_json::to_string(&addr).unwrap();
+        assert_eq!(json, format!("\"{}\"", s));
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), addr);
+        assert!(serde_json::from_str::<Address>("\"J5YDZLPO\"").is_err());
+    }
+
+    #[test]
+    fn serde_msgpack() {
+        let addr = Address([7; 32]);
+        let bytes = crate::encoding::to_msgpack(&addr);
+        // bin8 of length 32
+        assert_eq!(bytes[..2], [0xc4, 32]);
+        assert_eq!(crate::encoding::from_msgpack::<Address>(&bytes), Ok(addr));
+    }
+
+    #[test]
+    fn hex() {
+        let addr = Address::from_str("J5YDZLPOHWB5O6MVRHNFGY4JXIQAYYM6NUJWPBSYBBIXH5ENQ4Z5LTJELU")
+            .unwrap();
+        let hex = addr.to_hex();
+        assert_eq!(hex.len(), 64);
+        assert_eq!(Address::from_hex(&hex), Ok(addr));
+        assert_eq!(Address::from_hex(&hex.to_uppercase()), Ok(addr));
+
+        assert_eq!(
+            Address::from_hex(&hex[..62]),
+            Err(AddressError::WrongLength)
+        );
+        assert_eq!(Address::from_hex(&hex[1..]), Err(AddressError::InvalidHex));
+        assert_eq!(
+            Address::from_hex(&hex.replace(&hex[..2], "zz")),
+            Err(AddressError::InvalidHex)
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        let mut addrs = vec![
+            Address([2; 32]),
+            Address([0; 32]),
+            Address([1; 32]),
+            Address([1; 32]),
+        ];
+        let mut reversed: Vec<_> = addrs.iter().rev().copied().collect();
+        addrs.sort();
+        reversed.sort();
+        assert_eq!(addrs, reversed);
+        assert_eq!(
+            addrs,
+            [
+                Address([0; 32]),
+                Address([1; 32]),
+                Address([1; 32]),
+                Address([2; 32])
+            ]
+        );
+
+        let mut high_last = [0; 32];
+        high_last[31] = 0xff;
+        assert!(Address([1; 32]) > Address(high_last));
+    }
+
+    #[test]
+    fn constant_time_eq() {
+        let mut other = [1; 32];
+        assert!(bool::from(Address([1; 32]).ct_eq(&Address(other))));
+        other[31] = 2;
+        assert!(!bool::from(Address([1; 32]).ct_eq(&Address(other))));
+    }
 }
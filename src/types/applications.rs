@@ -1,28 +1,36 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
+use alloc::vec::Vec;
+use core::fmt;
+
 use serde::{Deserialize, Serialize};
 
 use super::*;
-use crate::util::is_default;
+use crate::encoding::{byte_vecs, bytes};
+use crate::util::{is_default, Base64Debug, Base64ListDebug};
 
 pub type AppIndex = u64;
 
 /// Allocation bound for the maximum number of ApplicationArgs that a transaction decoded off of the wire can contain.
 /// Its value is verified against consensus parameters in TestEncodedAppTxnAllocationBounds.
-const ENCODED_MAX_APPLICATION_ARGS: u32 = 32;
+pub(super) const ENCODED_MAX_APPLICATION_ARGS: usize = 32;
 
 /// Allocation bound for the maximum number of Accounts that a transaction decoded off of the wire can contain.
 /// Its value is verified against consensus parameters in TestEncodedAppTxnAllocationBounds
-const ENCODED_MAX_ACCOUNTS: u32 = 32;
+pub(super) const ENCODED_MAX_ACCOUNTS: usize = 32;
 
 /// Allocation bound for the maximum number of ForeignApps that a transaction decoded off of the wire can contain.
 /// Its value is verified against consensus parameters in TestEncodedAppTxnAllocationBounds
-const ENCODED_MAX_FOREIGN_APPS: u32 = 32;
+pub(super) const ENCODED_MAX_FOREIGN_APPS: usize = 32;
 
 /// Allocation bound for the maximum number of ForeignAssets that a transaction decoded off of the wire can contain.
 /// Its value is verified against consensus parameters in TestEncodedAppTxnAllocationBounds
-const ENCODED_MAX_FOREIGN_ASSETS: u32 = 32;
+pub(super) const ENCODED_MAX_FOREIGN_ASSETS: usize = 32;
+
+/// Allocation bound for the maximum number of Boxes that a transaction decoded off of the wire can contain.
+/// Its value is verified against consensus parameters in TestEncodedAppTxnAllocationBounds
+pub(super) const ENCODED_MAX_BOXES: usize = 32;
 
 /// Captures the transaction fields used for all interactions with applications.
 #[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -31,7 +39,12 @@ pub struct AppCallFields {
     pub application_id: AppIndex,
     #[serde(rename = "apan", default, skip_serializing_if = "is_default")]
     pub on_completion: OnCompletion,
-    #[serde(rename = "apaa", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "apaa",
+        default,
+        skip_serializing_if = "is_default",
+        with = "byte_vecs"
+    )]
     pub application_args: Vec<Vec<u8>>,
     #[serde(rename = "apat", default, skip_serializing_if = "is_default")]
     pub accounts: Vec<Address>,
@@ -39,24 +52,89 @@ pub struct AppCallFields {
     pub foreign_apps: Vec<AppIndex>,
     #[serde(rename = "apas", default, skip_serializing_if = "is_default")]
     pub foreign_assets: Vec<AssetIndex>,
+    /// Boxes the programs may access.
+    #[serde(rename = "apbx", default, skip_serializing_if = "is_default")]
+    pub boxes: Vec<BoxRef>,
 
     #[serde(rename = "apls", default, skip_serializing_if = "is_default")]
     pub local_state_schema: StateSchema,
     #[serde(rename = "apgs", default, skip_serializing_if = "is_default")]
     pub global_state_schema: StateSchema,
-    #[serde(rename = "apap", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "apap",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
     pub approval_program: Vec<u8>,
-    #[serde(rename = "apsu", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "apsu",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
     pub clear_state_program: Vec<u8>,
     #[serde(rename = "apep", default, skip_serializing_if = "is_default")]
     pub extra_program_pages: u32,
 }
 
+/// A typed application argument, encoded the way programs expect to decode it,
+/// e.g. with `btoi` for integers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AppArg {
+    /// An integer, encoded as 8 big-endian bytes.
+    Uint(u64),
+    /// Raw bytes, passed as they are.
+    Bytes(Vec<u8>),
+    /// An address, encoded as its 32 byte public key.
+    Address(Address),
+    /// An application ID, encoded like `Uint`.
+    AppId(AppIndex),
+    /// An asset ID, encoded like `Uint`.
+    AssetId(AssetIndex),
+}
+
+impl AppArg {
+    /// Encodes the argument into the bytes passed to the program.
+    pub fn into_arg(self) -> Vec<u8> {
+        match self {
+            AppArg::Uint(n) | AppArg::AppId(n) | AppArg::AssetId(n) => n.to_be_bytes().to_vec(),
+            AppArg::Bytes(bytes) => bytes,
+            AppArg::Address(address) => address.0.to_vec(),
+        }
+    }
+}
+
+impl AppCallFields {
+    /// Sets the application args to the encodings of `args`, replacing any previous ones.
+    pub fn with_args(mut self, args: impl IntoIterator<Item = AppArg>) -> Self {
+        self.application_args = args.into_iter().map(AppArg::into_arg).collect();
+        self
+    }
+}
+
+/// Refers to a box of an application, which programs may only access if it is referenced.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoxRef {
+    /// Index of the box's application in `[application_id, foreign_apps...]`,
+    /// i.e. zero for the called application.
+    #[serde(rename = "i", default, skip_serializing_if = "is_default")]
+    pub app_index: u64,
+    #[serde(
+        rename = "n",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
+    pub name: Vec<u8>,
+}
+
 /// Represents some layer 1 side effect that an `ApplicationCall` transaction will have if it is included in a block.
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 // TODO serialize as int https://serde.rs/enum-number.html
 pub enum OnCompletion {
     /// NoOpOC indicates that an application transaction will simply call its ApprovalProgram.
+    #[default]
     NoOpOC,
 
     /// OptInOC indicates that an application transaction will allocate some
@@ -83,7 +161,7 @@ pub enum OnCompletion {
 }
 
 /// Sets maximums on the number of each type that may be stored.
-#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StateSchema {
     #[serde(rename = "nui", default, skip_serializing_if = "is_default")]
     pub num_uint: u64,
@@ -91,8 +169,35 @@ pub struct StateSchema {
     pub num_byte_slice: u64,
 }
 
-impl Default for OnCompletion {
-    fn default() -> Self {
-        Self::NoOpOC
+/// Formats the args and programs as base64.
+impl fmt::Debug for AppCallFields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AppCallFields")
+            .field("application_id", &self.application_id)
+            .field("on_completion", &self.on_completion)
+            .field("application_args", &Base64ListDebug(&self.application_args))
+            .field("accounts", &self.accounts)
+            .field("foreign_apps", &self.foreign_apps)
+            .field("foreign_assets", &self.foreign_assets)
+            .field("boxes", &self.boxes)
+            .field("local_state_schema", &self.local_state_schema)
+            .field("global_state_schema", &self.global_state_schema)
+            .field("approval_program", &Base64Debug(&self.approval_program))
+            .field(
+                "clear_state_program",
+                &Base64Debug(&self.clear_state_program),
+            )
+            .field("extra_program_pages", &self.extra_program_pages)
+            .finish()
+    }
+}
+
+/// Formats the name as base64.
+impl fmt::Debug for BoxRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BoxRef")
+            .field("app_index", &self.app_index)
+            .field("name", &Base64Debug(&self.name))
+            .finish()
     }
 }
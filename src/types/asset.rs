@@ -1,10 +1,19 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+
+use data_encoding::BASE64;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256, Sha512_256};
+use thiserror::Error;
 
 use super::*;
-use crate::util::is_default;
+use crate::encoding::bytes;
+use crate::util::{is_default, Base64Debug};
 
 /// Maximum length (in bytes) for the asset name.
 const ASSET_NAME_MAX_LEN: usize = 32;
@@ -18,6 +27,13 @@ const ASSET_URL_MAX_LEN: usize = 96;
 /// Length of the Asset's `metadata_hash` (in bytes).
 const ASSET_METADATA_HASH_LEN: usize = 32;
 
+/// Prefix of the JSON metadata when hashing it into an ARC-3 asset's metadata hash
+/// together with extra metadata.
+const ARC3_JSON_METADATA_PREFIX: &[u8] = b"arc0003/amj";
+
+/// Prefix of the hash of the JSON metadata and the extra metadata of an ARC-3 asset.
+const ARC3_METADATA_PREFIX: &[u8] = b"arc0003/am";
+
 /// Maximum value of the `decimals` field.
 const ASSET_MAX_NUMBER_OF_DECIMALS: u32 = 19;
 
@@ -25,6 +41,26 @@ const ASSET_MAX_NUMBER_OF_DECIMALS: u32 = 19;
 /// whose balance record contains the `AssetParams`.
 pub type AssetIndex = u64;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum AssetParamsError {
+    #[error("asset has {0} decimals, more than the maximum of {ASSET_MAX_NUMBER_OF_DECIMALS}")]
+    TooManyDecimals(u32),
+    #[error(
+        "asset unit name is {0} bytes long, more than the maximum of {ASSET_UNIT_NAME_MAX_LEN}"
+    )]
+    UnitNameTooLong(usize),
+    #[error("asset name is {0} bytes long, more than the maximum of {ASSET_NAME_MAX_LEN}")]
+    AssetNameTooLong(usize),
+    #[error("asset url is {0} bytes long, more than the maximum of {ASSET_URL_MAX_LEN}")]
+    UrlTooLong(usize),
+    #[error("metadata hash is {0} bytes long instead of {ASSET_METADATA_HASH_LEN}")]
+    InvalidMetadataHashLength(usize),
+    #[error("asset metadata is not a JSON document")]
+    InvalidMetadataJson,
+    #[error("extra metadata of the asset is not a base64 string")]
+    InvalidExtraMetadata,
+}
+
 /// Describes the parameters of an asset.
 #[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AssetParams {
@@ -35,7 +71,8 @@ pub struct AssetParams {
     /// Number of digits to display after the decimal place when displaying this asset:
     ///   - 0 represents an asset that is not divisible
     ///   - 1 represents an asset divisible into tenths
-    ///   ... and so on
+    ///   - ... and so on
+    ///
     /// This value must be between `0` and `ASSET_MAX_NUMBER_OF_DECIMALS` (inclusive).
     #[serde(rename = "dc", default, skip_serializing_if = "is_default")]
     pub decimals: u32,
@@ -58,7 +95,12 @@ pub struct AssetParams {
 
     /// Commitment to some unspecified asset metadata.
     /// The format of this metadata is up to the application.
-    #[serde(rename = "am", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "am",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
     pub metadata_hash: [u8; ASSET_METADATA_HASH_LEN],
 
     /// An account that is allowed to change the non-zero addresses in this `AssetParams`.
@@ -77,3 +119,291 @@ pub struct AssetParams {
     #[serde(rename = "c", default, skip_serializing_if = "is_default")]
     pub clawback: Address,
 }
+
+impl AssetParams {
+    /// Creates the parameters of an asset without any special accounts,
+    /// checking them against the protocol's bounds.
+    pub fn new(
+        total: u64,
+        decimals: u32,
+        unit_name: &str,
+        asset_name: &str,
+        url: &str,
+    ) -> Result<AssetParams, AssetParamsError> {
+        let params = AssetParams {
+            total,
+            decimals,
+            unit_name: unit_name.to_owned(),
+            asset_name: asset_name.to_owned(),
+            url: url.to_owned(),
+            ..Default::default()
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Sets the commitment to the asset's metadata, which must be exactly 32 bytes long.
+    pub fn with_metadata_hash(mut self, hash: &[u8]) -> Result<AssetParams, AssetParamsError> {
+        self.metadata_hash = hash
+            .try_into()
+            .map_err(|_| AssetParamsError::InvalidMetadataHashLength(hash.len()))?;
+        Ok(self)
+    }
+
+    /// Describes the asset by the ARC-3 JSON metadata document at `url`,
+    /// setting the metadata hash to the SHA-256 hash of `metadata_json`.
+    ///
+    /// If the document has an `extra_metadata` field, the metadata hash instead commits
+    /// to both the document and the base64 decoded extra metadata, as ARC-3 specifies.
+    /// The document is hashed as it is, so it has to be hosted byte for byte.
+    /// Unless the name or URL already mark the asset as ARC-3, `#arc3` is appended to the URL.
+    pub fn with_arc3_metadata(
+        mut self,
+        asset_name: &str,
+        unit_name: &str,
+        url: &str,
+        metadata_json: &str,
+    ) -> Result<AssetParams, AssetParamsError> {
+        let metadata: serde_json::Value = serde_json::from_str(metadata_json)
+            .map_err(|_| AssetParamsError::InvalidMetadataJson)?;
+        let extra_metadata = match metadata.get("extra_metadata") {
+            None => None,
+            Some(extra) => Some(
+                extra
+                    .as_str()
+                    .and_then(|extra| BASE64.decode(extra.as_bytes()).ok())
+                    .ok_or(AssetParamsError::InvalidExtraMetadata)?,
+            ),
+        };
+        let is_arc3 =
+            asset_name == "arc3" || asset_name.ends_with("@arc3") || url.ends_with("#arc3");
+        self.asset_name = asset_name.to_owned();
+        self.unit_name = unit_name.to_owned();
+        self.url = if is_arc3 {
+            url.to_owned()
+        } else {
+            format!("{}#arc3", url)
+        };
+        self.metadata_hash = match extra_metadata {
+            None => Sha256::digest(metadata_json.as_bytes()).into(),
+            Some(extra) => {
+                let json_hash = Sha512_256::new()
+                    .chain_update(ARC3_JSON_METADATA_PREFIX)
+                    .chain_update(metadata_json.as_bytes())
+                    .finalize();
+                Sha512_256::new()
+                    .chain_update(ARC3_METADATA_PREFIX)
+                    .chain_update(json_hash)
+                    .chain_update(extra)
+                    .finalize()
+                    .into()
+            }
+        };
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Points the URL to the IPFS content whose CIDv1 has the multicodec `codec`
+    /// (e.g. `raw` or `dag-pb`) and the SHA-256 `digest`, following ARC-19.
+    ///
+    /// The digest is stored as the reserve address, so that the manager can later
+    /// change the content by changing the reserve address.
+    pub fn with_arc19_url(
+        mut self,
+        codec: &str,
+        digest: [u8; 32],
+    ) -> Result<AssetParams, AssetParamsError> {
+        self.url = format!("template-ipfs://{{ipfscid:1:{}:reserve:sha2-256}}", codec);
+        self.reserve = Address(digest);
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Checks that the parameters are within the protocol's bounds.
+    pub fn validate(&self) -> Result<(), AssetParamsError> {
+        if self.decimals > ASSET_MAX_NUMBER_OF_DECIMALS {
+            return Err(AssetParamsError::TooManyDecimals(self.decimals));
+        } else if self.unit_name.len() > ASSET_UNIT_NAME_MAX_LEN {
+            return Err(AssetParamsError::UnitNameTooLong(self.unit_name.len()));
+        } else if self.asset_name.len() > ASSET_NAME_MAX_LEN {
+            return Err(AssetParamsError::AssetNameTooLong(self.asset_name.len()));
+        } else if self.url.len() > ASSET_URL_MAX_LEN {
+            return Err(AssetParamsError::UrlTooLong(self.url.len()));
+        }
+        Ok(())
+    }
+}
+
+/// Formats the metadata hash as base64.
+impl fmt::Debug for AssetParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AssetParams")
+            .field("total", &self.total)
+            .field("decimals", &self.decimals)
+            .field("default_frozen", &self.default_frozen)
+            .field("unit_name", &self.unit_name)
+            .field("asset_name", &self.asset_name)
+            .field("url", &self.url)
+            .field("metadata_hash", &Base64Debug(&self.metadata_hash))
+            .field("manager", &self.manager)
+            .field("reserve", &self.reserve)
+            .field("freeze", &self.freeze)
+            .field("clawback", &self.clawback)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use data_encoding::HEXLOWER;
+
+    fn new(
+        decimals: u32,
+        unit_name: &str,
+        asset_name: &str,
+        url: &str,
+    ) -> Option<AssetParamsError> {
+        AssetParams::new(1000, decimals, unit_name, asset_name, url).err()
+    }
+
+    #[test]
+    fn bounds() {
+        assert_eq!(new(19, "", "", ""), None);
+        assert_eq!(
+            new(20, "", "", ""),
+            Some(AssetParamsError::TooManyDecimals(20))
+        );
+
+        assert_eq!(new(0, &"x".repeat(8), "", ""), None);
+        assert_eq!(
+            new(0, &"x".repeat(9), "", ""),
+            Some(AssetParamsError::UnitNameTooLong(9))
+        );
+
+        assert_eq!(new(0, "", &"x".repeat(32), ""), None);
+        assert_eq!(
+            new(0, "", &"x".repeat(33), ""),
+            Some(AssetParamsError::AssetNameTooLong(33))
+        );
+
+        assert_eq!(new(0, "", "", &"x".repeat(96)), None);
+        assert_eq!(
+            new(0, "", "", &"x".repeat(97)),
+            Some(AssetParamsError::UrlTooLong(97))
+        );
+    }
+
+    #[test]
+    fn bounds_in_bytes() {
+        // "€" is three bytes long in UTF-8
+        assert_eq!(new(0, &"€".repeat(2), "", ""), None);
+        assert_eq!(
+            new(0, &"€".repeat(3), "", ""),
+            Some(AssetParamsError::UnitNameTooLong(9))
+        );
+    }
+
+    #[test]
+    fn metadata_hash() {
+        let params = AssetParams::new(1000, 0, "TKN", "Token", "").unwrap();
+        let params = params.with_metadata_hash(&[7; 32]).unwrap();
+        assert_eq!(params.metadata_hash, [7; 32]);
+
+        assert_eq!(
+            params.clone().with_metadata_hash(&[7; 31]).err(),
+            Some(AssetParamsError::InvalidMetadataHashLength(31))
+        );
+        assert_eq!(
+            params.with_metadata_hash(&[7; 33]).err(),
+            Some(AssetParamsError::InvalidMetadataHashLength(33))
+        );
+    }
+
+    #[test]
+    fn arc3_metadata() {
+        let params = AssetParams::default()
+            .with_arc3_metadata("Rock", "ROCK", "ipfs://bafkrei", "{}")
+            .unwrap();
+        assert_eq!(params.asset_name, "Rock");
+        assert_eq!(params.unit_name, "ROCK");
+        assert_eq!(params.url, "ipfs://bafkrei#arc3");
+        assert_eq!(
+            HEXLOWER.encode(&params.metadata_hash),
+            "44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+        );
+
+        let named = AssetParams::default()
+            .with_arc3_metadata("Rock@arc3", "ROCK", "ipfs://bafkrei", "{}")
+            .unwrap();
+        assert_eq!(named.url, "ipfs://bafkrei");
+
+        assert_eq!(
+            AssetParams::default()
+                .with_arc3_metadata("Rock", "ROCK", "ipfs://bafkrei", "{")
+                .err(),
+            Some(AssetParamsError::InvalidMetadataJson)
+        );
+        // the URL is too long only with the suffix
+        assert_eq!(
+            AssetParams::default()
+                .with_arc3_metadata("Rock", "ROCK", &"x".repeat(92), "{}")
+                .err(),
+            Some(AssetParamsError::UrlTooLong(97))
+        );
+        assert_eq!(
+            AssetParams::default()
+                .with_arc3_metadata("Rock", "ROCKSTONE", "", "{}")
+                .err(),
+            Some(AssetParamsError::UnitNameTooLong(9))
+        );
+    }
+
+    #[test]
+    fn arc3_extra_metadata() {
+        const JSON: &str =
+            r#"{"name":"Rock","extra_metadata":"iHcUslDaL/jEM/oTxqEX++4CS8o3+IZp7/V5Rgchqwc="}"#;
+        let params = AssetParams::default()
+            .with_arc3_metadata("Rock", "ROCK", "ipfs://bafkrei", JSON)
+            .unwrap();
+        // SHA-512/256("arc0003/am" || SHA-512/256("arc0003/amj" || JSON) || extra metadata)
+        assert_eq!(
+            HEXLOWER.encode(&params.metadata_hash),
+            "160058e89002eb1152b633c274e0da14dffcd52054526e167cda971f24c3ca24"
+        );
+
+        for invalid in [
+            r#"{"extra_metadata":"not base64"}"#,
+            r#"{"extra_metadata":7}"#,
+        ] {
+            assert_eq!(
+                AssetParams::default()
+                    .with_arc3_metadata("Rock", "ROCK", "ipfs://bafkrei", invalid)
+                    .err(),
+                Some(AssetParamsError::InvalidExtraMetadata)
+            );
+        }
+    }
+
+    #[test]
+    fn arc19_url() {
+        let params = AssetParams::default()
+            .with_arc19_url("raw", [9; 32])
+            .unwrap();
+        assert_eq!(
+            params.url,
+            "template-ipfs://{ipfscid:1:raw:reserve:sha2-256}"
+        );
+        assert_eq!(params.reserve, Address([9; 32]));
+    }
+
+    #[test]
+    fn validate_decoded() {
+        let params = AssetParams {
+            url: "x".repeat(97),
+            ..Default::default()
+        };
+        assert_eq!(params.validate(), Err(AssetParamsError::UrlTooLong(97)));
+    }
+}
@@ -1,10 +1,18 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use super::*;
+use crate::encoding;
+#[cfg(feature = "std")]
+use crate::encoding::DecodeError;
 use crate::util::is_default;
 
 /// Represents a bid by a user as part of an auction.
@@ -40,6 +48,37 @@ pub struct Bid {
     pub auction_id: u64,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum BidError {
+    #[error("maximum price {max_price} is below the current price {current_price}")]
+    PriceTooLow { max_price: u64, current_price: u64 },
+    #[error("bid has no currency")]
+    ZeroCurrency,
+    #[error("bidder key is the zero address")]
+    ZeroBidderKey,
+    #[error("auction key is the zero address")]
+    ZeroAuctionKey,
+}
+
+impl Bid {
+    /// Checks that the bid is well-formed and can be placed at the auction's `current_price`.
+    pub fn validate(&self, current_price: u64) -> Result<(), BidError> {
+        if self.max_price < current_price {
+            return Err(BidError::PriceTooLow {
+                max_price: self.max_price,
+                current_price,
+            });
+        } else if self.bid_currency == 0 {
+            return Err(BidError::ZeroCurrency);
+        } else if self.bidder_key.is_zero() {
+            return Err(BidError::ZeroBidderKey);
+        } else if self.auction_key.is_zero() {
+            return Err(BidError::ZeroAuctionKey);
+        }
+        Ok(())
+    }
+}
+
 /// Represents a signed bid by a bidder.
 #[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default)]
@@ -74,3 +113,102 @@ pub struct NoteField {
     #[serde(rename = "b", default, skip_serializing_if = "is_default")]
     pub signed_bid: SignedBid,
 }
+
+impl NoteField {
+    /// Encodes the message into canonical msgpack, for use as a transaction's `note`.
+    pub fn encode(&self) -> Vec<u8> {
+        encoding::to_msgpack(self)
+    }
+
+    /// Decodes a message from a transaction's `note`.
+    #[cfg(feature = "std")]
+    pub fn decode(note: &[u8]) -> Result<NoteField, DecodeError> {
+        encoding::from_msgpack(note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ed25519_dalek::Signer;
+
+    use crate::account::Account;
+
+    #[test]
+    fn note_field_round_trip() {
+        let bidder = Account::from_seed(&[1; 32]);
+        let bid = Bid {
+            bidder_key: bidder.address(),
+            bid_currency: 100,
+            max_price: 5,
+            bid_id: 1,
+            auction_key: Address([9; 32]),
+            auction_id: 3,
+        };
+        let sig = Signature(bidder.keypair().sign(&encoding::to_msgpack(&bid)));
+        let note = NoteField {
+            note_type: NOTE_BID.clone(),
+            signed_bid: SignedBid { bid, sig },
+        };
+
+        let encoded = note.encode();
+        // fixmap(2), "b": the signed bid, ..., "t": "b"
+        assert_eq!(encoded[..3], [0x82, 0xa1, b'b']);
+        assert_eq!(encoded[encoded.len() - 4..], [0xa1, b't', 0xa1, b'b']);
+
+        let decoded = NoteField::decode(&encoded).unwrap();
+        assert!(decoded == note);
+        assert_eq!(decoded.signed_bid.bid.max_price, 5);
+
+        let header = Header {
+            note: encoded,
+            ..Default::default()
+        };
+        assert!(NoteField::decode(&header.note).unwrap() == note);
+    }
+
+    #[test]
+    fn bid_validation() {
+        let bid = Bid {
+            bidder_key: Address([1; 32]),
+            bid_currency: 100,
+            max_price: 5,
+            auction_key: Address([9; 32]),
+            ..Default::default()
+        };
+        assert_eq!(bid.validate(4), Ok(()));
+        assert_eq!(bid.validate(5), Ok(()));
+        assert_eq!(
+            bid.validate(6),
+            Err(BidError::PriceTooLow {
+                max_price: 5,
+                current_price: 6
+            })
+        );
+
+        let no_currency = Bid {
+            bid_currency: 0,
+            ..bid.clone()
+        };
+        assert_eq!(no_currency.validate(5), Err(BidError::ZeroCurrency));
+        let no_bidder = Bid {
+            bidder_key: Address::default(),
+            ..bid.clone()
+        };
+        assert_eq!(no_bidder.validate(5), Err(BidError::ZeroBidderKey));
+        let no_auction = Bid {
+            auction_key: Address::default(),
+            ..bid
+        };
+        assert_eq!(no_auction.validate(5), Err(BidError::ZeroAuctionKey));
+    }
+
+    #[test]
+    fn note_field_invalid() {
+        assert!(matches!(
+            NoteField::decode(b"hello"),
+            Err(DecodeError::InvalidMsgpack(_))
+        ));
+    }
+}
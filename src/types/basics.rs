@@ -1,41 +1,83 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use core::iter::Sum;
+use core::ops::{Add, Sub};
+use core::str::FromStr;
+
 use ed25519_dalek::PublicKey;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::encoding::{bytes, public_key};
+use crate::mnemonic::{self, MnemonicError};
+use crate::util::Base64Debug;
 
 const MASTER_DERIVATION_KEY_LEN_BYTES: usize = 32;
 
 /// Maximum number of transactions in a single group.
-const MAX_TX_GROUP_SIZE: usize = 16;
+pub const MAX_TX_GROUP_SIZE: usize = 16;
 
-/// Maximum TEAL program size (with args).
-const LOGIC_SIG_MAX_SIZE: usize = 1000;
+/// Maximum number of rounds between a transaction's first and last valid round.
+pub(crate) const MAX_TXN_LIFE: u64 = super::consensus::CURRENT_PARAMS.max_txn_life;
 
-/// Maximum execution cost of a TEAL program.
-const LOGIC_SIG_MAX_COST: usize = 20_000;
+/// Maximum length (in bytes) of a transaction's note.
+pub(crate) const MAX_TX_NOTE_BYTES: usize = 1024;
+
+/// Maximum TEAL program size (with args).
+pub(crate) const LOGIC_SIG_MAX_SIZE: usize = 1000;
 
 /// Base unit of currency in Algorand, which is 1e-6 Algos.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct MicroAlgos(pub u64);
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum ParseAlgosError {
+    #[error("invalid Algo amount")]
+    Invalid,
+    #[error("Algo amount has more than 6 decimal places")]
+    TooPrecise,
+    #[error("Algo amount is too large")]
+    Overflow,
+}
+
 /// Represents a round of the Algorand consensus protocol.
-pub type Round = u64;
+///
+/// Rounds can be converted from and into `u64`, and are encoded as such.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Round(pub u64);
 
-/// Participation public key used in key registration transactions.
-pub type VotePK = PublicKey;
+/// Participation public key used in key registration transactions,
+/// the ed25519 key that the account's one-time voting keys are signed with.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VotePK(#[serde(with = "public_key")] pub PublicKey);
 
-/// VRF public key used in key registration transactions.
-pub type VrfPK = PublicKey;
+/// VRF public key used in key registration transactions, to select the account
+/// for committees. This is a key of the VRF scheme, not an ed25519 key.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VrfPK(#[serde(with = "bytes")] pub [u8; 32]);
 
-/// Secret key used to derive keys in wallets.
-type MasterDerivationKey = [u8; MASTER_DERIVATION_KEY_LEN_BYTES];
+/// Secret key from which a kmd wallet derives all of its keys,
+/// so that the wallet can be restored from it.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MasterDerivationKey(#[serde(with = "bytes")] pub [u8; MASTER_DERIVATION_KEY_LEN_BYTES]);
 
 /// A SHA512_256 hash value.
 pub type Digest = [u8; 32];
 
-const MICROALGO_CONVERSION_FACTOR: f64 = 1e6;
+const MICROALGOS_PER_ALGO: u64 = 1_000_000;
+
+const MICROALGO_CONVERSION_FACTOR: f64 = MICROALGOS_PER_ALGO as f64;
 
 impl MicroAlgos {
     /// Converts currency amount in `MicroAlgos` to Algos.
@@ -47,28 +89,314 @@ impl MicroAlgos {
     pub fn from_algos(algos: f64) -> MicroAlgos {
         MicroAlgos((algos * MICROALGO_CONVERSION_FACTOR) as u64)
     }
+
+    /// Adds two amounts, returning `None` on overflow.
+    pub fn checked_add(self, rhs: MicroAlgos) -> Option<MicroAlgos> {
+        self.0.checked_add(rhs.0).map(MicroAlgos)
+    }
+
+    /// Subtracts `rhs` from this amount, returning `None` if the result would be negative.
+    pub fn checked_sub(self, rhs: MicroAlgos) -> Option<MicroAlgos> {
+        self.0.checked_sub(rhs.0).map(MicroAlgos)
+    }
+}
+
+/// Formats the amount in Algos with all 6 decimal places, e.g. `1.500000`.
+impl fmt::Display for MicroAlgos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:06}",
+            self.0 / MICROALGOS_PER_ALGO,
+            self.0 % MICROALGOS_PER_ALGO
+        )
+    }
+}
+
+/// Parses an amount in Algos with up to 6 decimal places, e.g. `1.5`.
+///
+/// Parsing is exact, i.e. does not go through floating point.
+impl FromStr for MicroAlgos {
+    type Err = ParseAlgosError;
+
+    fn from_str(s: &str) -> Result<MicroAlgos, ParseAlgosError> {
+        let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty() || !is_digits(whole) || !is_digits(frac) || s.ends_with('.') {
+            return Err(ParseAlgosError::Invalid);
+        }
+        if frac.len() > 6 {
+            return Err(ParseAlgosError::TooPrecise);
+        }
+
+        let whole: u64 = whole.parse().map_err(|_| ParseAlgosError::Overflow)?;
+        let frac = format!("{:0<6}", frac).parse::<u64>().unwrap();
+        whole
+            .checked_mul(MICROALGOS_PER_ALGO)
+            .and_then(|micro| micro.checked_add(frac))
+            .map(MicroAlgos)
+            .ok_or(ParseAlgosError::Overflow)
+    }
+}
+
+/// Panics on overflow, use `MicroAlgos::checked_add` to handle it instead.
+impl Add for MicroAlgos {
+    type Output = MicroAlgos;
+
+    fn add(self, rhs: MicroAlgos) -> MicroAlgos {
+        self.checked_add(rhs)
+            .expect("overflow when adding MicroAlgos")
+    }
+}
+
+/// Panics if the result would be negative, use `MicroAlgos::checked_sub` to handle it instead.
+impl Sub for MicroAlgos {
+    type Output = MicroAlgos;
+
+    fn sub(self, rhs: MicroAlgos) -> MicroAlgos {
+        self.checked_sub(rhs)
+            .expect("underflow when subtracting MicroAlgos")
+    }
 }
 
-/*func (signedTxn *SignedTxn) FromBase64String(b64string string) error {
-    txnBytes, err := base64.StdEncoding.DecodeString(b64string)
-    if err != nil {
-        return err
+/// Panics on overflow, like `Add`.
+impl Sum for MicroAlgos {
+    fn sum<I: Iterator<Item = MicroAlgos>>(iter: I) -> MicroAlgos {
+        iter.fold(MicroAlgos(0), Add::add)
     }
-    err = msgpack.Decode(txnBytes, &signedTxn)
-    if err != nil {
-        return err
+}
+
+/// Formats the key as base64.
+impl fmt::Debug for VotePK {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("VotePK")
+            .field(&Base64Debug(self.0.as_bytes()))
+            .finish()
     }
-    return nil
 }
 
-func (block *Block) FromBase64String(b64string string) error {
-    txnBytes, err := base64.StdEncoding.DecodeString(b64string)
-    if err != nil {
-        return err
+/// Formats the key as base64.
+impl fmt::Debug for VrfPK {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("VrfPK").field(&Base64Debug(&self.0)).finish()
     }
-    err = msgpack.Decode(txnBytes, &block)
-    if err != nil {
-        return err
+}
+
+impl MasterDerivationKey {
+    /// Decodes the key from a mnemonic phrase of 25 words, as made by `to_mnemonic`.
+    pub fn from_mnemonic(phrase: &str) -> Result<Self, MnemonicError> {
+        mnemonic::to_key(phrase).map(MasterDerivationKey)
+    }
+
+    /// Encodes the key as a mnemonic phrase of 25 words, the format kmd and `goal` back it up in.
+    pub fn to_mnemonic(&self) -> String {
+        mnemonic::from_key(&self.0)
+    }
+}
+
+/// Does not print the secret key.
+impl fmt::Debug for MasterDerivationKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("MasterDerivationKey(..)")
+    }
+}
+
+impl Round {
+    /// Returns the round `rounds` after this one, or `None` on overflow.
+    pub fn checked_add(self, rounds: u64) -> Option<Round> {
+        self.0.checked_add(rounds).map(Round)
+    }
+
+    /// Returns the round `rounds` before this one, or `None` if it would be before round 0.
+    pub fn checked_sub(self, rounds: u64) -> Option<Round> {
+        self.0.checked_sub(rounds).map(Round)
     }
-    return nil
-}*/
+
+    /// Returns the round `rounds` after this one, or the last round on overflow.
+    pub fn saturating_add(self, rounds: u64) -> Round {
+        Round(self.0.saturating_add(rounds))
+    }
+}
+
+impl fmt::Display for Round {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<u64> for Round {
+    fn from(round: u64) -> Round {
+        Round(round)
+    }
+}
+
+impl From<Round> for u64 {
+    fn from(round: Round) -> u64 {
+        round.0
+    }
+}
+
+/// Panics on overflow, use `Round::checked_add` to handle it instead.
+impl Add<u64> for Round {
+    type Output = Round;
+
+    fn add(self, rounds: u64) -> Round {
+        self.checked_add(rounds)
+            .expect("overflow when adding to Round")
+    }
+}
+
+/// Counts the rounds from `rhs` to this round.
+/// Panics if `rhs` is after this round.
+impl Sub for Round {
+    type Output = u64;
+
+    fn sub(self, rhs: Round) -> u64 {
+        self.0
+            .checked_sub(rhs.0)
+            .expect("underflow when subtracting Rounds")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::encoding;
+
+    #[test]
+    fn round_arithmetic() {
+        assert_eq!(Round(5) + 3, Round(8));
+        assert_eq!(Round(8) - Round(5), 3);
+        assert_eq!(Round(u64::MAX).checked_add(1), None);
+        assert_eq!(Round(u64::MAX).saturating_add(1), Round(u64::MAX));
+        assert_eq!(Round(0).checked_sub(1), None);
+        assert_eq!(Round(1).checked_sub(1), Some(Round(0)));
+        assert_eq!(Round::from(7).to_string(), "7");
+        assert_eq!(u64::from(Round(7)), 7);
+
+        // encoded like a plain integer
+        assert_eq!(
+            encoding::to_msgpack(&Round(300)),
+            encoding::to_msgpack(&300u64)
+        );
+    }
+
+    #[test]
+    fn participation_keys() {
+        // [2; 32] is not an ed25519 point, but any 32 bytes are a valid VRF key
+        let vrf_pk = VrfPK([2; 32]);
+        let blob = encoding::to_msgpack(&vrf_pk);
+        assert_eq!(encoding::from_msgpack::<VrfPK>(&blob).unwrap(), vrf_pk);
+        assert!(encoding::from_msgpack::<VotePK>(&blob).is_err());
+        assert_eq!(
+            format!("{:?}", vrf_pk),
+            "VrfPK(AgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgI=)"
+        );
+
+        let vote_pk = VotePK(crate::account::Account::from_seed(&[1; 32]).public_key());
+        let blob = encoding::to_msgpack(&vote_pk);
+        assert_eq!(encoding::from_msgpack::<VotePK>(&blob).unwrap(), vote_pk);
+        assert!(format!("{:?}", vote_pk).starts_with("VotePK("));
+    }
+
+    #[test]
+    fn master_derivation_key_mnemonic() {
+        let zero = MasterDerivationKey([0; 32]);
+        let phrase = format!("{}invest", "abandon ".repeat(24));
+        assert_eq!(zero.to_mnemonic(), phrase);
+        assert_eq!(MasterDerivationKey::from_mnemonic(&phrase), Ok(zero));
+
+        let mdk = MasterDerivationKey(core::array::from_fn(|i| i as u8));
+        let restored = MasterDerivationKey::from_mnemonic(&mdk.to_mnemonic()).unwrap();
+        assert_eq!(restored, mdk);
+        assert_eq!(
+            MasterDerivationKey::from_mnemonic(&phrase.replace("invest", "abandon")),
+            Err(MnemonicError::WrongChecksum)
+        );
+        assert_eq!(format!("{:?}", mdk), "MasterDerivationKey(..)");
+
+        // kmd exports the key as base64 in JSON
+        let json = serde_json::to_string(&mdk).unwrap();
+        assert_eq!(json, "\"AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=\"");
+        assert_eq!(
+            serde_json::from_str::<MasterDerivationKey>(&json).unwrap(),
+            mdk
+        );
+    }
+
+    #[test]
+    fn micro_algos_arithmetic() {
+        let fees = [MicroAlgos(1000), MicroAlgos(2000), MicroAlgos(3000)];
+        assert_eq!(fees.iter().copied().sum::<MicroAlgos>(), MicroAlgos(6000));
+        assert_eq!(MicroAlgos(5) + MicroAlgos(3), MicroAlgos(8));
+        assert_eq!(MicroAlgos(5) - MicroAlgos(3), MicroAlgos(2));
+
+        assert_eq!(MicroAlgos(u64::MAX).checked_add(MicroAlgos(1)), None);
+        assert_eq!(
+            MicroAlgos(u64::MAX - 1).checked_add(MicroAlgos(1)),
+            Some(MicroAlgos(u64::MAX))
+        );
+        assert_eq!(MicroAlgos(0).checked_sub(MicroAlgos(1)), None);
+        assert_eq!(
+            MicroAlgos(1).checked_sub(MicroAlgos(1)),
+            Some(MicroAlgos(0))
+        );
+    }
+
+    #[test]
+    fn micro_algos_display() {
+        assert_eq!(MicroAlgos(1_500_000).to_string(), "1.500000");
+        assert_eq!(MicroAlgos(1).to_string(), "0.000001");
+        assert_eq!(MicroAlgos(0).to_string(), "0.000000");
+        assert_eq!(MicroAlgos(u64::MAX).to_string(), "18446744073709.551615");
+    }
+
+    #[test]
+    fn micro_algos_from_str() {
+        assert_eq!("1.1".parse(), Ok(MicroAlgos(1_100_000)));
+        assert_eq!("1.500000".parse(), Ok(MicroAlgos(1_500_000)));
+        assert_eq!("42".parse(), Ok(MicroAlgos(42_000_000)));
+        assert_eq!("0.000001".parse(), Ok(MicroAlgos(1)));
+        assert_eq!("18446744073709.551615".parse(), Ok(MicroAlgos(u64::MAX)));
+        for amount in [0, 1, 999_999, 1_000_000, 123_456_789, u64::MAX] {
+            assert_eq!(
+                MicroAlgos(amount).to_string().parse(),
+                Ok(MicroAlgos(amount))
+            );
+        }
+
+        for invalid in ["", ".", "1.", ".5", "-1", "+1", "1.2.3", "1,5", " 1", "1e6"] {
+            assert_eq!(
+                invalid.parse::<MicroAlgos>(),
+                Err(ParseAlgosError::Invalid),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(
+            "0.0000001".parse::<MicroAlgos>(),
+            Err(ParseAlgosError::TooPrecise)
+        );
+        assert_eq!(
+            "18446744073709.551616".parse::<MicroAlgos>(),
+            Err(ParseAlgosError::Overflow)
+        );
+        assert_eq!(
+            "99999999999999999999".parse::<MicroAlgos>(),
+            Err(ParseAlgosError::Overflow)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow")]
+    fn micro_algos_add_overflow() {
+        let _ = MicroAlgos(u64::MAX) + MicroAlgos(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "underflow")]
+    fn micro_algos_sub_underflow() {
+        let _ = MicroAlgos(0) - MicroAlgos(1);
+    }
+}
@@ -1,48 +1,260 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
-use std::collections::HashMap;
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use data_encoding::BASE64;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use sha2::{Digest as _, Sha512_256};
+use thiserror::Error;
 
 use super::*;
+use crate::crypto::{
+    self, BLOCK_HEADER_PREFIX, PAYSET_FLAT_PREFIX, SIGNED_TX_IN_BLOCK_PREFIX, TX_MERKLE_LEAF_PREFIX,
+};
+#[cfg(feature = "std")]
+use crate::encoding::DecodeError;
+use crate::encoding::{self, bounded, byte_vecs, str_bytes};
+use crate::merkle;
 use crate::util::is_default;
 
 // TODO ConsensusVersion and String...
 // TODO impl Borrow<Header> for Block?
 
 /// A Block contains the Payset and metadata corresponding to a given Round.
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Block {
+    #[serde(flatten)]
     pub header: BlockHeader,
+    #[serde(rename = "txns", default, skip_serializing_if = "is_default")]
     pub payset: Payset,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum BlockError {
+    #[error("previous block is at the last possible round")]
+    RoundOverflow,
+    #[error("expected round {expected}, got {actual}")]
+    WrongRound { expected: Round, actual: Round },
+    #[error("block does not follow the previous block")]
+    WrongBranch,
+    #[error("expected genesis ID {expected}, got {actual}")]
+    GenesisIdMismatch { expected: String, actual: String },
+    #[error("genesis hash does not match the previous block")]
+    GenesisHashMismatch,
+    #[error("expected protocol {expected}, got {actual}")]
+    WrongProtocol { expected: String, actual: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum EvalDeltaError {
+    #[error("local delta at offset {0} refers to no account of the transaction")]
+    UnknownAccountOffset(u64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum RewardsError {
+    #[error("expected fee sink {expected}, got {actual}")]
+    FeeSinkMismatch { expected: Address, actual: Address },
+    #[error("expected rewards pool {expected}, got {actual}")]
+    RewardsPoolMismatch { expected: Address, actual: Address },
+}
+
+impl Block {
+    /// Decodes a block from msgpack.
+    ///
+    /// The header is decoded strictly, see `encoding::from_msgpack_strict`, since any field
+    /// this crate doesn't know of would change its hash. The payset is not.
+    #[cfg(feature = "std")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Block, DecodeError> {
+        let block: Block = encoding::from_msgpack(bytes)?;
+
+        let mut value = rmpv::decode::read_value(&mut &bytes[..])
+            .map_err(|e| DecodeError::InvalidMsgpack(e.to_string()))?;
+        if let rmpv::Value::Map(entries) = &mut value {
+            entries.retain(|(key, _)| key.as_str() != Some("txns"));
+        }
+        let mut header = Vec::new();
+        rmpv::encode::write_value(&mut header, &value).expect("writing to a Vec never fails");
+        encoding::from_msgpack_strict::<BlockHeader>(&header)?;
+        Ok(block)
+    }
+
+    /// Decodes a block from base64 encoded msgpack, like `from_msgpack` does.
+    #[cfg(feature = "std")]
+    pub fn from_base64(s: &str) -> Result<Block, DecodeError> {
+        let bytes = BASE64
+            .decode(s.as_bytes())
+            .map_err(|_| DecodeError::InvalidBase64)?;
+        Block::from_msgpack(&bytes)
+    }
+
+    /// Computes the hash of the block, which is the hash of its header.
+    /// The payset is committed to through the header's `tx_root`.
+    pub fn hash(&self) -> Digest {
+        self.header.hash()
+    }
+
+    /// Computes the commitment to the block's payset, which should equal its header's `tx_root`.
+    ///
+    /// Transactions in blocks usually omit the genesis ID and hash,
+    /// which are restored from the header before committing.
+    pub fn commit_payset(&self, commit_type: PaysetCommitType) -> Digest {
+        if commit_type == PaysetCommitType::Flat {
+            return self.payset.commit(commit_type);
+        }
+        let leaves: Vec<Digest> = self
+            .payset
+            .0
+            .iter()
+            .map(|stib| stib.merkle_leaf(&stib.restored_tx(&self.header)))
+            .collect();
+        merkle::vector_commitment(&leaves)
+    }
+
+    /// Iterates over the block's signed transactions, with the genesis ID and hash they omit
+    /// restored from the header, so that their IDs and signatures check out.
+    pub fn transactions(&self) -> impl Iterator<Item = SignedTx> + '_ {
+        self.payset.0.iter().map(|stib| {
+            let mut stx = stib.sig_txad.tx.clone();
+            stib.restore_genesis(&mut stx.tx, &self.header);
+            stx
+        })
+    }
+
+    /// Checks that the block's header is consistent with the previous block's header.
+    pub fn valid(&self, prev: &BlockHeader) -> Result<(), BlockError> {
+        let header = &self.header;
+        let expected = prev.round.checked_add(1).ok_or(BlockError::RoundOverflow)?;
+        if header.round != expected {
+            return Err(BlockError::WrongRound {
+                expected,
+                actual: header.round,
+            });
+        } else if header.branch != prev.hash() {
+            return Err(BlockError::WrongBranch);
+        } else if header.genesis_id != prev.genesis_id {
+            return Err(BlockError::GenesisIdMismatch {
+                expected: prev.genesis_id.clone(),
+                actual: header.genesis_id.clone(),
+            });
+        } else if header.genesis_hash != prev.genesis_hash {
+            return Err(BlockError::GenesisHashMismatch);
+        }
+
+        let expected = prev.upgrade_state.protocol_at(header.round);
+        if header.upgrade_state.current_protocol != expected {
+            return Err(BlockError::WrongProtocol {
+                expected: expected.to_owned(),
+                actual: header.upgrade_state.current_protocol.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl BlockHeader {
+    /// Computes the hash of this header, which the next block refers to as its `branch`.
+    pub fn hash(&self) -> Digest {
+        crypto::hash_with_prefix(BLOCK_HEADER_PREFIX, self)
+    }
+}
+
 /// Represents the metadata and commitments to the state of a Block.
 /// The Algorand Ledger may be defined minimally as a cryptographically authenticated series of `BlockHeader` objects.
 #[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BlockHeader {
+    #[serde(rename = "rnd", skip_serializing_if = "is_default")]
     pub round: basics::Round,
 
     /// The hash of the previous block
+    #[serde(
+        rename = "prev",
+        skip_serializing_if = "is_default",
+        with = "encoding::digest"
+    )]
     pub branch: Digest,
 
+    /// The SHA-512 hash of the previous block, 64 bytes long once the protocol commits to it.
+    #[serde(
+        rename = "prev512",
+        skip_serializing_if = "is_default",
+        with = "encoding::bytes"
+    )]
+    pub branch_512: Vec<u8>,
+
     /// Sortition seed
+    #[serde(
+        rename = "seed",
+        skip_serializing_if = "is_default",
+        with = "serde_bytes"
+    )]
     pub seed: [u8; 32],
 
     /// Root hash that authenticates the set of transactions appearing in the block.
     /// Computed based on the `PaysetCommitType` specified in the block's consensus protocol.
+    #[serde(
+        rename = "txn",
+        skip_serializing_if = "is_default",
+        with = "encoding::digest"
+    )]
     pub tx_root: Digest,
 
+    /// Like `tx_root`, but a vector commitment using SHA-256, for verifiers without SHA-512/256.
+    #[serde(
+        rename = "txn256",
+        skip_serializing_if = "is_default",
+        with = "encoding::digest"
+    )]
+    pub tx_root_sha256: Digest,
+
+    /// Like `tx_root`, but a vector commitment using SHA-512, 64 bytes long once the protocol commits to it.
+    #[serde(
+        rename = "txn512",
+        skip_serializing_if = "is_default",
+        with = "encoding::bytes"
+    )]
+    pub tx_root_sha512: Vec<u8>,
+
     /// TimeStamp in seconds since epoch
+    #[serde(rename = "ts", skip_serializing_if = "is_default")]
     pub timestamp: u64,
 
     /// Genesis ID to which this block belongs.
+    #[serde(rename = "gen", skip_serializing_if = "is_default")]
     pub genesis_id: String,
 
     /// Genesis hash to which this block belongs.
+    #[serde(
+        rename = "gh",
+        skip_serializing_if = "is_default",
+        with = "encoding::digest"
+    )]
     pub genesis_hash: Digest,
 
+    /// The account that proposed this block, set once the protocol pays proposers.
+    #[serde(rename = "prp", skip_serializing_if = "is_default")]
+    pub proposer: Address,
+
+    /// The sum of the fees paid by the block's transactions.
+    #[serde(rename = "fc", skip_serializing_if = "is_default")]
+    pub fees_collected: MicroAlgos,
+
+    /// The bonus paid to the proposer on top of its share of the fees.
+    #[serde(rename = "bi", skip_serializing_if = "is_default")]
+    pub bonus: MicroAlgos,
+
+    /// The amount actually paid to the proposer from the fee sink.
+    #[serde(rename = "pp", skip_serializing_if = "is_default")]
+    pub proposer_payout: MicroAlgos,
+
     /// Rewards.
     ///
     /// When a block is applied, some amount of rewards are accrued to
@@ -59,6 +271,7 @@ pub struct BlockHeader {
     /// account to determine if it should get one more algo of rewards
     /// because compounding formed another whole config.Protocol.RewardUnit
     /// of algos.
+    #[serde(flatten)]
     pub rewards_state: RewardsState,
 
     /// Consensus protocol versioning.
@@ -67,7 +280,7 @@ pub struct BlockHeader {
     /// stored under UpgradeState.current_protocol.  The protocol version
     /// for a block can be determined without having to first decode the
     /// block and its CurrentProtocol field, and this field is present for
-    /// convenience and explicitness.  Block.Valid() checks that this field
+    /// convenience and explicitness.  `Block::valid` checks that this field
     /// correctly matches the expected protocol version.
     ///
     /// Each block is associated with at most one active upgrade proposal
@@ -91,8 +304,9 @@ pub struct BlockHeader {
     /// Once a block proposer determines its UpgradeVote, then UpdateState
     /// is updated deterministically based on the previous UpdateState and
     /// the new block's UpgradeVote.
-    #[serde(skip)]
+    #[serde(flatten)]
     pub upgrade_state: UpgradeState,
+    #[serde(flatten)]
     pub upgrade_vote: UpgradeVote,
 
     /// Counts the number of transactions committed in the ledger,
@@ -100,51 +314,219 @@ pub struct BlockHeader {
     ///
     /// Specifically, `tx_counter` is the number of the next transaction that will be committed after this block.
     /// It is 0 when no transactions have ever been committed (since `tx_counter` started being supported).
+    #[serde(rename = "tc", skip_serializing_if = "is_default")]
     pub tx_counter: u64,
+
+    /// Tracks the rounds and voters of the next state proof, by state proof type.
+    #[serde(
+        rename = "spt",
+        skip_serializing_if = "is_default",
+        deserialize_with = "bounded::map::<_, _, _, MAX_STATE_PROOF_TYPES>"
+    )]
+    pub state_proof_tracking: BTreeMap<u64, StateProofTrackingData>,
+
+    #[serde(flatten)]
+    pub participation_updates: ParticipationUpdates,
 }
 
+/// Maximum number of state proof types tracked in a decoded header, of which only `0` is defined.
+const MAX_STATE_PROOF_TYPES: usize = 1;
+
+/// Maximum number of accounts in each list of a decoded `ParticipationUpdates`,
+/// go-algorand's `MaxProposedExpiredOnlineAccounts` and `MaxMarkAbsent`.
+const MAX_PARTICIPATION_UPDATES: usize = 32;
+
+/// The state of the next state proof of some type, as tracked in block headers.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateProofTrackingData {
+    /// Commitment to the voters of the next state proof.
+    #[serde(
+        rename = "v",
+        skip_serializing_if = "is_default",
+        with = "encoding::bytes"
+    )]
+    pub voters_commitment: Vec<u8>,
+
+    /// The total online stake at the time the voters were chosen.
+    #[serde(rename = "t", skip_serializing_if = "is_default")]
+    pub online_total_weight: MicroAlgos,
+
+    /// The round of the next expected state proof.
+    #[serde(rename = "n", skip_serializing_if = "is_default")]
+    pub next_round: Round,
+}
+
+/// Accounts whose participation the block's proposer has changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticipationUpdates {
+    /// Online accounts whose participation keys have expired, which are taken offline.
+    #[serde(
+        rename = "partupdrmv",
+        skip_serializing_if = "is_default",
+        deserialize_with = "bounded::vec::<_, _, MAX_PARTICIPATION_UPDATES>"
+    )]
+    pub expired_participation_accounts: Vec<Address>,
+
+    /// Online accounts that haven't proposed for too long, which are suspended.
+    #[serde(
+        rename = "partupdabs",
+        skip_serializing_if = "is_default",
+        deserialize_with = "bounded::vec::<_, _, MAX_PARTICIPATION_UPDATES>"
+    )]
+    pub absent_participation_accounts: Vec<Address>,
+}
+
+/// Maximum number of transactions in a decoded payset.
+const MAX_PAYSET_LEN: usize = 100_000;
+
+/// Maximum number of key/value pairs in a decoded `StateDelta`,
+/// the largest number of entries in any application's state schema.
+const MAX_STATE_DELTA_KEYS: usize = 64;
+
+/// Maximum number of shared accounts in a decoded `EvalDelta`.
+/// The protocol bounds them far lower, this only guards allocations.
+const MAX_EVAL_DELTA_ACCOUNTS: usize = 1024;
+
 /// Represents a common, unforgeable, consistent, ordered set of `SignedTxInBlock` objects.
-//msgp:allocbound Payset 100000
-#[derive(Clone, Default, Serialize, Deserialize)]
+#[derive(Clone, Default, PartialEq, Eq, Serialize)]
 #[serde(transparent)]
 pub struct Payset(pub Vec<SignedTxInBlock>);
 
+impl<'de> Deserialize<'de> for Payset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        bounded::vec::<_, _, MAX_PAYSET_LEN>(deserializer).map(Payset)
+    }
+}
+
+/// How the transactions of a block are committed to in its header's `tx_root`,
+/// as determined by the block's consensus protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaysetCommitType {
+    /// Hash of the encoded payset.
+    Flat,
+    /// Root of a vector commitment over the transactions, allowing proofs of inclusion.
+    Merkle,
+}
+
+impl Payset {
+    /// Computes the commitment to the payset.
+    ///
+    /// For the Merkle commitment, the transactions' IDs must be computable,
+    /// so they must contain their genesis ID and hash (see `Block::commit_payset`).
+    pub fn commit(&self, commit_type: PaysetCommitType) -> Digest {
+        match commit_type {
+            PaysetCommitType::Flat => {
+                // an empty payset is encoded as nil, as go-algorand does outside of genesis
+                let encoded = if self.0.is_empty() {
+                    vec![0xc0]
+                } else {
+                    encoding::to_msgpack(self)
+                };
+                let mut hasher = Sha512_256::new();
+                hasher.update(PAYSET_FLAT_PREFIX);
+                hasher.update(encoded);
+                hasher.finalize().into()
+            }
+            PaysetCommitType::Merkle => {
+                let leaves: Vec<Digest> = self
+                    .0
+                    .iter()
+                    .map(|stib| stib.merkle_leaf(&stib.sig_txad.tx.tx))
+                    .collect();
+                merkle::vector_commitment(&leaves)
+            }
+        }
+    }
+}
+
 /// RewardsState represents the global parameters controlling the rate at which accounts accrue rewards.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RewardsState {
     /// The fee sink accepts transaction fees.
     /// It can only spend to the incentive pool.
+    #[serde(rename = "fees", skip_serializing_if = "is_default")]
     pub fee_sink: Address,
 
     /// The rewards pool accepts periodic injections from the fee sink
     /// and continually redistributes them to adresses as rewards.
+    #[serde(rename = "rwd", skip_serializing_if = "is_default")]
     pub rewards_pool: Address,
 
     /// Specifies how many rewards, in MicroAlgos, have been distributed to each
     /// config.protocol.reward_unit of MicroAlgos since genesis.
+    #[serde(rename = "earn", skip_serializing_if = "is_default")]
     pub rewards_level: u64,
 
     /// Number of new MicroAlgos added to the participation stake from rewards at the next round.
+    #[serde(rename = "rate", skip_serializing_if = "is_default")]
     pub rewards_rate: u64,
 
     /// Leftover MicroAlgos after the distribution of rewards_rate/reward_units
     /// MicroAlgos for every reward unit in the next round.
+    #[serde(rename = "frac", skip_serializing_if = "is_default")]
     pub rewards_residue: u64,
 
     /// The round at which the RewardsRate will be recalculated.
+    #[serde(rename = "rwcalr", skip_serializing_if = "is_default")]
     pub rewards_recalculation_round: basics::Round,
 }
 
+impl RewardsState {
+    /// Computes the rewards accrued by an account with `balance` since rewards level `prev_level`.
+    ///
+    /// Rewards are earned for every whole `reward_unit` of the balance, saturating on overflow.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reward_unit` is zero.
+    pub fn rewards_for(
+        &self,
+        prev_level: u64,
+        balance: MicroAlgos,
+        reward_unit: u64,
+    ) -> MicroAlgos {
+        let units = balance.0 / reward_unit;
+        MicroAlgos(
+            self.rewards_level
+                .saturating_sub(prev_level)
+                .saturating_mul(units),
+        )
+    }
+
+    /// Checks that the fee sink and rewards pool are the ones defined by the network's genesis.
+    pub fn validate(&self, genesis: &Genesis) -> Result<(), RewardsError> {
+        if self.fee_sink != genesis.fee_sink {
+            return Err(RewardsError::FeeSinkMismatch {
+                expected: genesis.fee_sink,
+                actual: self.fee_sink,
+            });
+        } else if self.rewards_pool != genesis.rewards_pool {
+            return Err(RewardsError::RewardsPoolMismatch {
+                expected: genesis.rewards_pool,
+                actual: self.rewards_pool,
+            });
+        }
+        Ok(())
+    }
+}
+
 /// Represents the vote of the block proposer with respect to protocol upgrades.
 #[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UpgradeVote {
     /// UpgradePropose indicates a proposed upgrade
+    #[serde(rename = "upgradeprop", skip_serializing_if = "is_default")]
     pub upgrade_propose: String,
 
     /// UpgradeDelay indicates the time between acceptance and execution
+    #[serde(rename = "upgradedelay", skip_serializing_if = "is_default")]
     pub upgrade_delay: basics::Round,
 
     /// UpgradeApprove indicates a yes vote for the current proposal
+    #[serde(rename = "upgradeyes", skip_serializing_if = "is_default")]
     pub upgrade_approve: bool,
 }
 
@@ -153,21 +535,35 @@ pub struct UpgradeVote {
 /// but we keep it in the block for explicitness and convenience
 /// (instead of materializing it separately, like balances).
 #[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UpgradeState {
-    #[serde(rename = "proto")]
+    #[serde(rename = "proto", skip_serializing_if = "is_default")]
     pub current_protocol: String,
-    #[serde(rename = "nextproto")]
+    #[serde(rename = "nextproto", skip_serializing_if = "is_default")]
     pub next_protocol: Option<String>,
-    #[serde(rename = "nextyes")]
+    #[serde(rename = "nextyes", skip_serializing_if = "is_default")]
     pub next_protocol_approvals: u64,
-    #[serde(rename = "nextbefore")]
+    #[serde(rename = "nextbefore", skip_serializing_if = "is_default")]
     pub next_protocol_vote_before: basics::Round,
-    #[serde(rename = "nextswitch")]
+    #[serde(rename = "nextswitch", skip_serializing_if = "is_default")]
     pub next_protocol_switch_on: basics::Round,
 }
 
+impl UpgradeState {
+    /// Returns the protocol of the block at `round`, the round after the one with this state.
+    ///
+    /// This is the next protocol if an approved upgrade switches over at `round`,
+    /// and the current protocol otherwise.
+    fn protocol_at(&self, round: Round) -> &str {
+        match &self.next_protocol {
+            Some(next) if round == self.next_protocol_switch_on => next,
+            _ => &self.current_protocol,
+        }
+    }
+}
+
 /// How a signed transaction is encoded in a block.
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SignedTxInBlock {
     #[serde(flatten)]
     pub sig_txad: SignedTxWithAD,
@@ -178,6 +574,40 @@ pub struct SignedTxInBlock {
     pub has_genesis_hash: bool,
 }
 
+impl SignedTxInBlock {
+    /// Computes the SHA512_256 hash of the `"STIB"` prefixed encoding.
+    pub fn hash(&self) -> Digest {
+        crypto::hash_with_prefix(SIGNED_TX_IN_BLOCK_PREFIX, self)
+    }
+
+    /// Returns the transaction with the genesis ID and hash it omits in the block
+    /// restored from the block's `header`.
+    pub(crate) fn restored_tx(&self, header: &BlockHeader) -> Transaction {
+        let mut tx = self.sig_txad.tx.tx.clone();
+        self.restore_genesis(&mut tx, header);
+        tx
+    }
+
+    fn restore_genesis(&self, tx: &mut Transaction, header: &BlockHeader) {
+        if self.has_genesis_id {
+            tx.header.genesis_id = header.genesis_id.clone();
+        }
+        if self.has_genesis_hash {
+            tx.header.genesis_hash = header.genesis_hash;
+        }
+    }
+
+    /// Computes the leaf of the Merkle commitment to the payset for this transaction,
+    /// committing to both the ID of `tx`, the full transaction, and its encoding in the block.
+    pub(crate) fn merkle_leaf(&self, tx: &Transaction) -> Digest {
+        let mut hasher = Sha512_256::new();
+        hasher.update(TX_MERKLE_LEAF_PREFIX);
+        hasher.update(tx.hash());
+        hasher.update(self.hash());
+        hasher.finalize().into()
+    }
+}
+
 /// A (decoded) SignedTx with associated ApplyData.
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SignedTxWithAD {
@@ -188,14 +618,14 @@ pub struct SignedTxWithAD {
 }
 
 /// Contains information about the transaction's execution.
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ApplyData {
     /// Closing amount for transaction.
     #[serde(rename = "ca", default, skip_serializing_if = "is_default")]
     pub closing_amount: MicroAlgos,
 
     /// Closing amount for asset transaction.
-    #[serde(rename = "ca", default, skip_serializing_if = "is_default")]
+    #[serde(rename = "aca", default, skip_serializing_if = "is_default")]
     pub asset_closing_amount: u64,
 
     // Rewards applied to the Sender, Receiver, and CloseRemainderTo accounts.
@@ -216,51 +646,1043 @@ pub struct ApplyData {
 
 #[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EvalDelta {
-    #[serde(rename = "gd", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "gd",
+        default,
+        skip_serializing_if = "is_default",
+        serialize_with = "str_bytes::keys::serialize",
+        deserialize_with = "str_bytes::keys::bounded::<_, _, MAX_STATE_DELTA_KEYS>"
+    )]
     pub global_delta: StateDelta,
 
     /// When decoding EvalDeltas, the integer key represents an offset into
-    /// `[tx.Sender, tx.Accounts[0], tx.Accounts[1], ...]`.
-    #[serde(rename = "ld", default, skip_serializing_if = "is_default")]
-    pub local_deltas: HashMap<u64, StateDelta>,
+    /// `[tx.Sender, tx.Accounts[0], tx.Accounts[1], ..., SharedAccts[0], SharedAccts[1], ...]`.
+    #[serde(
+        rename = "ld",
+        default,
+        skip_serializing_if = "is_default",
+        with = "local_deltas"
+    )]
+    pub local_deltas: BTreeMap<u64, StateDelta>,
 
-    #[serde(rename = "lg", default, skip_serializing_if = "is_default")]
-    pub logs: Vec<String>,
+    /// Accounts with local deltas that the transaction doesn't list in its accounts,
+    /// e.g. because they were made available by another transaction of the group.
+    #[serde(
+        rename = "sa",
+        default,
+        skip_serializing_if = "is_default",
+        deserialize_with = "bounded::vec::<_, _, MAX_EVAL_DELTA_ACCOUNTS>"
+    )]
+    pub shared_accounts: Vec<Address>,
+
+    /// Messages logged by the application, which are arbitrary bytes.
+    #[serde(
+        rename = "lg",
+        default,
+        skip_serializing_if = "is_default",
+        with = "byte_vecs"
+    )]
+    pub logs: Vec<Vec<u8>>,
 
     #[serde(rename = "itx", default, skip_serializing_if = "is_default")]
     pub inner_txs: Vec<SignedTxWithAD>,
 }
 
 // StateDelta is a map from key/value store keys to ValueDeltas, indicating
-// what should happen for that key
-//msgp:allocbound StateDelta config.MaxStateDeltaKeys
-pub type StateDelta = HashMap<String, ValueDelta>;
+// what should happen for that key. Keys are ordered, so iteration is deterministic.
+pub type StateDelta = BTreeMap<Vec<u8>, ValueDelta>;
+
+/// (De)serializes the local deltas of an `EvalDelta`, bounding the size of each `StateDelta`.
+mod local_deltas {
+    use alloc::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{StateDelta, MAX_STATE_DELTA_KEYS};
+    use crate::encoding::str_bytes;
+
+    struct KeyedStateDelta(StateDelta);
+
+    struct KeyedStateDeltaRef<'a>(&'a StateDelta);
+
+    impl Serialize for KeyedStateDeltaRef<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            str_bytes::keys::serialize(self.0, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for KeyedStateDelta {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            str_bytes::keys::bounded::<_, _, MAX_STATE_DELTA_KEYS>(deserializer)
+                .map(KeyedStateDelta)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        deltas: &BTreeMap<u64, StateDelta>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            deltas
+                .iter()
+                .map(|(i, delta)| (i, KeyedStateDeltaRef(delta))),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<u64, StateDelta>, D::Error> {
+        let deltas = BTreeMap::<u64, KeyedStateDelta>::deserialize(deserializer)?;
+        Ok(deltas.into_iter().map(|(i, d)| (i, d.0)).collect())
+    }
+}
+
+impl EvalDelta {
+    /// Maps the local deltas to the addresses of the accounts they apply to,
+    /// given the `sender` and `fields` of the application call that caused them.
+    ///
+    /// Offsets past the transaction's accounts refer to the shared accounts.
+    /// Fails if any offset refers to no account at all.
+    pub fn resolve_local_deltas(
+        &self,
+        sender: &Address,
+        fields: &AppCallFields,
+    ) -> Result<BTreeMap<Address, StateDelta>, EvalDeltaError> {
+        let mut resolved = BTreeMap::<Address, StateDelta>::new();
+        for (&offset, delta) in &self.local_deltas {
+            let address = match offset {
+                0 => Some(sender),
+                i => usize::try_from(i - 1)
+                    .ok()
+                    .and_then(|i| fields.accounts.iter().chain(&self.shared_accounts).nth(i)),
+            };
+            let address = address.ok_or(EvalDeltaError::UnknownAccountOffset(offset))?;
+            // an account may be referenced more than once
+            resolved.entry(*address).or_default().extend(delta.clone());
+        }
+        Ok(resolved)
+    }
+}
 
 /// Links a DeltaAction with a value to be set.
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ValueDelta {
     #[serde(rename = "at", default, skip_serializing_if = "is_default")]
     pub action: DeltaAction,
-    #[serde(rename = "bs", default, skip_serializing_if = "is_default")]
-    pub bytes: String,
+    #[serde(
+        rename = "bs",
+        default,
+        skip_serializing_if = "is_default",
+        with = "str_bytes"
+    )]
+    pub bytes: Vec<u8>,
     #[serde(rename = "ui", default, skip_serializing_if = "is_default")]
     pub uint: u64,
 }
 
+impl ValueDelta {
+    /// Returns the byte slice stored at the key, if the action is `SetBytes`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.action {
+            DeltaAction::SetBytes => Some(&self.bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the uint stored at the key, if the action is `SetUint`.
+    pub fn as_uint(&self) -> Option<u64> {
+        match self.action {
+            DeltaAction::SetUint => Some(self.uint),
+            _ => None,
+        }
+    }
+}
+
 /// Actions that may be performed when applying a delta to a TEAL key/value store.
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Default, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
 pub enum DeltaAction {
-    Invalid,
+    #[default]
+    Invalid = 0,
     /// Indicates that a TEAL byte slice should be stored at a key.
-    SetBytes,
+    SetBytes = 1,
     /// Indicates that a Uint should be stored at a key.
-    SetUint,
+    SetUint = 2,
     /// Indicates that the value for a particular key should be deleted.
-    Delete,
+    Delete = 3,
 }
 
-impl Default for DeltaAction {
-    fn default() -> Self {
-        DeltaAction::Invalid
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use data_encoding::BASE64;
+    use ed25519_dalek::{Signer, Verifier};
+    use rmpv::Value;
+
+    use crate::account::Account;
+
+    /// Header of mainnet round 1, reduced to its genesis, protocol, and rewards fields.
+    const MAINNET_HEADER: &str = "h6RmZWVzxCDH/M2yWPDUGJwr+LbWjuaXUIZCsK0AHzH8uRjDVLqFmqNnZW6sbWFpbm5ldC12MS4womdoxCDAYcTY/B293tLXYEvkVo4/bQQZh6w3veS2ILWrOSSK36Vwcm90b9lZaHR0cHM6Ly9naXRodWIuY29tL2FsZ29yYW5kZm91bmRhdGlvbi9zcGVjcy90cmVlLzU2MTVhZGMzNmJhZDYxMGM3ZjE2NWZhMjk2N2Y0ZWNmYTc1MTI1ZjCjcm5kAaZyd2NhbHLOAAehIKNyd2TEIP7/////////////////////////////////////////";
+
+    #[test]
+    fn decode_mainnet_header() {
+        let block = Block::from_base64(MAINNET_HEADER).unwrap();
+        let header = &block.header;
+
+        assert_eq!(header.round, Round(1));
+        assert_eq!(header.genesis_id, "mainnet-v1.0");
+        assert_eq!(
+            header.upgrade_state.current_protocol,
+            "https://github.com/algorandfoundation/specs/tree/5615adc36bad610c7f165fa2967f4ecfa75125f0"
+        );
+        assert_eq!(header.upgrade_state.next_protocol, None);
+        assert_eq!(
+            header.rewards_state.fee_sink,
+            Address::from_str("Y76M3MSY6DKBRHBL7C3NNDXGS5IIMQVQVUAB6MP4XEMMGVF2QWNPL226CA")
+                .unwrap()
+        );
+        assert_eq!(
+            header.rewards_state.rewards_recalculation_round,
+            Round(500_000)
+        );
+        assert!(block.payset.0.is_empty());
+
+        let encoded = encoding::to_msgpack(&block);
+        assert_eq!(BASE64.encode(&encoded), MAINNET_HEADER);
+    }
+
+    // TODO check a complete mainnet header against its published hash, i.e. the next block's `prev`
+    #[test]
+    fn reduced_mainnet_header_hash() {
+        // SHA-512/256 of "BH" and the fixture, computed independently of this crate
+        let expected = "Gz65535tBaLKh6U6qsjxq7E09xAOwFmJsQwYEsQcOM4=";
+
+        // a block without transactions is encoded exactly like its header
+        let block = Block::from_base64(MAINNET_HEADER).unwrap();
+        assert_eq!(BASE64.encode(&block.header.hash()), expected);
+        assert_eq!(BASE64.encode(&block.hash()), expected);
+    }
+
+    #[test]
+    fn header_with_current_fields() {
+        let entry = |key: &str, value: Value| (Value::from(key), value);
+        let tracking = Value::Map(vec![(
+            Value::from(0),
+            Value::Map(vec![
+                entry("n", Value::from(512)),
+                entry("t", Value::from(7_000_000)),
+                entry("v", Value::from(&[6; 32][..])),
+            ]),
+        )]);
+        let fields = vec![
+            entry("bi", Value::from(10_000_000)),
+            entry("fc", Value::from(3000)),
+            entry("gen", Value::from("mainnet-v1.0")),
+            entry("gh", Value::from(&[7; 32][..])),
+            entry("partupdabs", Value::Array(vec![Value::from(&[8; 32][..])])),
+            entry("partupdrmv", Value::Array(vec![Value::from(&[9; 32][..])])),
+            entry("pp", Value::from(10_001_500)),
+            entry("prev", Value::from(&[1; 32][..])),
+            entry("prev512", Value::from(&[2; 64][..])),
+            entry("proto", Value::from("future")),
+            entry("prp", Value::from(&[3; 32][..])),
+            entry("rnd", Value::from(500)),
+            entry("seed", Value::from(&[4; 32][..])),
+            entry("spt", tracking),
+            entry("tc", Value::from(123_456)),
+            entry("ts", Value::from(1_700_000_000)),
+            entry("txn", Value::from(&[5; 32][..])),
+            entry("txn256", Value::from(&[6; 32][..])),
+            entry("txn512", Value::from(&[7; 64][..])),
+        ];
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &Value::Map(fields.clone())).unwrap();
+
+        let block = Block::from_msgpack(&bytes).unwrap();
+        let header = &block.header;
+        assert_eq!(header.branch_512, [2; 64]);
+        assert_eq!(header.tx_root_sha256, [6; 32]);
+        assert_eq!(header.tx_root_sha512, [7; 64]);
+        assert_eq!(header.proposer, Address([3; 32]));
+        assert_eq!(header.fees_collected, MicroAlgos(3000));
+        assert_eq!(header.bonus, MicroAlgos(10_000_000));
+        assert_eq!(header.proposer_payout, MicroAlgos(10_001_500));
+        assert_eq!(header.state_proof_tracking[&0].next_round, Round(512));
+        assert_eq!(
+            header.participation_updates.expired_participation_accounts,
+            [Address([9; 32])]
+        );
+        assert_eq!(
+            header.participation_updates.absent_participation_accounts,
+            [Address([8; 32])]
+        );
+        assert_eq!(encoding::to_msgpack(&block), bytes);
+        let expected: Digest = Sha512_256::new()
+            .chain_update(BLOCK_HEADER_PREFIX)
+            .chain_update(&bytes)
+            .finalize()
+            .into();
+        assert_eq!(block.hash(), expected);
+
+        // a header field this crate doesn't know of would make the hash wrong
+        let mut unknown = fields;
+        unknown.push(entry("zz", Value::from(1)));
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &Value::Map(unknown)).unwrap();
+        assert_eq!(
+            Block::from_msgpack(&bytes).err(),
+            Some(DecodeError::NonCanonical(
+                r#"unknown field "zz""#.to_owned()
+            ))
+        );
+    }
+
+    fn stib(amount: u64) -> SignedTxInBlock {
+        let tx = Transaction {
+            header: Header {
+                sender: Address([1; 32]),
+                fee: MicroAlgos(1000),
+                first_valid: Round(1),
+                last_valid: Round(1001),
+                genesis_hash: [7; 32],
+                ..Default::default()
+            },
+            fields: TxFields::Payment(PaymentFields {
+                receiver: Address([2; 32]),
+                amount: MicroAlgos(amount),
+                ..Default::default()
+            }),
+        };
+        SignedTxInBlock {
+            sig_txad: SignedTxWithAD {
+                tx: SignedTx {
+                    sig: Signature::default(),
+                    msig: None,
+                    lsig: None,
+                    tx,
+                    auth_addr: Address::default(),
+                },
+                ad: ApplyData {
+                    closing_amount: MicroAlgos(0),
+                    asset_closing_amount: 0,
+                    sender_rewards: MicroAlgos(0),
+                    receiver_rewards: MicroAlgos(0),
+                    close_rewards: MicroAlgos(0),
+                    eval_delta: EvalDelta::default(),
+                    config_asset: 0,
+                    application_id: 0,
+                },
+            },
+            has_genesis_id: false,
+            has_genesis_hash: false,
+        }
+    }
+
+    fn hash_with_prefix(prefix: &[u8], parts: &[&[u8]]) -> Digest {
+        let mut hasher = Sha512_256::new();
+        hasher.update(prefix);
+        parts.iter().for_each(|part| hasher.update(part));
+        hasher.finalize().into()
+    }
+
+    #[test]
+    fn flat_payset_commitment() {
+        let empty = Payset::default();
+        assert_eq!(
+            empty.commit(PaysetCommitType::Flat),
+            hash_with_prefix(b"PF", &[&[0xc0]])
+        );
+
+        let payset = Payset(vec![stib(1), stib(2)]);
+        assert_eq!(
+            payset.commit(PaysetCommitType::Flat),
+            hash_with_prefix(b"PF", &[&encoding::to_msgpack(&payset)])
+        );
+    }
+
+    #[test]
+    fn merkle_payset_commitment() {
+        let stibs: Vec<_> = (1..=3).map(stib).collect();
+        let leaves: Vec<Digest> = stibs
+            .iter()
+            .map(|stib| {
+                let stib_hash = hash_with_prefix(b"STIB", &[&encoding::to_msgpack(stib)]);
+                hash_with_prefix(b"TL", &[&stib.sig_txad.tx.tx.hash(), &stib_hash])
+            })
+            .collect();
+
+        assert_eq!(
+            Payset::default().commit(PaysetCommitType::Merkle),
+            Digest::default()
+        );
+        assert_eq!(
+            Payset(stibs[..1].to_vec()).commit(PaysetCommitType::Merkle),
+            leaves[0]
+        );
+        // leaves are placed at the bit-reversal of their index, padded with the hash of "MB"
+        let bottom: Digest = Sha512_256::digest(b"MB").into();
+        let left = hash_with_prefix(b"MA", &[&leaves[0], &leaves[2]]);
+        let right = hash_with_prefix(b"MA", &[&leaves[1], &bottom]);
+        assert_eq!(
+            Payset(stibs).commit(PaysetCommitType::Merkle),
+            hash_with_prefix(b"MA", &[&left, &right])
+        );
+    }
+
+    #[test]
+    fn tx_proof() {
+        let stibs: Vec<_> = (1..=3).map(stib).collect();
+        let leaves: Vec<Digest> = stibs
+            .iter()
+            .map(|stib| stib.merkle_leaf(&stib.sig_txad.tx.tx))
+            .collect();
+        let root = Payset(stibs.clone()).commit(PaysetCommitType::Merkle);
+
+        // the leaf of index 1 is at position 2, next to the padding
+        let left = hash_with_prefix(b"MA", &[&leaves[0], &leaves[2]]);
+        let proof = [Sha512_256::digest(b"MB").into(), left];
+        assert!(merkle::verify_tx_proof(&stibs[1], &proof, 1, &root));
+        assert!(!merkle::verify_tx_proof(&stibs[1], &proof, 2, &root));
+        assert!(!merkle::verify_tx_proof(&stibs[1], &proof, 1 << 40, &root));
+        assert!(!merkle::verify_tx_proof(&stibs[2], &proof, 1, &root));
+    }
+
+    #[test]
+    fn tx_proof_restores_genesis() {
+        let mut stripped = stib(1);
+        stripped.sig_txad.tx.tx.header.genesis_hash = Digest::default();
+        stripped.has_genesis_hash = true;
+        let mut block = Block {
+            header: BlockHeader {
+                genesis_hash: [7; 32],
+                ..Default::default()
+            },
+            payset: Payset(vec![stripped.clone(), stib(2)]),
+        };
+        block.header.tx_root = block.commit_payset(PaysetCommitType::Merkle);
+
+        let proof = [stib(2).merkle_leaf(&stib(2).sig_txad.tx.tx)];
+        assert!(merkle::verify_tx_proof_in(
+            &block.header,
+            &stripped,
+            &proof,
+            0
+        ));
+        assert!(!merkle::verify_tx_proof(
+            &stripped,
+            &proof,
+            0,
+            &block.header.tx_root
+        ));
+    }
+
+    #[test]
+    fn commit_payset_restores_genesis() {
+        let mut stripped = stib(1);
+        stripped.sig_txad.tx.tx.header.genesis_hash = Digest::default();
+        stripped.has_genesis_hash = true;
+        let block = Block {
+            header: BlockHeader {
+                genesis_hash: [7; 32],
+                ..Default::default()
+            },
+            payset: Payset(vec![stripped.clone()]),
+        };
+
+        // the ID is that of the full transaction, the hash that of the stripped one
+        let leaf = hash_with_prefix(b"TL", &[&stib(1).sig_txad.tx.tx.hash(), &stripped.hash()]);
+        assert_eq!(block.commit_payset(PaysetCommitType::Merkle), leaf);
+        assert_eq!(
+            block.commit_payset(PaysetCommitType::Flat),
+            block.payset.commit(PaysetCommitType::Flat)
+        );
+    }
+
+    #[test]
+    fn transactions_restore_genesis() {
+        let account = Account::from_seed(&[1; 32]);
+        let mut signed = stib(1);
+        signed.sig_txad.tx = account.sign_transaction(&Transaction {
+            header: Header {
+                sender: account.address(),
+                genesis_id: "testnet-v1.0".to_owned(),
+                ..signed.sig_txad.tx.tx.header.clone()
+            },
+            ..signed.sig_txad.tx.tx.clone()
+        });
+        let full = signed.sig_txad.tx.clone();
+
+        let mut stripped = signed;
+        stripped.sig_txad.tx.tx.header.genesis_id = String::new();
+        stripped.sig_txad.tx.tx.header.genesis_hash = Digest::default();
+        stripped.has_genesis_id = true;
+        stripped.has_genesis_hash = true;
+        let block = Block {
+            header: header(Round(1), "future"),
+            payset: Payset(vec![stripped, stib(2)]),
+        };
+
+        let txs: Vec<SignedTx> = block.transactions().collect();
+        assert_eq!(txs.len(), 2);
+        assert_eq!(txs[0], full);
+        assert_eq!(txs[0].tx.id(), full.tx.id());
+        assert!(txs[0].verify().is_ok());
+        // transactions without the flags are left as they are
+        assert_eq!(txs[1], stib(2).sig_txad.tx);
+    }
+
+    #[test]
+    fn oversized_payset() {
+        // array32 declaring 100001 elements, rejected before reading any of them
+        let result = encoding::from_msgpack::<Payset>(&[0xdd, 0x00, 0x01, 0x86, 0xa1]);
+        assert!(
+            matches!(result, Err(DecodeError::InvalidMsgpack(e)) if e.contains("at most 100000"))
+        );
+
+        // 100000 elements are allowed, so decoding fails on the missing first one
+        let result = encoding::from_msgpack::<Payset>(&[0xdd, 0x00, 0x01, 0x86, 0xa0]);
+        assert!(matches!(result, Err(DecodeError::InvalidMsgpack(e)) if !e.contains("at most")));
+    }
+
+    #[test]
+    fn oversized_state_delta() {
+        let delta = |keys: u64| {
+            let entries = (0..keys)
+                .map(|i| {
+                    let value = Value::Map(vec![(Value::from("ui"), Value::from(i))]);
+                    (Value::from(i.to_string()), value)
+                })
+                .collect();
+            let mut bytes = Vec::new();
+            rmpv::encode::write_value(&mut bytes, &Value::Map(entries)).unwrap();
+            bytes
+        };
+        let eval_delta = |gd: Vec<u8>, ld: Vec<u8>| {
+            let mut bytes = vec![0x82, 0xa2, b'g', b'd'];
+            bytes.extend(gd);
+            bytes.extend([0xa2, b'l', b'd', 0x81, 0x00]);
+            bytes.extend(ld);
+            encoding::from_msgpack::<EvalDelta>(&bytes)
+        };
+
+        let decoded = eval_delta(delta(64), delta(64)).unwrap();
+        assert_eq!(decoded.global_delta.len(), 64);
+        assert_eq!(decoded.local_deltas[&0].len(), 64);
+        assert!(eval_delta(delta(65), delta(1)).is_err());
+        assert!(eval_delta(delta(1), delta(65)).is_err());
+
+        // map32 declaring 2^32 - 1 entries
+        let huge = vec![0xdf, 0xff, 0xff, 0xff, 0xff];
+        assert!(eval_delta(huge.clone(), delta(1)).is_err());
+        assert!(eval_delta(delta(1), huge).is_err());
+    }
+
+    #[test]
+    fn decode_asset_close() {
+        let entry = |key: &str, value: Value| (Value::from(key), value);
+        let txn = Value::Map(vec![
+            entry("type", Value::from("axfer")),
+            entry("snd", Value::from(&[1; 32][..])),
+            entry("aclose", Value::from(&[2; 32][..])),
+            entry("xaid", Value::from(10)),
+            entry("fee", Value::from(1000)),
+            entry("fv", Value::from(1)),
+            entry("lv", Value::from(1001)),
+        ]);
+        let stib = Value::Map(vec![
+            entry("txn", txn),
+            entry("sig", Value::from(&[3; 64][..])),
+            entry("ca", Value::from(5)),
+            entry("aca", Value::from(7)),
+            entry("hgi", Value::from(true)),
+        ]);
+        let block = Value::Map(vec![
+            entry("rnd", Value::from(2)),
+            entry("txns", Value::Array(vec![stib])),
+        ]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &block).unwrap();
+
+        let block = Block::from_base64(&BASE64.encode(&bytes)).unwrap();
+        let stib = &block.payset.0[0];
+        assert_eq!(stib.sig_txad.ad.closing_amount, MicroAlgos(5));
+        assert_eq!(stib.sig_txad.ad.asset_closing_amount, 7);
+        assert!(stib.has_genesis_id);
+        assert!(matches!(
+            &stib.sig_txad.tx.tx.fields,
+            TxFields::AssetTransfer(fields) if fields.asset_close_to == Address([2; 32])
+        ));
+    }
+
+    #[test]
+    fn decode_state_proof() {
+        let entry = |key: &str, value: Value| (Value::from(key), value);
+        let proof = |depth: u8| {
+            Value::Map(vec![
+                entry("hsh", Value::Map(vec![entry("t", Value::from(1))])),
+                entry("pth", Value::Array(vec![Value::from(&[4; 32][..])])),
+                entry("td", Value::from(depth)),
+            ])
+        };
+        let reveal = Value::Map(vec![
+            entry(
+                "p",
+                Value::Map(vec![
+                    entry("p", Value::from(&[5; 64][..])),
+                    entry("w", Value::from(1_000_000)),
+                ]),
+            ),
+            entry(
+                "s",
+                Value::Map(vec![entry(
+                    "s",
+                    Value::Map(vec![
+                        entry("idx", Value::from(3)),
+                        entry("prf", proof(16)),
+                        entry("sig", Value::from(&[6; 1200][..])),
+                        entry(
+                            "vkey",
+                            Value::Map(vec![entry("k", Value::from(&[7; 1793][..]))]),
+                        ),
+                    ]),
+                )]),
+            ),
+        ]);
+        let txn = Value::Map(vec![
+            entry("fv", Value::from(1_000)),
+            entry("lv", Value::from(1_001)),
+            entry("snd", Value::from(&StateProofFields::sender().0[..])),
+            entry(
+                "sp",
+                Value::Map(vec![
+                    entry("P", proof(8)),
+                    entry("S", proof(9)),
+                    entry("c", Value::from(&[8; 32][..])),
+                    entry("pr", Value::Array(vec![Value::from(12)])),
+                    entry("r", Value::Map(vec![(Value::from(12), reveal)])),
+                    entry("w", Value::from(5_000_000)),
+                ]),
+            ),
+            entry(
+                "spmsg",
+                Value::Map(vec![
+                    entry("P", Value::from(2_000_000)),
+                    entry("b", Value::from(&[9; 32][..])),
+                    entry("f", Value::from(769)),
+                    entry("l", Value::from(1024)),
+                    entry("v", Value::from(&[10; 64][..])),
+                ]),
+            ),
+            entry("type", Value::from("stpf")),
+        ]);
+        let block = Value::Map(vec![
+            entry("rnd", Value::from(1_000)),
+            entry(
+                "txns",
+                Value::Array(vec![Value::Map(vec![
+                    entry("hgi", Value::from(true)),
+                    entry("txn", txn),
+                ])]),
+            ),
+        ]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &block).unwrap();
+
+        let block = Block::from_base64(&BASE64.encode(&bytes)).unwrap();
+        let tx = &block.payset.0[0].sig_txad.tx.tx;
+        let TxFields::StateProof(fields) = &tx.fields else {
+            panic!("not a state proof");
+        };
+        assert_eq!(tx.header.sender, StateProofFields::sender());
+        assert_eq!(fields.message.first_attested_round, Round(769));
+        assert_eq!(fields.message.last_attested_round, Round(1024));
+        assert_eq!(fields.state_proof.signed_weight, 5_000_000);
+        assert_eq!(fields.state_proof.part_proofs.tree_depth, 8);
+        let reveal = &fields.state_proof.reveals[&12];
+        assert_eq!(reveal.participant.weight, 1_000_000);
+        assert_eq!(reveal.sig_slot.sig.proof.hash_factory.hash_type, 1);
+        assert_eq!(reveal.sig_slot.sig.verifying_key.public_key.len(), 1793);
+
+        // re-encodes exactly, so that the payset commitment can be recomputed
+        assert_eq!(encoding::to_msgpack(&block), bytes);
+    }
+
+    #[test]
+    fn decode_heartbeat() {
+        let entry = |key: &str, value: Value| (Value::from(key), value);
+        let (vote, pk2, pk) = (
+            Account::from_seed(&[1; 32]),
+            Account::from_seed(&[2; 32]),
+            Account::from_seed(&[3; 32]),
+        );
+        let sign = |account: &Account, msg: &[u8]| {
+            Value::from(&account.keypair().sign(msg).to_bytes()[..])
+        };
+        let key = |account: &Account| Value::from(&account.public_key().to_bytes()[..]);
+        let proof = Value::Map(vec![
+            entry("p", key(&pk)),
+            entry("p1s", sign(&pk2, pk.public_key().as_bytes())),
+            entry("p2", key(&pk2)),
+            entry("p2s", sign(&vote, pk2.public_key().as_bytes())),
+            entry("s", sign(&pk, &[4; 32])),
+        ]);
+        let heartbeat = Value::Map(vec![
+            entry("a", Value::from(&vote.address().0[..])),
+            entry("kd", Value::from(10_000)),
+            entry("prf", proof),
+            entry("sd", Value::from(&[4; 32][..])),
+            entry("vid", key(&vote)),
+        ]);
+        let txn = Value::Map(vec![
+            entry("fv", Value::from(1_000)),
+            entry("hb", heartbeat),
+            entry("lv", Value::from(1_010)),
+            entry("snd", Value::from(&[5; 32][..])),
+            entry("type", Value::from("hb")),
+        ]);
+        let block = Value::Map(vec![
+            entry("rnd", Value::from(1_000)),
+            entry(
+                "txns",
+                Value::Array(vec![Value::Map(vec![
+                    entry("hgi", Value::from(true)),
+                    entry("txn", txn),
+                ])]),
+            ),
+        ]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &block).unwrap();
+
+        let block = Block::from_base64(&BASE64.encode(&bytes)).unwrap();
+        let tx = &block.payset.0[0].sig_txad.tx.tx;
+        let TxFields::Heartbeat(fields) = &tx.fields else {
+            panic!("not a heartbeat");
+        };
+        assert_eq!(tx.header.fee, MicroAlgos(0));
+        assert_eq!(fields.address, vote.address());
+        assert_eq!(fields.vote_id, VotePK(vote.public_key()));
+        assert_eq!(fields.key_dilution, 10_000);
+        assert_eq!(fields.seed, [4; 32]);
+        assert_eq!(fields.proof.pk2, pk2.public_key());
+        assert!(vote
+            .public_key()
+            .verify(pk2.public_key().as_bytes(), &fields.proof.pk2_sig.0)
+            .is_ok());
+
+        assert_eq!(encoding::to_msgpack(&block), bytes);
+
+        // the nested fields are encoded even if they are all empty
+        let empty = Transaction {
+            header: Header::default(),
+            fields: TxFields::Heartbeat(Default::default()),
+        };
+        let value = rmpv::decode::read_value(&mut &encoding::to_msgpack(&empty)[..]).unwrap();
+        assert_eq!(value["hb"], Value::Map(Vec::new()));
+    }
+
+    #[test]
+    fn reencode_eval_delta() {
+        let entry = |key: &str, value: Value| (Value::from(key), value);
+        let set_uint = |uint: u64| {
+            Value::Map(vec![
+                entry("at", Value::from(2)),
+                entry("ui", Value::from(uint)),
+            ])
+        };
+        // keys of the deltas in reverse order, as a non-canonical encoder may emit them
+        let state_delta = |keys: &[&str]| {
+            Value::Map(
+                keys.iter()
+                    .map(|&k| entry(k, set_uint(k.len() as u64)))
+                    .collect(),
+            )
+        };
+        let eval_delta = Value::Map(vec![
+            entry("gd", state_delta(&["zz", "m", "a"])),
+            entry(
+                "ld",
+                Value::Map(vec![
+                    (Value::from(2), state_delta(&["y", "b"])),
+                    (Value::from(0), state_delta(&["x", "c"])),
+                ]),
+            ),
+            entry("sa", Value::Array(vec![Value::from(&[3; 32][..])])),
+        ]);
+        let stib = Value::Map(vec![
+            entry("dt", eval_delta),
+            entry(
+                "txn",
+                Value::Map(vec![
+                    entry("apid", Value::from(5)),
+                    entry("snd", Value::from(&[1; 32][..])),
+                    entry("type", Value::from("appl")),
+                ]),
+            ),
+        ]);
+        let block = Value::Map(vec![
+            entry("rnd", Value::from(1_000)),
+            entry("txns", Value::Array(vec![stib])),
+        ]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &block).unwrap();
+
+        let decoded = encoding::from_msgpack::<Block>(&bytes).unwrap();
+        let encoded = encoding::to_msgpack(&decoded);
+        assert_ne!(encoded, bytes);
+        let delta = &decoded.payset.0[0].sig_txad.ad.eval_delta;
+        let keys: Vec<_> = delta.global_delta.keys().map(Vec::as_slice).collect();
+        assert_eq!(keys, [&b"a"[..], b"m", b"zz"]);
+        assert_eq!(delta.shared_accounts, [Address([3; 32])]);
+
+        // the canonical re-encoding is byte-stable, and so are the commitments to it
+        let redecoded = encoding::from_msgpack_strict::<Block>(&encoded).unwrap();
+        assert_eq!(encoding::to_msgpack(&redecoded), encoded);
+        for commit_type in [PaysetCommitType::Flat, PaysetCommitType::Merkle] {
+            assert_eq!(
+                redecoded.commit_payset(commit_type),
+                decoded.commit_payset(commit_type)
+            );
+        }
+    }
+
+    fn header(round: Round, protocol: &str) -> BlockHeader {
+        BlockHeader {
+            round,
+            genesis_id: "testnet-v1.0".to_owned(),
+            genesis_hash: [7; 32],
+            upgrade_state: UpgradeState {
+                current_protocol: protocol.to_owned(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn next_block(prev: &BlockHeader, protocol: &str) -> Block {
+        Block {
+            header: BlockHeader {
+                branch: prev.hash(),
+                ..header(prev.round + 1, protocol)
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn header_hash() {
+        let prev = header(Round(10), "v1");
+        let mut expected = Sha512_256::new();
+        expected.update(b"BH");
+        expected.update(encoding::to_msgpack(&prev));
+        assert_eq!(prev.hash(), <[u8; 32]>::from(expected.finalize()));
+
+        let other = BlockHeader {
+            timestamp: 1,
+            ..prev.clone()
+        };
+        assert_ne!(prev.hash(), other.hash());
+    }
+
+    #[test]
+    fn valid_block() {
+        let prev = header(Round(10), "v1");
+        let block = next_block(&prev, "v1");
+        assert_eq!(block.valid(&prev), Ok(()));
+
+        let mut wrong_round = block.clone();
+        wrong_round.header.round = Round(12);
+        assert_eq!(
+            wrong_round.valid(&prev),
+            Err(BlockError::WrongRound {
+                expected: Round(11),
+                actual: Round(12)
+            })
+        );
+
+        let mut wrong_branch = block.clone();
+        wrong_branch.header.branch[0] ^= 1;
+        assert_eq!(wrong_branch.valid(&prev), Err(BlockError::WrongBranch));
+
+        let mut wrong_id = block.clone();
+        wrong_id.header.genesis_id = "mainnet-v1.0".to_owned();
+        assert!(matches!(
+            wrong_id.valid(&prev),
+            Err(BlockError::GenesisIdMismatch { .. })
+        ));
+
+        let mut wrong_hash = block.clone();
+        wrong_hash.header.genesis_hash = [8; 32];
+        assert_eq!(
+            wrong_hash.valid(&prev),
+            Err(BlockError::GenesisHashMismatch)
+        );
+
+        let last = header(Round(u64::MAX), "v1");
+        assert_eq!(block.valid(&last), Err(BlockError::RoundOverflow));
+    }
+
+    #[test]
+    fn valid_protocol_upgrade() {
+        let mut prev = header(Round(10), "v1");
+        prev.upgrade_state.next_protocol = Some("v2".to_owned());
+        prev.upgrade_state.next_protocol_switch_on = Round(11);
+
+        assert_eq!(next_block(&prev, "v2").valid(&prev), Ok(()));
+        assert_eq!(
+            next_block(&prev, "v1").valid(&prev),
+            Err(BlockError::WrongProtocol {
+                expected: "v2".to_owned(),
+                actual: "v1".to_owned()
+            })
+        );
+
+        prev.upgrade_state.next_protocol_switch_on = Round(12);
+        assert_eq!(next_block(&prev, "v1").valid(&prev), Ok(()));
+    }
+
+    #[test]
+    fn rewards_state() {
+        let genesis: Genesis =
+            serde_json::from_str(include_str!("../../data/genesis.json")).unwrap();
+        let mut rewards = RewardsState {
+            fee_sink: genesis.fee_sink,
+            rewards_pool: genesis.rewards_pool,
+            ..Default::default()
+        };
+        assert_eq!(rewards.validate(&genesis), Ok(()));
+
+        rewards.rewards_pool = Address([3; 32]);
+        assert_eq!(
+            rewards.validate(&genesis),
+            Err(RewardsError::RewardsPoolMismatch {
+                expected: genesis.rewards_pool,
+                actual: Address([3; 32]),
+            })
+        );
+
+        rewards.fee_sink = genesis.rewards_pool;
+        assert_eq!(
+            rewards.validate(&genesis),
+            Err(RewardsError::FeeSinkMismatch {
+                expected: genesis.fee_sink,
+                actual: genesis.rewards_pool,
+            })
+        );
+    }
+
+    #[test]
+    fn rewards_for() {
+        let rewards = RewardsState {
+            rewards_level: 13,
+            ..Default::default()
+        };
+        let unit = 1_000_000;
+        assert_eq!(
+            rewards.rewards_for(10, MicroAlgos(2_999_999), unit),
+            MicroAlgos(6)
+        );
+        assert_eq!(
+            rewards.rewards_for(10, MicroAlgos(3_000_000), unit),
+            MicroAlgos(9)
+        );
+        assert_eq!(
+            rewards.rewards_for(10, MicroAlgos(999_999), unit),
+            MicroAlgos(0)
+        );
+        assert_eq!(
+            rewards.rewards_for(13, MicroAlgos(5_000_000), unit),
+            MicroAlgos(0)
+        );
+        assert_eq!(
+            rewards.rewards_for(14, MicroAlgos(5_000_000), unit),
+            MicroAlgos(0)
+        );
+
+        let rewards = RewardsState {
+            rewards_level: u64::MAX,
+            ..Default::default()
+        };
+        assert_eq!(
+            rewards.rewards_for(0, MicroAlgos(2), 1),
+            MicroAlgos(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn resolve_local_deltas() {
+        let set_uint = |uint| ValueDelta {
+            action: DeltaAction::SetUint,
+            bytes: Vec::new(),
+            uint,
+        };
+        let delta = |key: &str, uint| StateDelta::from([(key.as_bytes().to_vec(), set_uint(uint))]);
+        let eval_delta = EvalDelta {
+            local_deltas: BTreeMap::from([
+                (0, delta("a", 1)),
+                (1, delta("b", 2)),
+                (2, delta("c", 3)),
+                (3, delta("d", 4)),
+            ]),
+            shared_accounts: vec![Address([3; 32])],
+            ..Default::default()
+        };
+        let fields = AppCallFields {
+            accounts: vec![Address([2; 32]), Address([1; 32])],
+            ..Default::default()
+        };
+
+        let resolved = eval_delta
+            .resolve_local_deltas(&Address([1; 32]), &fields)
+            .unwrap();
+        assert_eq!(resolved.len(), 3);
+        assert_eq!(resolved[&Address([3; 32])][&b"d"[..]].as_uint(), Some(4));
+        let sender = &resolved[&Address([1; 32])];
+        assert_eq!(sender.len(), 2);
+        assert_eq!(sender[&b"a"[..]].as_uint(), Some(1));
+        assert_eq!(sender[&b"c"[..]].as_uint(), Some(3));
+        assert_eq!(resolved[&Address([2; 32])][&b"b"[..]].as_uint(), Some(2));
+
+        let mut unknown = eval_delta;
+        unknown.local_deltas.insert(4, delta("e", 5));
+        assert!(matches!(
+            unknown.resolve_local_deltas(&Address([1; 32]), &fields),
+            Err(EvalDeltaError::UnknownAccountOffset(4))
+        ));
+    }
+
+    #[test]
+    fn value_delta() {
+        let bytes = ValueDelta {
+            action: DeltaAction::SetBytes,
+            bytes: b"hello".to_vec(),
+            uint: 0,
+        };
+        assert_eq!(bytes.as_bytes(), Some(&b"hello"[..]));
+        assert_eq!(bytes.as_uint(), None);
+        let value = rmpv::decode::read_value(&mut &encoding::to_msgpack(&bytes)[..]).unwrap();
+        assert_eq!(value["bs"], Value::from("hello"));
+
+        // go-algorand encodes byte values as str, even if they are not UTF-8
+        let binary: ValueDelta =
+            encoding::from_msgpack(b"\x82\xa2at\x01\xa2bs\xa2\xff\x00").unwrap();
+        assert_eq!(binary.as_bytes(), Some(&[0xff, 0x00][..]));
+
+        let uint = ValueDelta {
+            action: DeltaAction::SetUint,
+            bytes: Vec::new(),
+            uint: 0,
+        };
+        assert_eq!(uint.as_bytes(), None);
+        assert_eq!(uint.as_uint(), Some(0));
+
+        let delete = ValueDelta {
+            action: DeltaAction::Delete,
+            ..bytes
+        };
+        assert_eq!(delete.as_bytes(), None);
+        assert_eq!(delete.as_uint(), None);
     }
 }
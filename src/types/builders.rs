@@ -0,0 +1,1119 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Constructors for the different kinds of transactions.
+
+use alloc::vec::Vec;
+
+use thiserror::Error;
+
+use super::applications::{
+    ENCODED_MAX_ACCOUNTS, ENCODED_MAX_APPLICATION_ARGS, ENCODED_MAX_BOXES,
+    ENCODED_MAX_FOREIGN_APPS, ENCODED_MAX_FOREIGN_ASSETS,
+};
+use super::basics::MAX_TX_NOTE_BYTES;
+use super::*;
+use crate::abi::{AbiError, AbiValue, Method};
+use crate::util::is_default;
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum BuildError {
+    #[error("first valid round {first} is after last valid round {last}")]
+    InvalidValidityRange { first: Round, last: Round },
+    #[error("invalid asset parameters: {0}")]
+    InvalidAssetParams(#[from] AssetParamsError),
+    #[error("sender must not be the zero address")]
+    ZeroSender,
+    #[error("asset ID must not be zero")]
+    ZeroAssetId,
+    #[error("clawbacks must revoke assets from a non-zero address")]
+    ZeroClawbackTarget,
+    #[error("reconfiguring would clear the asset's {0} address, which cannot be set again")]
+    ClearedAssetRole(&'static str),
+    #[error("going online requires both a vote and a selection key")]
+    MissingParticipationKeys,
+    #[error("first voting round {first} is after last voting round {last}")]
+    InvalidVoteRange { first: Round, last: Round },
+    #[error("account must not be closed to its own address")]
+    CloseToSender,
+    #[error("application ID must not be zero")]
+    ZeroAppId,
+    #[error("both the approval and the clear state program are required")]
+    MissingProgram,
+    #[error("{0} application args, more than the maximum of {ENCODED_MAX_APPLICATION_ARGS}")]
+    TooManyAppArgs(usize),
+    #[error("{0} accounts, more than the maximum of {ENCODED_MAX_ACCOUNTS}")]
+    TooManyAccounts(usize),
+    #[error("{0} foreign apps, more than the maximum of {ENCODED_MAX_FOREIGN_APPS}")]
+    TooManyForeignApps(usize),
+    #[error("{0} foreign assets, more than the maximum of {ENCODED_MAX_FOREIGN_ASSETS}")]
+    TooManyForeignAssets(usize),
+    #[error("{0} box references, more than the maximum of {ENCODED_MAX_BOXES}")]
+    TooManyBoxes(usize),
+    #[error("note is {0} bytes long, more than the maximum of {MAX_TX_NOTE_BYTES}")]
+    NoteTooLong(usize),
+    #[error("invalid ABI method args: {0}")]
+    InvalidAbiArgs(#[from] AbiError),
+}
+
+impl Transaction {
+    /// Creates a payment of `amount` from `sender` to `receiver`.
+    ///
+    /// If `close_remainder_to` is set, the sender's account is closed
+    /// and its remaining balance is sent to that address.
+    pub fn payment(
+        sender: Address,
+        receiver: Address,
+        amount: MicroAlgos,
+        close_remainder_to: Option<Address>,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        let fields = TxFields::Payment(PaymentFields {
+            receiver,
+            amount,
+            close_remainder_to,
+        });
+        Transaction::with_params(sender, fields, params)
+    }
+
+    /// Creates a payment that closes the sender's account, sending its entire balance to `close_to`.
+    pub fn close_account(
+        sender: Address,
+        close_to: Address,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if close_to == sender {
+            return Err(BuildError::CloseToSender);
+        }
+        Transaction::payment(sender, close_to, MicroAlgos(0), Some(close_to), params)
+    }
+
+    /// Creates a new asset with the given parameters, owned by `sender`.
+    pub fn asset_create(
+        sender: Address,
+        asset_params: AssetParams,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        asset_params.validate()?;
+
+        let fields = TxFields::AssetConfig(AssetConfigFields {
+            config_asset: 0,
+            asset_params,
+        });
+        Transaction::with_params(sender, fields, params)
+    }
+
+    /// Destroys the asset, which requires all of its units to be held by its creator.
+    /// The `sender` must be the asset's manager address.
+    pub fn asset_destroy(
+        sender: Address,
+        asset_id: AssetIndex,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if asset_id == 0 {
+            return Err(BuildError::ZeroAssetId);
+        }
+
+        let fields = TxFields::AssetConfig(AssetConfigFields {
+            config_asset: asset_id,
+            asset_params: AssetParams::default(),
+        });
+        Transaction::with_params(sender, fields, params)
+    }
+
+    /// Changes the manager, reserve, freeze, and clawback addresses of the asset from the ones
+    /// in `current` to the ones in `new_params`. The `sender` must be the asset's manager address.
+    ///
+    /// All other parameters are immutable, so they are ignored.
+    /// Since a cleared address can never be set again, none of the addresses set in `current`
+    /// may be zero in `new_params`, while those already cleared stay so.
+    /// To clear one deliberately, zero it in the fields of the returned transaction.
+    pub fn asset_reconfigure(
+        sender: Address,
+        asset_id: AssetIndex,
+        current: &AssetParams,
+        new_params: AssetParams,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if asset_id == 0 {
+            return Err(BuildError::ZeroAssetId);
+        }
+        let roles = [
+            ("manager", current.manager, new_params.manager),
+            ("reserve", current.reserve, new_params.reserve),
+            ("freeze", current.freeze, new_params.freeze),
+            ("clawback", current.clawback, new_params.clawback),
+        ];
+        if let Some((role, ..)) = roles
+            .iter()
+            .find(|(_, old, new)| !old.is_zero() && new.is_zero())
+        {
+            return Err(BuildError::ClearedAssetRole(role));
+        }
+
+        let fields = TxFields::AssetConfig(AssetConfigFields {
+            config_asset: asset_id,
+            asset_params: AssetParams {
+                manager: new_params.manager,
+                reserve: new_params.reserve,
+                freeze: new_params.freeze,
+                clawback: new_params.clawback,
+                ..Default::default()
+            },
+        });
+        Transaction::with_params(sender, fields, params)
+    }
+
+    /// Transfers assets as described by `fields`.
+    ///
+    /// Besides regular transfers, this covers clawbacks, where `sender` is the asset's clawback
+    /// address and `fields.asset_sender` the account the assets are taken from, as well as
+    /// closing out the sender's holdings to `fields.asset_close_to`.
+    pub fn asset_transfer(
+        sender: Address,
+        fields: AssetTransferFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        Transaction::with_params(sender, TxFields::AssetTransfer(fields), params)
+    }
+
+    /// Revokes `amount` units of the asset from `from`, sending them to `to`.
+    /// The `clawback_sender` must be the asset's clawback address.
+    pub fn asset_clawback(
+        clawback_sender: Address,
+        asset_id: AssetIndex,
+        from: Address,
+        to: Address,
+        amount: u64,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if clawback_sender.is_zero() {
+            return Err(BuildError::ZeroSender);
+        } else if asset_id == 0 {
+            return Err(BuildError::ZeroAssetId);
+        } else if from.is_zero() {
+            // this would be a regular transfer from the clawback account
+            return Err(BuildError::ZeroClawbackTarget);
+        }
+
+        let fields = AssetTransferFields {
+            transfer_asset: asset_id,
+            asset_amount: amount,
+            asset_sender: from,
+            asset_receiver: to,
+            ..Default::default()
+        };
+        Transaction::asset_transfer(clawback_sender, fields, params)
+    }
+
+    /// Allocates a slot for the asset in the sender's account, which is needed to receive it.
+    pub fn asset_opt_in(
+        sender: Address,
+        asset_id: AssetIndex,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        let fields = AssetTransferFields {
+            transfer_asset: asset_id,
+            asset_amount: 0,
+            asset_receiver: sender,
+            ..Default::default()
+        };
+        Transaction::asset_transfer(sender, fields, params)
+    }
+
+    /// Freezes or unfreezes the holdings of `asset_id` in `freeze_account`.
+    /// The `sender` must be the asset's freeze address.
+    pub fn asset_freeze(
+        sender: Address,
+        freeze_account: Address,
+        asset_id: AssetIndex,
+        frozen: bool,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if sender.is_zero() {
+            return Err(BuildError::ZeroSender);
+        } else if asset_id == 0 {
+            return Err(BuildError::ZeroAssetId);
+        }
+
+        let fields = TxFields::AssetFreeze(AssetFreezeFields {
+            freeze_account,
+            freeze_asset: asset_id,
+            asset_frozen: frozen,
+        });
+        Transaction::with_params(sender, fields, params)
+    }
+
+    /// Registers participation keys for `sender`, marking the account as online
+    /// for the rounds from `vote_first` to `vote_last`.
+    pub fn key_reg_online(
+        sender: Address,
+        vote_pk: VotePK,
+        selection_pk: VrfPK,
+        vote_first: Round,
+        vote_last: Round,
+        vote_key_dilution: u64,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if is_default(&vote_pk) || is_default(&selection_pk) {
+            return Err(BuildError::MissingParticipationKeys);
+        } else if vote_first > vote_last {
+            return Err(BuildError::InvalidVoteRange {
+                first: vote_first,
+                last: vote_last,
+            });
+        }
+
+        let fields = TxFields::Keyreg(KeyregFields {
+            vote_pk,
+            selection_pk,
+            vote_first,
+            vote_last,
+            vote_key_dilution,
+            nonparticipation: false,
+        });
+        Transaction::with_params(sender, fields, params)
+    }
+
+    /// Removes the participation keys of `sender`, marking the account as offline.
+    pub fn key_reg_offline(
+        sender: Address,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        let fields = TxFields::Keyreg(KeyregFields::default());
+        Transaction::with_params(sender, fields, params)
+    }
+
+    /// Marks `sender` as permanently non-participating, so it stops earning rewards.
+    /// This cannot be undone.
+    pub fn key_reg_nonparticipating(
+        sender: Address,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        let fields = TxFields::Keyreg(KeyregFields {
+            nonparticipation: true,
+            ..Default::default()
+        });
+        Transaction::with_params(sender, fields, params)
+    }
+
+    /// Creates a new application from the programs and schemas in `fields`.
+    ///
+    /// The `on_completion` of `fields` is kept, so the creator can opt in right away.
+    pub fn app_create(
+        sender: Address,
+        fields: AppCallFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if fields.approval_program.is_empty() || fields.clear_state_program.is_empty() {
+            return Err(BuildError::MissingProgram);
+        }
+        let on_completion = fields.on_completion.clone();
+        let fields = AppCallFields {
+            application_id: 0,
+            ..fields
+        };
+        Transaction::app_call_with(sender, on_completion, fields, params)
+    }
+
+    /// Calls the application `fields.application_id`, running its approval program.
+    pub fn app_call(
+        sender: Address,
+        fields: AppCallFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        Transaction::app_call_existing(sender, OnCompletion::NoOpOC, fields, params)
+    }
+
+    /// Opts the sender into the application, allocating its local state.
+    pub fn app_opt_in(
+        sender: Address,
+        fields: AppCallFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        Transaction::app_call_existing(sender, OnCompletion::OptInOC, fields, params)
+    }
+
+    /// Closes out the sender's local state, if the approval program allows it.
+    pub fn app_close_out(
+        sender: Address,
+        fields: AppCallFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        Transaction::app_call_existing(sender, OnCompletion::CloseOutOC, fields, params)
+    }
+
+    /// Clears the sender's local state, which always succeeds.
+    pub fn app_clear_state(
+        sender: Address,
+        fields: AppCallFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        Transaction::app_call_existing(sender, OnCompletion::ClearStateOC, fields, params)
+    }
+
+    /// Replaces the programs of the application with those in `fields`.
+    pub fn app_update(
+        sender: Address,
+        fields: AppCallFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if fields.approval_program.is_empty() || fields.clear_state_program.is_empty() {
+            return Err(BuildError::MissingProgram);
+        }
+        Transaction::app_call_existing(sender, OnCompletion::UpdateApplicationOC, fields, params)
+    }
+
+    /// Deletes the application.
+    pub fn app_delete(
+        sender: Address,
+        fields: AppCallFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        Transaction::app_call_existing(sender, OnCompletion::DeleteApplicationOC, fields, params)
+    }
+
+    /// Calls `method` of the application `fields.application_id` with `args`,
+    /// replacing the application args of `fields` with the selector and encoded args.
+    ///
+    /// Unlike `app_call`, this keeps the `on_completion` of `fields`,
+    /// since methods may also be called when opting in or closing out.
+    pub fn abi_method_call(
+        sender: Address,
+        method: &Method,
+        args: &[AbiValue],
+        fields: AppCallFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        let on_completion = fields.on_completion.clone();
+        let fields = AppCallFields {
+            application_args: method.encode_args(args)?,
+            ..fields
+        };
+        Transaction::app_call_existing(sender, on_completion, fields, params)
+    }
+
+    /// Attaches an arbitrary note of at most 1024 bytes to the transaction.
+    ///
+    /// With a fee per byte, the fee is recomputed from `params` to pay for the note.
+    pub fn with_note(
+        mut self,
+        note: Vec<u8>,
+        params: &SuggestedParams,
+    ) -> Result<Self, BuildError> {
+        if note.len() > MAX_TX_NOTE_BYTES {
+            return Err(BuildError::NoteTooLong(note.len()));
+        }
+        self.header.note = note;
+        self.reprice(params);
+        Ok(self)
+    }
+
+    /// Sets the transaction's lease, which prevents other transactions with the same
+    /// sender and lease from being confirmed until this one's last valid round.
+    ///
+    /// With a fee per byte, the fee is recomputed from `params` to pay for the lease.
+    pub fn with_lease(mut self, lease: [u8; 32], params: &SuggestedParams) -> Self {
+        self.header.lease = lease;
+        self.reprice(params);
+        self
+    }
+
+    /// Recomputes a fee per byte after the transaction grew, keeping flat fees as they are.
+    fn reprice(&mut self, params: &SuggestedParams) {
+        if !params.flat_fee {
+            self.set_fee_from_params(params);
+        }
+    }
+
+    /// Assembles a call of the existing application `fields.application_id`.
+    fn app_call_existing(
+        sender: Address,
+        on_completion: OnCompletion,
+        fields: AppCallFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if fields.application_id == 0 {
+            return Err(BuildError::ZeroAppId);
+        }
+        Transaction::app_call_with(sender, on_completion, fields, params)
+    }
+
+    /// Assembles an application call with the given `on_completion`,
+    /// checking the bounds on its arguments and references.
+    fn app_call_with(
+        sender: Address,
+        on_completion: OnCompletion,
+        fields: AppCallFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if fields.application_args.len() > ENCODED_MAX_APPLICATION_ARGS {
+            return Err(BuildError::TooManyAppArgs(fields.application_args.len()));
+        } else if fields.accounts.len() > ENCODED_MAX_ACCOUNTS {
+            return Err(BuildError::TooManyAccounts(fields.accounts.len()));
+        } else if fields.foreign_apps.len() > ENCODED_MAX_FOREIGN_APPS {
+            return Err(BuildError::TooManyForeignApps(fields.foreign_apps.len()));
+        } else if fields.foreign_assets.len() > ENCODED_MAX_FOREIGN_ASSETS {
+            return Err(BuildError::TooManyForeignAssets(
+                fields.foreign_assets.len(),
+            ));
+        } else if fields.boxes.len() > ENCODED_MAX_BOXES {
+            return Err(BuildError::TooManyBoxes(fields.boxes.len()));
+        }
+
+        let fields = TxFields::AppCall(AppCallFields {
+            on_completion,
+            ..fields
+        });
+        Transaction::with_params(sender, fields, params)
+    }
+
+    /// Assembles a transaction sent by `sender`, taking its header from `params`.
+    fn with_params(
+        sender: Address,
+        fields: TxFields,
+        params: &SuggestedParams,
+    ) -> Result<Transaction, BuildError> {
+        if params.first_round_valid > params.last_round_valid {
+            return Err(BuildError::InvalidValidityRange {
+                first: params.first_round_valid,
+                last: params.last_round_valid,
+            });
+        }
+
+        let mut tx = Transaction {
+            header: Header {
+                sender,
+                first_valid: params.first_round_valid,
+                last_valid: params.last_round_valid,
+                genesis_id: params.genesis_id.clone(),
+                genesis_hash: params.genesis_hash,
+                ..Default::default()
+            },
+            fields,
+        };
+        tx.set_fee_from_params(params);
+        Ok(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::account::Account;
+    use crate::encoding;
+
+    fn params() -> SuggestedParams {
+        SuggestedParams {
+            fee: MicroAlgos(1000),
+            genesis_id: "testnet-v1.0".to_owned(),
+            genesis_hash: [4; 32],
+            first_round_valid: Round(100),
+            last_round_valid: Round(1100),
+            flat_fee: true,
+            min_fee: MicroAlgos(1000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn payment() {
+        let tx = Transaction::payment(
+            Address([1; 32]),
+            Address([2; 32]),
+            MicroAlgos(5000),
+            None,
+            &params(),
+        )
+        .unwrap()
+        .with_note(b"hello".to_vec(), &params())
+        .unwrap()
+        .with_lease([3; 32], &params());
+
+        assert!(matches!(
+            &tx.fields,
+            TxFields::Payment(PaymentFields {
+                receiver: Address([2, ..]),
+                amount: MicroAlgos(5000),
+                close_remainder_to: None,
+            })
+        ));
+        assert_eq!(tx.header.sender, Address([1; 32]));
+        assert_eq!(tx.header.fee, MicroAlgos(1000));
+        assert_eq!(
+            (tx.header.first_valid, tx.header.last_valid),
+            (Round(100), Round(1100))
+        );
+        assert_eq!(tx.header.genesis_id, "testnet-v1.0");
+        assert_eq!(tx.header.genesis_hash, [4; 32]);
+        assert_eq!(tx.header.note, b"hello");
+        assert_eq!(tx.header.lease, [3; 32]);
+    }
+
+    #[test]
+    fn close_account() {
+        let tx = Transaction::close_account(Address([1; 32]), Address([2; 32]), &params()).unwrap();
+        assert_eq!(
+            tx.fields,
+            TxFields::Payment(PaymentFields {
+                receiver: Address([2; 32]),
+                amount: MicroAlgos(0),
+                close_remainder_to: Some(Address([2; 32])),
+            })
+        );
+        assert_eq!(tx.header.sender, Address([1; 32]));
+        assert_eq!(tx.well_formed(&params()), Ok(()));
+
+        assert_eq!(
+            Transaction::close_account(Address([1; 32]), Address([1; 32]), &params()),
+            Err(BuildError::CloseToSender)
+        );
+    }
+
+    #[test]
+    fn note_limit() {
+        let tx = Transaction::payment(
+            Address([1; 32]),
+            Address([2; 32]),
+            MicroAlgos(5000),
+            None,
+            &params(),
+        )
+        .unwrap();
+
+        let tx = tx.with_note(vec![7; 1024], &params()).unwrap();
+        assert_eq!(tx.header.note.len(), 1024);
+        assert_eq!(
+            tx.with_note(vec![7; 1025], &params()),
+            Err(BuildError::NoteTooLong(1025))
+        );
+    }
+
+    #[test]
+    fn payment_per_byte_fee() {
+        let params = SuggestedParams {
+            fee: MicroAlgos(10),
+            flat_fee: false,
+            ..params()
+        };
+        let tx = Transaction::payment(
+            Address([1; 32]),
+            Address([2; 32]),
+            MicroAlgos(5000),
+            Some(Address([3; 32])),
+            &params,
+        )
+        .unwrap();
+        assert!(tx.header.fee > MicroAlgos(1000));
+
+        let mut repriced = tx.clone();
+        repriced.set_fee_from_params(&params);
+        assert_eq!(tx.header.fee, repriced.header.fee);
+
+        // the note and lease are paid for, byte by byte
+        let with_note = tx.clone().with_note(vec![7; 100], &params).unwrap();
+        assert!(with_note.header.fee.0 >= tx.header.fee.0 + 100 * 10);
+        let with_lease = with_note.clone().with_lease([3; 32], &params);
+        assert!(with_lease.header.fee.0 >= with_note.header.fee.0 + 32 * 10);
+    }
+
+    #[test]
+    fn invalid_validity_range() {
+        let params = SuggestedParams {
+            first_round_valid: Round(1101),
+            ..params()
+        };
+        let result = Transaction::payment(
+            Address([1; 32]),
+            Address([2; 32]),
+            MicroAlgos(5000),
+            None,
+            &params,
+        );
+        assert_eq!(
+            result.err(),
+            Some(BuildError::InvalidValidityRange {
+                first: Round(1101),
+                last: Round(1100)
+            })
+        );
+    }
+
+    #[test]
+    fn asset_create() {
+        let asset_params = AssetParams {
+            total: 1_000_000,
+            decimals: 2,
+            unit_name: "TKN".to_owned(),
+            asset_name: "Token".to_owned(),
+            url: "https://example.com".to_owned(),
+            manager: Address([1; 32]),
+            ..Default::default()
+        };
+        let tx =
+            Transaction::asset_create(Address([1; 32]), asset_params.clone(), &params()).unwrap();
+        assert!(matches!(
+            &tx.fields,
+            TxFields::AssetConfig(fields)
+                if fields.config_asset == 0 && fields.asset_params == asset_params
+        ));
+
+        let result = Transaction::asset_create(
+            Address([1; 32]),
+            AssetParams {
+                decimals: 20,
+                ..asset_params
+            },
+            &params(),
+        );
+        assert_eq!(
+            result.err(),
+            Some(BuildError::InvalidAssetParams(
+                AssetParamsError::TooManyDecimals(20)
+            ))
+        );
+    }
+
+    #[test]
+    fn asset_opt_in() {
+        let sender = Address([1; 32]);
+        let tx = Transaction::asset_opt_in(sender, 10, &params()).unwrap();
+        let TxFields::AssetTransfer(fields) = &tx.fields else {
+            panic!("not an asset transfer");
+        };
+        assert_eq!(fields.transfer_asset, 10);
+        assert_eq!(fields.asset_amount, 0);
+        assert_eq!(fields.asset_receiver, sender);
+        assert_eq!(fields.classify(&sender), AssetTransferKind::OptIn);
+    }
+
+    #[test]
+    fn asset_transfer() {
+        let clawback = Address([1; 32]);
+        let fields = AssetTransferFields {
+            transfer_asset: 10,
+            asset_amount: 50,
+            asset_sender: Address([2; 32]),
+            asset_receiver: Address([3; 32]),
+            ..Default::default()
+        };
+        let tx = Transaction::asset_transfer(clawback, fields, &params()).unwrap();
+        let TxFields::AssetTransfer(fields) = &tx.fields else {
+            panic!("not an asset transfer");
+        };
+        assert_eq!(fields.classify(&clawback), AssetTransferKind::Clawback);
+    }
+
+    #[test]
+    fn asset_destroy() {
+        let tx = Transaction::asset_destroy(Address([1; 32]), 10, &params()).unwrap();
+        assert_eq!(
+            tx.fields,
+            TxFields::AssetConfig(AssetConfigFields {
+                config_asset: 10,
+                asset_params: AssetParams::default(),
+            })
+        );
+        // the zero parameters are omitted entirely
+        let encoded = encoding::to_msgpack(&tx);
+        let value = rmpv::decode::read_value(&mut &encoded[..]).unwrap();
+        assert_eq!(value["caid"].as_u64(), Some(10));
+        assert!(value["apar"].is_nil());
+
+        let zero_asset = Transaction::asset_destroy(Address([1; 32]), 0, &params());
+        assert_eq!(zero_asset.err(), Some(BuildError::ZeroAssetId));
+    }
+
+    #[test]
+    fn asset_reconfigure() {
+        let current = AssetParams {
+            total: 1000,
+            decimals: 2,
+            unit_name: "TST".to_owned(),
+            manager: Address([1; 32]),
+            reserve: Address([2; 32]),
+            freeze: Address([3; 32]),
+            clawback: Address([4; 32]),
+            ..Default::default()
+        };
+        let new_params = AssetParams {
+            manager: Address([5; 32]),
+            ..current.clone()
+        };
+        let tx =
+            Transaction::asset_reconfigure(Address([1; 32]), 10, &current, new_params, &params())
+                .unwrap();
+        assert_eq!(
+            tx.fields,
+            TxFields::AssetConfig(AssetConfigFields {
+                config_asset: 10,
+                asset_params: AssetParams {
+                    manager: Address([5; 32]),
+                    reserve: Address([2; 32]),
+                    freeze: Address([3; 32]),
+                    clawback: Address([4; 32]),
+                    ..Default::default()
+                },
+            })
+        );
+
+        let cleared_freeze = AssetParams {
+            freeze: Address::default(),
+            ..current.clone()
+        };
+        assert_eq!(
+            Transaction::asset_reconfigure(
+                Address([1; 32]),
+                10,
+                &current,
+                cleared_freeze.clone(),
+                &params()
+            )
+            .err(),
+            Some(BuildError::ClearedAssetRole("freeze"))
+        );
+
+        // roles that were never set may stay unset
+        let new_manager = AssetParams {
+            manager: Address([5; 32]),
+            ..cleared_freeze.clone()
+        };
+        let tx = Transaction::asset_reconfigure(
+            Address([1; 32]),
+            10,
+            &cleared_freeze,
+            new_manager,
+            &params(),
+        )
+        .unwrap();
+        let TxFields::AssetConfig(fields) = &tx.fields else {
+            panic!("not an asset config");
+        };
+        assert_eq!(fields.asset_params.manager, Address([5; 32]));
+        assert!(fields.asset_params.freeze.is_zero());
+    }
+
+    #[test]
+    fn asset_clawback() {
+        let (clawback, from, to) = (Address([1; 32]), Address([2; 32]), Address([3; 32]));
+        let tx = Transaction::asset_clawback(clawback, 10, from, to, 50, &params()).unwrap();
+        assert_eq!(tx.header.sender, clawback);
+        let TxFields::AssetTransfer(fields) = &tx.fields else {
+            panic!("not an asset transfer");
+        };
+        assert_eq!(
+            fields,
+            &AssetTransferFields {
+                transfer_asset: 10,
+                asset_amount: 50,
+                asset_sender: from,
+                asset_receiver: to,
+                asset_close_to: Address::default(),
+            }
+        );
+        assert_eq!(fields.classify(&clawback), AssetTransferKind::Clawback);
+
+        let zero = Address::default();
+        let clawback_with = |sender, asset_id, from| {
+            Transaction::asset_clawback(sender, asset_id, from, to, 50, &params()).err()
+        };
+        assert_eq!(clawback_with(zero, 10, from), Some(BuildError::ZeroSender));
+        assert_eq!(
+            clawback_with(clawback, 0, from),
+            Some(BuildError::ZeroAssetId)
+        );
+        assert_eq!(
+            clawback_with(clawback, 10, zero),
+            Some(BuildError::ZeroClawbackTarget)
+        );
+    }
+
+    #[test]
+    fn asset_freeze() {
+        let tx = Transaction::asset_freeze(Address([1; 32]), Address([2; 32]), 10, true, &params())
+            .unwrap();
+        assert!(matches!(
+            &tx.fields,
+            TxFields::AssetFreeze(AssetFreezeFields {
+                freeze_account: Address([2, ..]),
+                freeze_asset: 10,
+                asset_frozen: true,
+            })
+        ));
+
+        let zero_sender =
+            Transaction::asset_freeze(Address::default(), Address([2; 32]), 10, true, &params());
+        assert_eq!(zero_sender.err(), Some(BuildError::ZeroSender));
+        let zero_asset =
+            Transaction::asset_freeze(Address([1; 32]), Address([2; 32]), 0, true, &params());
+        assert_eq!(zero_asset.err(), Some(BuildError::ZeroAssetId));
+    }
+
+    #[test]
+    fn key_reg() {
+        let sender = Address([1; 32]);
+        let vote_pk = VotePK(Account::from_seed(&[2; 32]).public_key());
+        let selection_pk = VrfPK([3; 32]);
+
+        let online = Transaction::key_reg_online(
+            sender,
+            vote_pk,
+            selection_pk,
+            Round(100),
+            Round(200),
+            10,
+            &params(),
+        )
+        .unwrap();
+        let TxFields::Keyreg(fields) = &online.fields else {
+            panic!("not a key registration");
+        };
+        assert!(fields.vote_pk == vote_pk && fields.selection_pk == selection_pk);
+        assert_eq!(
+            (fields.vote_first, fields.vote_last),
+            (Round(100), Round(200))
+        );
+        assert_eq!(fields.vote_key_dilution, 10);
+        assert!(!fields.nonparticipation);
+
+        let offline = Transaction::key_reg_offline(sender, &params()).unwrap();
+        assert!(offline.fields == TxFields::Keyreg(KeyregFields::default()));
+
+        let nonpart = Transaction::key_reg_nonparticipating(sender, &params()).unwrap();
+        assert!(matches!(
+            &nonpart.fields,
+            TxFields::Keyreg(fields) if fields.nonparticipation && is_default(&fields.vote_pk)
+        ));
+    }
+
+    #[test]
+    fn key_reg_online_invalid() {
+        let sender = Address([1; 32]);
+        let vote_pk = VotePK(Account::from_seed(&[2; 32]).public_key());
+        let selection_pk = VrfPK([3; 32]);
+
+        let missing_key = Transaction::key_reg_online(
+            sender,
+            vote_pk,
+            Default::default(),
+            Round(100),
+            Round(200),
+            10,
+            &params(),
+        );
+        assert_eq!(
+            missing_key.err(),
+            Some(BuildError::MissingParticipationKeys)
+        );
+        let inverted = Transaction::key_reg_online(
+            sender,
+            vote_pk,
+            selection_pk,
+            Round(200),
+            Round(100),
+            10,
+            &params(),
+        );
+        assert_eq!(
+            inverted.err(),
+            Some(BuildError::InvalidVoteRange {
+                first: Round(200),
+                last: Round(100)
+            })
+        );
+    }
+
+    fn app_fields() -> AppCallFields {
+        AppCallFields {
+            application_id: 7,
+            approval_program: vec![0x06, 0x81, 0x01],
+            clear_state_program: vec![0x06, 0x81, 0x01],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn app_calls() {
+        let sender = Address([1; 32]);
+        let on_completion = |tx: Transaction| match tx.fields {
+            TxFields::AppCall(fields) => (fields.application_id, fields.on_completion),
+            _ => panic!("not an app call"),
+        };
+
+        let create = Transaction::app_create(sender, app_fields(), &params()).unwrap();
+        assert!(on_completion(create) == (0, OnCompletion::NoOpOC));
+        let builders = [
+            (
+                Transaction::app_call as fn(_, _, &_) -> _,
+                OnCompletion::NoOpOC,
+            ),
+            (Transaction::app_opt_in, OnCompletion::OptInOC),
+            (Transaction::app_close_out, OnCompletion::CloseOutOC),
+            (Transaction::app_clear_state, OnCompletion::ClearStateOC),
+            (Transaction::app_update, OnCompletion::UpdateApplicationOC),
+            (Transaction::app_delete, OnCompletion::DeleteApplicationOC),
+        ];
+        for (build, expected) in builders {
+            let tx = build(sender, app_fields(), &params()).unwrap();
+            assert!(on_completion(tx) == (7, expected));
+
+            let zero_id = AppCallFields {
+                application_id: 0,
+                ..app_fields()
+            };
+            assert_eq!(
+                build(sender, zero_id, &params()).err(),
+                Some(BuildError::ZeroAppId)
+            );
+        }
+    }
+
+    #[test]
+    fn app_call_args() {
+        assert_eq!(AppArg::Uint(1).into_arg(), [0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(AppArg::AppId(258).into_arg(), 258u64.to_be_bytes());
+        assert_eq!(AppArg::AssetId(u64::MAX).into_arg(), [0xff; 8]);
+        assert_eq!(AppArg::Bytes(b"hi".to_vec()).into_arg(), b"hi");
+        assert_eq!(AppArg::Address(Address([3; 32])).into_arg(), [3; 32]);
+
+        let fields = app_fields().with_args([
+            AppArg::Bytes(b"swap".to_vec()),
+            AppArg::Uint(1_000),
+            AppArg::Address(Address([3; 32])),
+        ]);
+        let tx = Transaction::app_call(Address([1; 32]), fields, &params()).unwrap();
+        let TxFields::AppCall(call) = &tx.fields else {
+            panic!("not an app call");
+        };
+        assert_eq!(
+            call.application_args,
+            [
+                b"swap".to_vec(),
+                1_000u64.to_be_bytes().to_vec(),
+                vec![3; 32]
+            ]
+        );
+
+        let too_many = app_fields().with_args((0..33).map(AppArg::Uint));
+        assert_eq!(
+            Transaction::app_call(Address([1; 32]), too_many, &params()).err(),
+            Some(BuildError::TooManyAppArgs(33))
+        );
+    }
+
+    #[test]
+    fn app_calls_invalid() {
+        let sender = Address([1; 32]);
+        let no_clear = AppCallFields {
+            clear_state_program: Vec::new(),
+            ..app_fields()
+        };
+        assert_eq!(
+            Transaction::app_create(sender, no_clear.clone(), &params()).err(),
+            Some(BuildError::MissingProgram)
+        );
+        assert_eq!(
+            Transaction::app_update(sender, no_clear, &params()).err(),
+            Some(BuildError::MissingProgram)
+        );
+
+        let call = |fields| Transaction::app_call(sender, fields, &params()).err();
+        let at_limit = AppCallFields {
+            application_args: vec![Vec::new(); 32],
+            accounts: vec![Address([2; 32]); 32],
+            foreign_apps: vec![1; 32],
+            foreign_assets: vec![1; 32],
+            ..app_fields()
+        };
+        assert_eq!(call(at_limit.clone()), None);
+
+        let mut too_many = at_limit.clone();
+        too_many.application_args.push(Vec::new());
+        assert_eq!(call(too_many), Some(BuildError::TooManyAppArgs(33)));
+        let mut too_many = at_limit.clone();
+        too_many.accounts.push(Address([2; 32]));
+        assert_eq!(call(too_many), Some(BuildError::TooManyAccounts(33)));
+        let mut too_many = at_limit.clone();
+        too_many.foreign_apps.push(1);
+        assert_eq!(call(too_many), Some(BuildError::TooManyForeignApps(33)));
+        let mut too_many = at_limit.clone();
+        too_many.foreign_assets.push(1);
+        assert_eq!(call(too_many), Some(BuildError::TooManyForeignAssets(33)));
+        let mut too_many = at_limit;
+        too_many.boxes = vec![BoxRef::default(); 33];
+        assert_eq!(call(too_many), Some(BuildError::TooManyBoxes(33)));
+    }
+
+    #[test]
+    fn app_call_boxes() {
+        let fields = AppCallFields {
+            foreign_apps: vec![9],
+            boxes: vec![
+                BoxRef {
+                    app_index: 0,
+                    name: b"own".to_vec(),
+                },
+                BoxRef {
+                    app_index: 1,
+                    name: b"foreign".to_vec(),
+                },
+            ],
+            ..app_fields()
+        };
+        let tx = Transaction::app_call(Address([1; 32]), fields, &params()).unwrap();
+
+        let encoded = encoding::to_msgpack(&tx);
+        let value = rmpv::decode::read_value(&mut &encoded[..]).unwrap();
+        let boxes = value["apbx"].as_array().unwrap();
+        assert_eq!(boxes.len(), 2);
+        // the zero index is omitted, like all default values
+        assert!(boxes[0]["i"].is_nil());
+        assert_eq!(boxes[0]["n"].as_slice(), Some(&b"own"[..]));
+        assert_eq!(boxes[1]["i"].as_u64(), Some(1));
+
+        let decoded: Transaction = encoding::from_msgpack(&encoded).unwrap();
+        assert_eq!(decoded, tx);
+    }
+
+    #[test]
+    fn abi_method_call() {
+        let method = Method::from_signature("add(uint64,uint64)uint64").unwrap();
+        let fields = AppCallFields {
+            on_completion: OnCompletion::OptInOC,
+            application_args: vec![b"replaced".to_vec()],
+            ..app_fields()
+        };
+        let args = [AbiValue::from(1), AbiValue::from(2)];
+        let tx = Transaction::abi_method_call(
+            Address([1; 32]),
+            &method,
+            &args,
+            fields.clone(),
+            &params(),
+        )
+        .unwrap();
+        let TxFields::AppCall(call) = &tx.fields else {
+            panic!("not an app call");
+        };
+        assert!(call.on_completion == OnCompletion::OptInOC);
+        assert_eq!(
+            call.application_args,
+            [
+                method.selector().to_vec(),
+                vec![0, 0, 0, 0, 0, 0, 0, 1],
+                vec![0, 0, 0, 0, 0, 0, 0, 2]
+            ]
+        );
+
+        let result =
+            Transaction::abi_method_call(Address([1; 32]), &method, &args[..1], fields, &params());
+        assert_eq!(
+            result.err(),
+            Some(BuildError::InvalidAbiArgs(AbiError::WrongArgCount {
+                expected: 2,
+                got: 1
+            }))
+        );
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+use super::*;
+
+/// Protocol version of mainnet since consensus upgrade v40.
+const CONSENSUS_V40: &str =
+    "https://github.com/algorandfoundation/specs/tree/236dcc18c9c507d794813ab768e467ea42d1b4d9";
+
+/// Protocol version of private networks running the in-development protocol.
+const CONSENSUS_FUTURE: &str = "future";
+
+/// Parameters of the current protocol version.
+pub(crate) const CURRENT_PARAMS: ConsensusParams = ConsensusParams {
+    min_txn_fee: MicroAlgos(1000),
+    max_txn_life: 1000,
+    reward_unit: 1_000_000,
+    max_assets_per_account: 0,
+    min_balance: MicroAlgos(100_000),
+    app_flat_params_min_balance: MicroAlgos(100_000),
+    app_flat_opt_in_min_balance: MicroAlgos(100_000),
+    schema_min_balance_per_entry: MicroAlgos(25_000),
+    schema_uint_min_balance: MicroAlgos(3_500),
+    schema_bytes_min_balance: MicroAlgos(25_000),
+};
+
+/// Parameters of the consensus protocol that clients need to know about.
+///
+/// The defaults are the values of the current protocol version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsensusParams {
+    /// Minimum fee of a transaction.
+    pub min_txn_fee: MicroAlgos,
+    /// Maximum number of rounds between a transaction's first and last valid round.
+    pub max_txn_life: u64,
+    /// Number of microalgos per reward unit, rewards are paid for whole units only.
+    pub reward_unit: u64,
+    /// Maximum number of assets an account can hold, or zero if unlimited.
+    pub max_assets_per_account: u64,
+
+    /// Minimum balance of every account, which is also required per asset it holds.
+    pub min_balance: MicroAlgos,
+
+    /// Minimum balance required per application an account created,
+    /// and per extra program page of those applications.
+    pub app_flat_params_min_balance: MicroAlgos,
+    /// Minimum balance required per application an account opted into.
+    pub app_flat_opt_in_min_balance: MicroAlgos,
+
+    /// Minimum balance required per key/value entry of the state schemas an account
+    /// is responsible for, plus the amount for the entry's type.
+    pub schema_min_balance_per_entry: MicroAlgos,
+    pub schema_uint_min_balance: MicroAlgos,
+    pub schema_bytes_min_balance: MicroAlgos,
+}
+
+impl Default for ConsensusParams {
+    fn default() -> Self {
+        CURRENT_PARAMS
+    }
+}
+
+/// Looks up the parameters of a protocol version, as found in `UpgradeState::current_protocol`.
+///
+/// Returns `None` for protocol versions unknown to this crate, including older ones.
+pub fn params_for(proto: &str) -> Option<&'static ConsensusParams> {
+    static CURRENT: ConsensusParams = CURRENT_PARAMS;
+    match proto {
+        CONSENSUS_V40 | CONSENSUS_FUTURE => Some(&CURRENT),
+        _ => None,
+    }
+}
+
+impl AccountData {
+    /// Computes the minimum balance of the account, below which it cannot go, from the assets
+    /// it holds, the applications it created or opted into, and their state schemas.
+    ///
+    /// Saturates at the maximum amount instead of overflowing.
+    pub fn min_balance(&self, params: &ConsensusParams) -> MicroAlgos {
+        let times = |amount: MicroAlgos, n: u64| amount.0.saturating_mul(n);
+
+        let mut schema = StateSchema::default();
+        let local_schemas = self.app_local_states.values().map(|state| &state.schema);
+        let global_schemas = self.app_params.values().map(|app| &app.global_state_schema);
+        for s in local_schemas.chain(global_schemas) {
+            schema.num_uint = schema.num_uint.saturating_add(s.num_uint);
+            schema.num_byte_slice = schema.num_byte_slice.saturating_add(s.num_byte_slice);
+        }
+        let entries = schema.num_uint.saturating_add(schema.num_byte_slice);
+        let extra_pages = self
+            .app_params
+            .values()
+            .map(|app| u64::from(app.extra_program_pages))
+            .sum::<u64>();
+
+        let total = [
+            params.min_balance.0,
+            times(params.min_balance, self.assets.len() as u64),
+            times(
+                params.app_flat_params_min_balance,
+                self.app_params.len() as u64,
+            ),
+            times(params.app_flat_params_min_balance, extra_pages),
+            times(
+                params.app_flat_opt_in_min_balance,
+                self.app_local_states.len() as u64,
+            ),
+            times(params.schema_min_balance_per_entry, entries),
+            times(params.schema_uint_min_balance, schema.num_uint),
+            times(params.schema_bytes_min_balance, schema.num_byte_slice),
+        ]
+        .into_iter()
+        .fold(0u64, u64::saturating_add);
+        MicroAlgos(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_for_protocol() {
+        let params = params_for(CONSENSUS_V40).unwrap();
+        assert_eq!(params, &ConsensusParams::default());
+        assert_eq!(params.min_txn_fee, MicroAlgos(1000));
+        assert_eq!(params.max_txn_life, 1000);
+        assert_eq!(params_for("future"), Some(params));
+
+        // genesis protocol of mainnet
+        let genesis =
+            "https://github.com/algorandfoundation/specs/tree/5615adc36bad610c7f165fa2967f4ecfa75125f0";
+        assert_eq!(params_for(genesis), None);
+        assert_eq!(params_for(""), None);
+    }
+
+    #[test]
+    fn min_balance() {
+        let params = ConsensusParams::default();
+        let mut account = AccountData::default();
+        assert_eq!(account.min_balance(&params), MicroAlgos(100_000));
+
+        account.assets.insert(1, AssetHolding::default());
+        account.assets.insert(2, AssetHolding::default());
+        assert_eq!(account.min_balance(&params), MicroAlgos(300_000));
+
+        // opted into an app with 1 uint and 1 byte slice of local state
+        let local = AppLocalState {
+            schema: StateSchema {
+                num_uint: 1,
+                num_byte_slice: 1,
+            },
+            ..Default::default()
+        };
+        account.app_local_states.insert(3, local);
+        let opted_in = 300_000 + 100_000 + 2 * 25_000 + 3_500 + 25_000;
+        assert_eq!(account.min_balance(&params), MicroAlgos(opted_in));
+
+        // created an app with 2 uints of global state and an extra program page
+        let app = AppParams {
+            global_state_schema: StateSchema {
+                num_uint: 2,
+                num_byte_slice: 0,
+            },
+            extra_program_pages: 1,
+            ..Default::default()
+        };
+        account.app_params.insert(4, app);
+        let created = opted_in + 2 * 100_000 + 2 * (25_000 + 3_500);
+        assert_eq!(account.min_balance(&params), MicroAlgos(created));
+    }
+
+    #[test]
+    fn min_balance_saturates() {
+        let params = ConsensusParams {
+            min_balance: MicroAlgos(u64::MAX / 2),
+            ..Default::default()
+        };
+        let mut account = AccountData::default();
+        account.assets.insert(1, AssetHolding::default());
+        account.assets.insert(2, AssetHolding::default());
+        assert_eq!(account.min_balance(&params), MicroAlgos(u64::MAX));
+    }
+}
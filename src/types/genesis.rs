@@ -0,0 +1,223 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use super::*;
+use crate::crypto::{self, GENESIS_PREFIX};
+use crate::util::is_default;
+
+/// The initial state of a network, as in the `genesis.json` file shipped with algod.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Genesis {
+    /// Version of the genesis, e.g. `v1.0`.
+    #[serde(rename = "id", default, skip_serializing_if = "is_default")]
+    pub schema_id: String,
+    #[serde(rename = "network", default, skip_serializing_if = "is_default")]
+    pub network: String,
+
+    /// Consensus protocol the network starts with.
+    #[serde(rename = "proto", default, skip_serializing_if = "is_default")]
+    pub proto: String,
+
+    #[serde(rename = "alloc", default, skip_serializing_if = "is_default")]
+    pub allocation: Vec<GenesisAllocation>,
+
+    #[serde(
+        rename = "rwd",
+        default,
+        skip_serializing_if = "is_default",
+        with = "address_string"
+    )]
+    pub rewards_pool: Address,
+    #[serde(
+        rename = "fees",
+        default,
+        skip_serializing_if = "is_default",
+        with = "address_string"
+    )]
+    pub fee_sink: Address,
+
+    /// Seconds since epoch.
+    #[serde(rename = "timestamp", default, skip_serializing_if = "is_default")]
+    pub timestamp: i64,
+    #[serde(rename = "comment", default, skip_serializing_if = "is_default")]
+    pub comment: String,
+    #[serde(rename = "devmode", default, skip_serializing_if = "is_default")]
+    pub dev_mode: bool,
+}
+
+/// The initial balance record of an account.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisAllocation {
+    #[serde(
+        rename = "addr",
+        default,
+        skip_serializing_if = "is_default",
+        with = "address_string"
+    )]
+    pub address: Address,
+    #[serde(rename = "comment", default, skip_serializing_if = "is_default")]
+    pub comment: String,
+    #[serde(rename = "state", default, skip_serializing_if = "is_default")]
+    pub state: AccountData,
+}
+
+impl Genesis {
+    /// Returns the genesis ID, which transactions on this network carry, e.g. `mainnet-v1.0`.
+    pub fn id(&self) -> String {
+        format!("{}-{}", self.network, self.schema_id)
+    }
+
+    /// Computes the genesis hash, which transactions and blocks on this network carry.
+    pub fn hash(&self) -> Digest {
+        crypto::hash_with_prefix(GENESIS_PREFIX, self)
+    }
+}
+
+/// Serde helper for the addresses of the genesis, which are encoded as strings
+/// even in msgpack, and thus when hashing the genesis.
+mod address_string {
+    use alloc::string::String;
+
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
+
+    use crate::types::Address;
+
+    pub fn serialize<S: Serializer>(addr: &Address, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(addr)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Address, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use data_encoding::BASE64;
+    use rmpv::Value;
+    use sha2::{Digest as _, Sha512_256};
+
+    use crate::encoding;
+
+    /// Genesis of a private network with two online accounts.
+    const GENESIS_FIXTURE: &str = include_str!("../../data/genesis.json");
+
+    #[test]
+    fn from_json() {
+        let genesis: Genesis = serde_json::from_str(GENESIS_FIXTURE).unwrap();
+        assert_eq!(genesis.id(), "privnet-v1");
+        assert_eq!(genesis.proto, "future");
+        assert_eq!(genesis.timestamp, 1_600_000_000);
+        assert_eq!(
+            genesis.fee_sink.to_string(),
+            "TAS5CMCPAY5GJZQF66H3QYX5LGGZNMF2T4Y43NXCSCSSRLJYQMJFBRKADA"
+        );
+        assert_eq!(genesis.allocation.len(), 4);
+
+        let online = &genesis.allocation[0];
+        assert_eq!(online.comment, "Wallet1");
+        assert_eq!(online.state.status, AccountStatus::Online);
+        assert_eq!(online.state.micro_algos, MicroAlgos(4_000_000_000_000_000));
+        assert_eq!(online.state.vote_last, Round(3_000_000));
+        assert_eq!(online.state.vote_key_dilution, 10_000);
+        assert_eq!(online.state.vote_pk[..2], [0x9b, 0x3f]);
+
+        let fee_sink = &genesis.allocation[2];
+        assert_eq!(fee_sink.address, genesis.fee_sink);
+        assert_eq!(fee_sink.state.status, AccountStatus::NotParticipating);
+    }
+
+    /// Converts the JSON of a genesis file to the msgpack value go-algorand hashes,
+    /// without going through `Genesis`.
+    fn json_to_msgpack(json: &serde_json::Value) -> Value {
+        use serde_json::Value as Json;
+
+        match json {
+            Json::Object(fields) => {
+                let mut entries: Vec<_> = fields
+                    .iter()
+                    .filter(|(_, value)| !is_empty(value))
+                    .map(|(key, value)| {
+                        let value = match (key.as_str(), value) {
+                            ("sel" | "vote" | "stprf", Json::String(s)) => {
+                                Value::from(BASE64.decode(s.as_bytes()).unwrap())
+                            }
+                            _ => json_to_msgpack(value),
+                        };
+                        (key.as_str(), value)
+                    })
+                    .collect();
+                entries.sort_by_key(|(key, _)| *key);
+                Value::Map(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (Value::from(key), value))
+                        .collect(),
+                )
+            }
+            Json::Array(items) => Value::Array(items.iter().map(json_to_msgpack).collect()),
+            Json::String(s) => Value::from(s.as_str()),
+            Json::Number(n) => Value::from(n.as_u64().unwrap()),
+            Json::Bool(b) => Value::from(*b),
+            Json::Null => Value::Nil,
+        }
+    }
+
+    fn is_empty(json: &serde_json::Value) -> bool {
+        use serde_json::Value as Json;
+
+        match json {
+            Json::Object(fields) => fields.values().all(is_empty),
+            Json::Array(items) => items.is_empty(),
+            Json::String(s) => s.is_empty(),
+            Json::Number(n) => n.as_u64() == Some(0),
+            Json::Bool(b) => !b,
+            Json::Null => true,
+        }
+    }
+
+    // TODO check against mainnet's genesis.json, which hashes to
+    // wGHE2Pwdvd7S12BL5FaOP20EGYesN73ktiC1qzkkit8=
+    #[test]
+    fn hash() {
+        let json: serde_json::Value = serde_json::from_str(GENESIS_FIXTURE).unwrap();
+        let genesis: Genesis = serde_json::from_value(json.clone()).unwrap();
+
+        // Addresses are hashed as strings, and participation keys as bytes.
+        let encoded = encoding::to_msgpack(&genesis);
+        let value = rmpv::decode::read_value(&mut &encoded[..]).unwrap();
+        assert_eq!(
+            value["fees"].as_str(),
+            Some(genesis.fee_sink.to_string().as_str())
+        );
+        assert!(value["alloc"][0]["addr"].is_str());
+        assert!(matches!(&value["alloc"][0]["state"]["vote"], Value::Binary(b) if b.len() == 32));
+
+        // go-algorand hashes the fixture's fields under their JSON names with sorted keys,
+        // omitting empty values, and with participation keys decoded from base64
+        let mut expected = Vec::new();
+        rmpv::encode::write_value(&mut expected, &json_to_msgpack(&json)).unwrap();
+        assert_eq!(encoded, expected);
+        let hash = genesis.hash();
+        let expected: Digest = Sha512_256::digest([b"GE".as_slice(), &expected].concat()).into();
+        assert_eq!(hash, expected);
+
+        let mut modified = genesis.clone();
+        modified.allocation[1].state.micro_algos = MicroAlgos(1);
+        assert_ne!(modified.hash(), hash);
+
+        let decoded: Genesis = encoding::from_msgpack(&encoded).unwrap();
+        assert!(decoded == genesis);
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use super::*;
+
+/// Identifies the lease a transaction acquires: its sender and the lease value.
+pub type LeaseKey = (Address, [u8; 32]);
+
+impl Transaction {
+    /// Returns the key of the lease the transaction acquires, if it sets a lease.
+    pub fn lease_key(&self) -> Option<LeaseKey> {
+        if self.header.lease == [0; 32] {
+            None
+        } else {
+            Some((self.header.sender, self.header.lease))
+        }
+    }
+}
+
+/// Keeps track of the leases of submitted transactions, to avoid submitting
+/// transactions that are mutually exclusive with one another.
+///
+/// Two transactions conflict if they have the same lease key and their validity
+/// windows overlap, since then at most one of them can be confirmed.
+#[derive(Clone, Debug, Default)]
+pub struct LeaseTracker {
+    /// Validity windows of the tracked transactions, by lease key.
+    windows: BTreeMap<LeaseKey, Vec<(Round, Round)>>,
+}
+
+impl LeaseTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks whether the transaction conflicts with any tracked transaction.
+    /// Transactions without a lease never conflict.
+    pub fn conflicts(&self, tx: &Transaction) -> bool {
+        let (first, last) = (tx.header.first_valid, tx.header.last_valid);
+        tx.lease_key()
+            .and_then(|key| self.windows.get(&key))
+            .is_some_and(|windows| windows.iter().any(|&(f, l)| f <= last && first <= l))
+    }
+
+    /// Starts tracking the transaction's lease, unless it conflicts with a tracked one.
+    ///
+    /// Returns whether the transaction was tracked, like `BTreeSet::insert`.
+    pub fn insert(&mut self, tx: &Transaction) -> bool {
+        if self.conflicts(tx) {
+            return false;
+        }
+        if let Some(key) = tx.lease_key() {
+            let window = (tx.header.first_valid, tx.header.last_valid);
+            self.windows.entry(key).or_default().push(window);
+        }
+        true
+    }
+
+    /// Stops tracking leases that expired before `round`, the current round of the ledger.
+    pub fn prune(&mut self, round: Round) {
+        self.windows.retain(|_, windows| {
+            windows.retain(|&(_, last)| last >= round);
+            !windows.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leased(sender: u8, lease: u8, first_valid: u64, last_valid: u64) -> Transaction {
+        Transaction {
+            header: Header {
+                sender: Address([sender; 32]),
+                first_valid: Round(first_valid),
+                last_valid: Round(last_valid),
+                lease: [lease; 32],
+                ..Default::default()
+            },
+            fields: TxFields::Payment(PaymentFields::default()),
+        }
+    }
+
+    #[test]
+    fn lease_key() {
+        assert_eq!(leased(1, 0, 10, 20).lease_key(), None);
+        assert_eq!(
+            leased(1, 2, 10, 20).lease_key(),
+            Some((Address([1; 32]), [2; 32]))
+        );
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut tracker = LeaseTracker::new();
+        assert!(tracker.insert(&leased(1, 2, 10, 20)));
+
+        // overlapping windows, including at the edges
+        assert!(!tracker.insert(&leased(1, 2, 20, 30)));
+        assert!(!tracker.insert(&leased(1, 2, 5, 10)));
+        assert!(tracker.conflicts(&leased(1, 2, 12, 15)));
+
+        // disjoint windows, other senders, other leases, and no lease at all
+        assert!(tracker.insert(&leased(1, 2, 21, 30)));
+        assert!(tracker.insert(&leased(3, 2, 10, 20)));
+        assert!(tracker.insert(&leased(1, 4, 10, 20)));
+        assert!(tracker.insert(&leased(1, 0, 10, 20)));
+        assert!(tracker.insert(&leased(1, 0, 10, 20)));
+        assert!(tracker.conflicts(&leased(1, 2, 25, 25)));
+    }
+
+    #[test]
+    fn prune() {
+        let mut tracker = LeaseTracker::new();
+        tracker.insert(&leased(1, 2, 10, 20));
+        tracker.insert(&leased(1, 2, 21, 30));
+
+        tracker.prune(Round(20));
+        assert!(tracker.conflicts(&leased(1, 2, 15, 15)));
+        tracker.prune(Round(21));
+        assert!(!tracker.conflicts(&leased(1, 2, 15, 15)));
+        assert!(tracker.conflicts(&leased(1, 2, 30, 40)));
+        tracker.prune(Round(31));
+        assert!(tracker.windows.is_empty());
+    }
+}
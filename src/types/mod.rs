@@ -1,20 +1,54 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
+mod account_data;
 mod address;
 mod applications;
 mod asset;
 mod auction;
 mod basics;
 mod block;
+mod builders;
+mod consensus;
+mod genesis;
+mod lease;
 mod signature;
+mod state_proof;
 mod transaction;
+mod validation;
 
+pub use account_data::{
+    AccountData, AccountStatus, AppLocalState, AppParams, AssetHolding, TealKeyValue, TealType,
+    TealValue,
+};
 pub use address::{Address, AddressError};
-pub use applications::{AppCallFields, AppIndex, OnCompletion};
-pub use asset::{AssetIndex, AssetParams};
-pub use auction::Bid;
-pub use basics::{Digest, MicroAlgos, Round, VotePK, VrfPK};
-pub use block::{Block, BlockHeader, UpgradeState, UpgradeVote};
-pub use signature::{LogicSig, MultisigSignature, MultisigSubsig, Signature};
-pub use transaction::{SignedTx, Transaction};
+pub use applications::{AppArg, AppCallFields, AppIndex, BoxRef, OnCompletion, StateSchema};
+pub use asset::{AssetIndex, AssetParams, AssetParamsError};
+pub use auction::{Bid, BidError, NoteField, SignedBid};
+pub use basics::{
+    Digest, MasterDerivationKey, MicroAlgos, ParseAlgosError, Round, VotePK, VrfPK,
+    MAX_TX_GROUP_SIZE,
+};
+pub use block::{
+    ApplyData, Block, BlockError, BlockHeader, DeltaAction, EvalDelta, EvalDeltaError,
+    ParticipationUpdates, Payset, PaysetCommitType, RewardsError, RewardsState, SignedTxInBlock,
+    SignedTxWithAD, StateDelta, StateProofTrackingData, UpgradeState, UpgradeVote, ValueDelta,
+};
+pub use builders::BuildError;
+pub use consensus::{params_for, ConsensusParams};
+pub use genesis::{Genesis, GenesisAllocation};
+pub use lease::{LeaseKey, LeaseTracker};
+pub use signature::{
+    HeartbeatProof, LogicSig, LogicSigError, MsigError, MultisigSignature, MultisigSubsig,
+    Signature,
+};
+pub use state_proof::{
+    FalconVerifier, HashFactory, MerkleProof, MerkleSignature, Participant, Reveal, SigSlotCommit,
+    StateProof, StateProofFields, StateProofMessage,
+};
+pub use transaction::{
+    assign_group_ids, compute_group_id, verify_group, AssetConfigFields, AssetFreezeFields,
+    AssetTransferFields, AssetTransferKind, GroupError, Header, HeartbeatFields, KeyregFields,
+    PaymentFields, SignedTx, SuggestedParams, Transaction, TxFields, VerifyError,
+};
+pub use validation::{group_well_formed, WellFormedError};
@@ -1,26 +1,44 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
-use ed25519_dalek::PublicKey;
-use serde::{Deserialize, Serialize};
+use alloc::vec::Vec;
+use core::fmt;
 
-use crate::util::is_default;
+use ed25519_dalek::{Keypair, PublicKey, Signer, Verifier};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha512_256};
+use subtle::ConstantTimeEq;
+use thiserror::Error;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(transparent)]
-pub struct Signature(ed25519::Signature);
+use super::basics::LOGIC_SIG_MAX_SIZE;
+use super::{Address, Transaction};
+use crate::crypto::{MULTISIG_ADDR_PREFIX, PROGRAM_PREFIX};
+use crate::encoding::{byte_vecs, bytes, public_key};
+use crate::signer::SignError;
+use crate::util::{is_default, Base64Debug, Base64ListDebug};
+
+/// The only multisig version currently supported by the protocol.
+const MULTISIG_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature(pub(crate) ed25519::Signature);
 
 /// Contains a single public key and, optionally, a signature.
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MultisigSubsig {
-    #[serde(rename = "pk", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "pk",
+        default,
+        skip_serializing_if = "is_default",
+        with = "public_key"
+    )]
     pub key: PublicKey,
     #[serde(rename = "s", default, skip_serializing_if = "is_default")]
     pub sig: Option<Signature>,
 }
 
 /// Holds multiple Subsigs, as well as threshold and version info.
-#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MultisigSignature {
     #[serde(rename = "v", default, skip_serializing_if = "is_default")]
     pub version: u8,
@@ -30,6 +48,61 @@ pub struct MultisigSignature {
     pub subsigs: Vec<MultisigSubsig>,
 }
 
+/// Proof that a heartbeat was sent by the holder of an account's participation keys,
+/// as the two-level ed25519 signature that votes are signed with.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeartbeatProof {
+    /// Signature of the heartbeat seed by the ephemeral key `pk`.
+    #[serde(rename = "s", default, skip_serializing_if = "is_default")]
+    pub sig: Signature,
+    #[serde(
+        rename = "p",
+        default,
+        skip_serializing_if = "is_default",
+        with = "public_key"
+    )]
+    pub pk: PublicKey,
+    #[serde(
+        rename = "p2",
+        default,
+        skip_serializing_if = "is_default",
+        with = "public_key"
+    )]
+    pub pk2: PublicKey,
+    /// Signature of `pk` by `pk2`.
+    #[serde(rename = "p1s", default, skip_serializing_if = "is_default")]
+    pub pk1_sig: Signature,
+    /// Signature of `pk2` by the account's vote key.
+    #[serde(rename = "p2s", default, skip_serializing_if = "is_default")]
+    pub pk2_sig: Signature,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum MsigError {
+    #[error("multisig versions differ")]
+    VersionMismatch,
+    #[error("multisig thresholds differ")]
+    ThresholdMismatch,
+    #[error("multisig keys differ")]
+    KeysMismatch,
+    #[error("subsig {0} carries different signatures")]
+    ConflictingSignatures(usize),
+    #[error("multisig version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("multisig has no subsigs")]
+    NoSubsigs,
+    #[error("multisig threshold {threshold} is not between 1 and the {subsigs} subsigs")]
+    InvalidThreshold { threshold: u8, subsigs: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum LogicSigError {
+    #[error("program is {0} bytes, more than the maximum of {LOGIC_SIG_MAX_SIZE}")]
+    ProgramTooLarge(usize),
+    #[error("program and args are {0} bytes, more than the maximum of {LOGIC_SIG_MAX_SIZE}")]
+    ArgsTooLarge(usize),
+}
+
 /// LogicSig contains logic for validating a transaction.
 /// LogicSig is signed by an account, allowing delegation of operations.
 /// OR
@@ -38,7 +111,12 @@ pub struct MultisigSignature {
 pub struct LogicSig {
     /// Logic signed by Sig or Msig
     /// OR hashed to be the Address of an account.
-    #[serde(rename = "l", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "l",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
     pub logic: Vec<u8>,
 
     /// The signature of the account that has delegated to this LogicSig, if any
@@ -46,16 +124,611 @@ pub struct LogicSig {
     pub sig: Signature,
 
     /// The signature of the multisig account that has delegated to this LogicSig, if any
-    #[serde(rename = "sig", default, skip_serializing_if = "is_default")]
+    #[serde(rename = "msig", default, skip_serializing_if = "is_default")]
     pub msig: MultisigSignature,
 
     /// Args are not signed, but checked by Logic
-    #[serde(rename = "arg", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "arg",
+        default,
+        skip_serializing_if = "is_default",
+        with = "byte_vecs"
+    )]
     pub args: Vec<Vec<u8>>,
 }
 
+impl MultisigSignature {
+    /// Creates an unsigned multisig for the account with the given parameters.
+    /// The order of `keys` matters, as it determines the account's address.
+    pub fn new(version: u8, threshold: u8, keys: &[PublicKey]) -> MultisigSignature {
+        MultisigSignature {
+            version,
+            threshold,
+            subsigs: keys
+                .iter()
+                .map(|&key| MultisigSubsig { key, sig: None })
+                .collect(),
+        }
+    }
+
+    /// Computes the address of the multisig account described by
+    /// the version, threshold, and ordered public keys of this signature.
+    pub fn address(&self) -> Address {
+        let mut hasher = Sha512_256::new();
+        hasher.update(MULTISIG_ADDR_PREFIX);
+        hasher.update([self.version, self.threshold]);
+        for subsig in &self.subsigs {
+            hasher.update(subsig.key.as_bytes());
+        }
+        Address(hasher.finalize().into())
+    }
+
+    /// Checks that the multisig is well-formed: it has the supported version,
+    /// at least one subsig, and a threshold that is reachable but not zero.
+    ///
+    /// The network rejects transactions signed by malformed multisigs.
+    pub fn validate(&self) -> Result<(), MsigError> {
+        if self.version != MULTISIG_VERSION {
+            return Err(MsigError::UnsupportedVersion(self.version));
+        } else if self.subsigs.is_empty() {
+            return Err(MsigError::NoSubsigs);
+        } else if self.threshold == 0 || usize::from(self.threshold) > self.subsigs.len() {
+            return Err(MsigError::InvalidThreshold {
+                threshold: self.threshold,
+                subsigs: self.subsigs.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Adds the signature of `keypair` over `tx` to the matching subsig.
+    ///
+    /// This allows the parties of a multisig account to sign one after another,
+    /// passing around the partially signed multisig.
+    pub fn sign(&mut self, keypair: &Keypair, tx: &Transaction) -> Result<(), SignError> {
+        self.validate()?;
+        let subsig = self
+            .subsigs
+            .iter_mut()
+            .find(|s| s.key == keypair.public)
+            .ok_or(SignError::KeyNotInMultisig)?;
+        subsig.sig = Some(Signature(keypair.sign(&tx.bytes_to_sign())));
+        Ok(())
+    }
+
+    /// Adds the signatures present in `other`, a partially signed copy of the same multisig,
+    /// to this one.
+    ///
+    /// This allows the parties of a multisig account to sign in parallel.
+    /// Nothing is merged if the multisigs' version, threshold, or keys differ,
+    /// or if a subsig carries different signatures in both.
+    pub fn merge(&mut self, other: &MultisigSignature) -> Result<(), MsigError> {
+        if self.version != other.version {
+            return Err(MsigError::VersionMismatch);
+        } else if self.threshold != other.threshold {
+            return Err(MsigError::ThresholdMismatch);
+        } else if self.subsigs.len() != other.subsigs.len()
+            || self
+                .subsigs
+                .iter()
+                .zip(&other.subsigs)
+                .any(|(a, b)| a.key != b.key)
+        {
+            return Err(MsigError::KeysMismatch);
+        }
+        for (i, (ours, theirs)) in self.subsigs.iter().zip(&other.subsigs).enumerate() {
+            if let (Some(a), Some(b)) = (ours.sig, theirs.sig) {
+                if a != b {
+                    return Err(MsigError::ConflictingSignatures(i));
+                }
+            }
+        }
+
+        for (ours, theirs) in self.subsigs.iter_mut().zip(&other.subsigs) {
+            ours.sig = ours.sig.or(theirs.sig);
+        }
+        Ok(())
+    }
+
+    /// Checks that at least `threshold` subsigs carry a valid signature over `data`
+    /// and that all other signatures present are valid, too.
+    ///
+    /// Malformed multisigs (see `validate`) and ones with the same key
+    /// in several subsigs are rejected.
+    /// This does not check which account the multisig belongs to,
+    /// compare `address()` against the expected signer for that.
+    pub fn verify(&self, data: &[u8]) -> bool {
+        if self.validate().is_err() {
+            return false;
+        }
+        for (i, subsig) in self.subsigs.iter().enumerate() {
+            if self.subsigs[..i].iter().any(|s| s.key == subsig.key) {
+                return false;
+            }
+        }
+
+        let mut valid = 0;
+        for subsig in &self.subsigs {
+            if let Some(sig) = &subsig.sig {
+                if subsig.key.verify(data, &sig.0).is_err() {
+                    return false;
+                }
+                valid += 1;
+            }
+        }
+        valid >= usize::from(self.threshold)
+    }
+}
+
+impl LogicSig {
+    /// Computes the address of the contract account defined by this logic.
+    pub fn address(&self) -> Address {
+        Address(Sha512_256::digest(self.bytes_to_sign()).into())
+    }
+
+    /// Checks that this logic sig may authorize transactions for `signer`.
+    ///
+    /// Without a signature, the logic defines a contract account and `signer` must be its address.
+    /// Otherwise `signer` must have delegated to the logic by signing it, either directly or as a multisig.
+    pub fn verify(&self, signer: &Address) -> bool {
+        let has_sig = !is_default(&self.sig);
+        let has_msig = !is_default(&self.msig);
+        match (has_sig, has_msig) {
+            (false, false) => self.address().ct_eq(signer).into(),
+            (true, false) => match PublicKey::from_bytes(&signer.0) {
+                Ok(key) => key.verify(&self.bytes_to_sign(), &self.sig.0).is_ok(),
+                Err(_) => false,
+            },
+            (false, true) => {
+                bool::from(self.msig.address().ct_eq(signer))
+                    && self.msig.verify(&self.bytes_to_sign())
+            }
+            (true, true) => false,
+        }
+    }
+
+    /// Checks that the program and its args together fit into `LOGIC_SIG_MAX_SIZE`,
+    /// as the network rejects larger logic sigs.
+    ///
+    /// The execution cost can only be checked by evaluating the program, e.g. in a dryrun.
+    pub fn validate(&self) -> Result<(), LogicSigError> {
+        let program_size = self.logic.len();
+        if program_size > LOGIC_SIG_MAX_SIZE {
+            return Err(LogicSigError::ProgramTooLarge(program_size));
+        }
+        let total_size = program_size + self.args.iter().map(Vec::len).sum::<usize>();
+        if total_size > LOGIC_SIG_MAX_SIZE {
+            return Err(LogicSigError::ArgsTooLarge(total_size));
+        }
+        Ok(())
+    }
+
+    /// Returns the bytes that delegating accounts sign, which excludes the args.
+    pub fn bytes_to_sign(&self) -> Vec<u8> {
+        [PROGRAM_PREFIX, &self.logic].concat()
+    }
+}
+
+impl Signature {
+    /// Wraps the 64 bytes of an ed25519 signature, e.g. one made by a hardware wallet.
+    pub fn from_bytes(bytes: &[u8; ed25519::Signature::BYTE_SIZE]) -> Result<Self, ed25519::Error> {
+        ed25519::Signature::from_bytes(bytes).map(Signature)
+    }
+
+    /// Returns the 64 bytes of the signature.
+    pub fn to_bytes(&self) -> [u8; ed25519::Signature::BYTE_SIZE] {
+        self.0.to_bytes()
+    }
+}
+
+impl From<ed25519::Signature> for Signature {
+    fn from(sig: ed25519::Signature) -> Self {
+        Signature(sig)
+    }
+}
+
+/// The all-zero signature, which stands for no signature in encodings.
 impl Default for Signature {
     fn default() -> Self {
-        Self(ed25519::Signature::from_bytes(&[0; ed25519::Signature::BYTE_SIZE]).unwrap())
+        // `from_bytes` only rejects signatures with any of the three highest bits set.
+        let zero = ed25519::Signature::from_bytes(&[0; ed25519::Signature::BYTE_SIZE]);
+        Self(zero.expect("the all-zero signature is well-formed"))
+    }
+}
+
+impl Serialize for Signature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        bytes::serialize(&self.to_bytes(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Signature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes: [u8; ed25519::Signature::BYTE_SIZE] = bytes::deserialize(deserializer)?;
+        Signature::from_bytes(&bytes).map_err(|_| {
+            de::Error::invalid_value(de::Unexpected::Bytes(&bytes), &"an ed25519 signature")
+        })
+    }
+}
+
+/// Formats the key as the address of its account.
+impl fmt::Debug for MultisigSubsig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MultisigSubsig")
+            .field("key", &Address::from(self.key))
+            .field("sig", &self.sig)
+            .finish()
+    }
+}
+
+/// Formats the keys as base64.
+impl fmt::Debug for HeartbeatProof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HeartbeatProof")
+            .field("sig", &self.sig)
+            .field("pk", &Base64Debug(self.pk.as_bytes()))
+            .field("pk2", &Base64Debug(self.pk2.as_bytes()))
+            .field("pk1_sig", &self.pk1_sig)
+            .field("pk2_sig", &self.pk2_sig)
+            .finish()
+    }
+}
+
+/// Formats the program and args as base64.
+impl fmt::Debug for LogicSig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LogicSig")
+            .field("logic", &Base64Debug(&self.logic))
+            .field("sig", &self.sig)
+            .field("msig", &self.msig)
+            .field("args", &Base64ListDebug(&self.args))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use ed25519_dalek::Verifier;
+
+    use crate::account::Account;
+    use crate::encoding::{self, DecodeError};
+    use crate::types::{Header, MicroAlgos, PaymentFields, Round, TxFields};
+
+    #[test]
+    fn multisig_address() {
+        let keys: Vec<PublicKey> = [
+            "XMHLMNAVJIMAW2RHJXLXKKK4G3J3U6VONNO3BTAQYVDC3MHTGDP3J5OCRU",
+            "HTNOX33OCQI2JCOLZ2IRM3BC2WZ6JUILSLEORBPFI6W7GU5Q4ZW6LINHLA",
+            "E6JSNTY4PVCY3IRZ6XEDHEO6VIHCQ5KGXCIQKFQCMB2N6HXRY4IB43VSHI",
+        ]
+        .iter()
+        .map(|a| PublicKey::from_bytes(&Address::from_str(a).unwrap().0).unwrap())
+        .collect();
+
+        let msig = MultisigSignature::new(1, 2, &keys);
+        assert_eq!(
+            msig.address().to_string(),
+            "UCE2U2JC4O4ZR6W763GUQCG57HQCDZEUJY4J5I6VYY4HQZUJDF7AKZO5GM"
+        );
+
+        // the order of keys is part of the address
+        let reordered = MultisigSignature::new(1, 2, &[keys[1], keys[0], keys[2]]);
+        assert_ne!(reordered.address(), msig.address());
+    }
+
+    #[test]
+    fn multisig_sign_one_by_one() {
+        let accounts: Vec<Account> = (1..=3).map(|i| Account::from_seed(&[i; 32])).collect();
+        let keys: Vec<_> = accounts.iter().map(|a| a.public_key()).collect();
+        let mut msig = MultisigSignature::new(1, 2, &keys);
+        let tx = Transaction {
+            header: Header {
+                sender: msig.address(),
+                fee: MicroAlgos(1000),
+                first_valid: Round(1),
+                last_valid: Round(1001),
+                ..Default::default()
+            },
+            fields: TxFields::Payment(PaymentFields::default()),
+        };
+
+        msig.sign(accounts[2].keypair(), &tx).unwrap();
+        msig.sign(accounts[0].keypair(), &tx).unwrap();
+        assert!(msig.subsigs[1].sig.is_none());
+        for i in [0, 2] {
+            let sig = msig.subsigs[i].sig.unwrap();
+            assert!(keys[i].verify(&tx.bytes_to_sign(), &sig.0).is_ok());
+        }
+
+        let outsider = Account::from_seed(&[4; 32]);
+        assert_eq!(
+            msig.sign(outsider.keypair(), &tx),
+            Err(SignError::KeyNotInMultisig)
+        );
+    }
+
+    #[test]
+    fn multisig_merge() {
+        let accounts: Vec<Account> = (1..=3).map(|i| Account::from_seed(&[i; 32])).collect();
+        let keys: Vec<_> = accounts.iter().map(|a| a.public_key()).collect();
+        let unsigned = MultisigSignature::new(1, 2, &keys);
+        let tx = Transaction {
+            header: Header {
+                sender: unsigned.address(),
+                fee: MicroAlgos(1000),
+                first_valid: Round(1),
+                last_valid: Round(1001),
+                ..Default::default()
+            },
+            fields: TxFields::Payment(PaymentFields::default()),
+        };
+
+        let mut first = unsigned.clone();
+        first.sign(accounts[0].keypair(), &tx).unwrap();
+        let mut second = unsigned.clone();
+        second.sign(accounts[2].keypair(), &tx).unwrap();
+        second.sign(accounts[0].keypair(), &tx).unwrap();
+
+        first.merge(&second).unwrap();
+        assert!(first.subsigs[1].sig.is_none());
+        assert_eq!(first.subsigs[2].sig, second.subsigs[2].sig);
+        assert!(first.verify(&tx.bytes_to_sign()));
+
+        let mut other_tx = tx.clone();
+        other_tx.header.fee = MicroAlgos(2000);
+        let mut conflicting = unsigned.clone();
+        conflicting.sign(accounts[0].keypair(), &other_tx).unwrap();
+        conflicting.sign(accounts[1].keypair(), &other_tx).unwrap();
+        let before = first.clone();
+        assert_eq!(
+            first.merge(&conflicting),
+            Err(MsigError::ConflictingSignatures(0))
+        );
+        assert!(first == before);
+
+        let mut other = MultisigSignature::new(1, 1, &keys);
+        assert_eq!(first.merge(&other), Err(MsigError::ThresholdMismatch));
+        other = MultisigSignature::new(2, 2, &keys);
+        assert_eq!(first.merge(&other), Err(MsigError::VersionMismatch));
+        other = MultisigSignature::new(1, 2, &keys[..2]);
+        assert_eq!(first.merge(&other), Err(MsigError::KeysMismatch));
+        other = MultisigSignature::new(1, 2, &[keys[1], keys[0], keys[2]]);
+        assert_eq!(first.merge(&other), Err(MsigError::KeysMismatch));
+    }
+
+    #[test]
+    fn multisig_verify() {
+        let accounts: Vec<Account> = (1..=3).map(|i| Account::from_seed(&[i; 32])).collect();
+        let keys: Vec<_> = accounts.iter().map(|a| a.public_key()).collect();
+        let data = b"some message";
+        let signed = |msig: &mut MultisigSignature, i: usize| {
+            msig.subsigs[i].sig = Some(Signature(accounts[i].keypair().sign(data)));
+        };
+
+        let mut msig = MultisigSignature::new(1, 2, &keys);
+        signed(&mut msig, 1);
+        assert!(!msig.verify(data));
+        signed(&mut msig, 2);
+        assert!(msig.verify(data));
+        assert!(!msig.verify(b"other message"));
+
+        // an invalid signature is rejected even if the threshold is met without it
+        let mut extra = msig.clone();
+        extra.subsigs[0].sig = extra.subsigs[1].sig;
+        assert!(!extra.verify(data));
+
+        let mut duplicate = MultisigSignature::new(1, 2, &[keys[0], keys[0]]);
+        signed(&mut duplicate, 0);
+        duplicate.subsigs[1].sig = duplicate.subsigs[0].sig;
+        assert!(!duplicate.verify(data));
+
+        let mut wrong_version = msig.clone();
+        wrong_version.version = 2;
+        assert!(!wrong_version.verify(data));
+
+        let mut unreachable = msig;
+        unreachable.threshold = 4;
+        assert!(!unreachable.verify(data));
+    }
+
+    #[test]
+    fn multisig_validate() {
+        let keys: Vec<_> = (1..=3)
+            .map(|i| Account::from_seed(&[i; 32]).public_key())
+            .collect();
+        assert_eq!(MultisigSignature::new(1, 1, &keys).validate(), Ok(()));
+        assert_eq!(MultisigSignature::new(1, 3, &keys).validate(), Ok(()));
+
+        assert_eq!(
+            MultisigSignature::new(1, 0, &keys).validate(),
+            Err(MsigError::InvalidThreshold {
+                threshold: 0,
+                subsigs: 3
+            })
+        );
+        assert_eq!(
+            MultisigSignature::new(1, 4, &keys).validate(),
+            Err(MsigError::InvalidThreshold {
+                threshold: 4,
+                subsigs: 3
+            })
+        );
+        assert_eq!(
+            MultisigSignature::new(1, 1, &[]).validate(),
+            Err(MsigError::NoSubsigs)
+        );
+        assert_eq!(
+            MultisigSignature::new(2, 1, &keys).validate(),
+            Err(MsigError::UnsupportedVersion(2))
+        );
+
+        let account = Account::from_seed(&[1; 32]);
+        let mut msig = MultisigSignature::new(1, 4, &keys);
+        let tx = Transaction {
+            header: Header::default(),
+            fields: TxFields::Payment(PaymentFields::default()),
+        };
+        assert!(matches!(
+            msig.sign(account.keypair(), &tx),
+            Err(SignError::InvalidMultisig(
+                MsigError::InvalidThreshold { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn decode_malformed_key() {
+        let key = Account::from_seed(&[1; 32]).public_key();
+        let subsig = MultisigSubsig { key, sig: None };
+        let blob = encoding::to_msgpack(&subsig);
+        // fixmap(1), "pk", bin8(32)
+        assert_eq!(blob[..6], [0x81, 0xa2, b'p', b'k', 0xc4, 32]);
+        assert_eq!(encoding::from_msgpack(&blob), Ok(subsig));
+
+        let mut truncated = blob[..blob.len() - 1].to_vec();
+        truncated[5] = 31;
+        assert!(matches!(
+            encoding::from_msgpack::<MultisigSubsig>(&truncated),
+            Err(DecodeError::InvalidMsgpack(msg)) if msg.contains("invalid length 31")
+        ));
+
+        // not the encoding of a point on the curve
+        let mut off_curve = blob.clone();
+        off_curve[6..].fill(2);
+        assert!(matches!(
+            encoding::from_msgpack::<MultisigSubsig>(&off_curve),
+            Err(DecodeError::InvalidMsgpack(msg)) if msg.contains("expected an ed25519 public key")
+        ));
+    }
+
+    #[test]
+    fn decode_malformed_signature() {
+        let sig = Account::from_seed(&[1; 32]).sign_transaction(&Transaction {
+            header: Header::default(),
+            fields: TxFields::Payment(PaymentFields::default()),
+        });
+        let blob = encoding::to_msgpack(&sig.sig);
+        // bin8(64)
+        assert_eq!(blob[..2], [0xc4, 64]);
+        assert_eq!(encoding::from_msgpack(&blob), Ok(sig.sig));
+
+        let mut truncated = blob[..blob.len() - 1].to_vec();
+        truncated[1] = 63;
+        assert!(matches!(
+            encoding::from_msgpack::<Signature>(&truncated),
+            Err(DecodeError::InvalidMsgpack(msg)) if msg.contains("invalid length 63")
+        ));
+
+        // the scalar is not reduced
+        let mut unreduced = blob.clone();
+        unreduced[65] |= 0b1110_0000;
+        assert!(matches!(
+            encoding::from_msgpack::<Signature>(&unreduced),
+            Err(DecodeError::InvalidMsgpack(msg)) if msg.contains("expected an ed25519 signature")
+        ));
+    }
+
+    #[test]
+    fn logic_sig_address() {
+        // `int 1`, a program that approves every transaction
+        let lsig = LogicSig {
+            logic: vec![0x01, 0x20, 0x01, 0x01, 0x22],
+            sig: Signature::default(),
+            msig: MultisigSignature::default(),
+            args: Vec::new(),
+        };
+        assert_eq!(
+            lsig.address().to_string(),
+            "6Z3C3LDVWGMX23BMSYMANACQOSINPFIRF77H7N3AWJZYV6OH6GWTJKVMXY"
+        );
+    }
+
+    #[test]
+    fn logic_sig_verify() {
+        let account = Account::from_seed(&[1; 32]);
+        let mut lsig = LogicSig {
+            logic: vec![0x01, 0x20, 0x01, 0x01, 0x22],
+            sig: Signature::default(),
+            msig: MultisigSignature::default(),
+            args: Vec::new(),
+        };
+
+        // contract account
+        assert!(lsig.verify(&lsig.address()));
+        assert!(!lsig.verify(&account.address()));
+
+        // delegated by a single account, args are not signed
+        lsig.sig = Signature(account.keypair().sign(&lsig.bytes_to_sign()));
+        lsig.args = vec![vec![1, 2, 3]];
+        assert!(lsig.verify(&account.address()));
+        assert!(!lsig.verify(&lsig.address()));
+        let mut tampered = lsig.clone();
+        tampered.logic.push(0x22);
+        assert!(!tampered.verify(&account.address()));
+
+        // delegated by a multisig
+        let mut msig = MultisigSignature::new(1, 1, &[account.public_key()]);
+        msig.subsigs[0].sig = Some(lsig.sig);
+        let mut delegated = lsig.clone();
+        delegated.sig = Signature::default();
+        delegated.msig = msig.clone();
+        assert!(delegated.verify(&msig.address()));
+        assert!(!delegated.verify(&account.address()));
+
+        lsig.msig = msig;
+        assert!(!lsig.verify(&account.address()));
+    }
+
+    #[test]
+    fn logic_sig_wire_keys() {
+        let account = Account::from_seed(&[1; 32]);
+        let mut msig = MultisigSignature::new(1, 1, &[account.public_key()]);
+        let mut lsig = LogicSig {
+            logic: vec![0x01, 0x20, 0x01, 0x01, 0x22],
+            sig: Signature::default(),
+            msig: MultisigSignature::default(),
+            args: Vec::new(),
+        };
+        msig.subsigs[0].sig = Some(Signature(account.keypair().sign(&lsig.bytes_to_sign())));
+        lsig.msig = msig;
+
+        let blob = encoding::to_msgpack(&lsig);
+        let value = rmpv::decode::read_value(&mut &blob[..]).unwrap();
+        let keys: Vec<_> = value
+            .as_map()
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k.as_str().unwrap())
+            .collect();
+        assert_eq!(keys, ["l", "msig"]);
+        assert_eq!(encoding::from_msgpack::<LogicSig>(&blob).unwrap(), lsig);
+    }
+
+    #[test]
+    fn logic_sig_size_bounds() {
+        let mut lsig = LogicSig {
+            logic: vec![0; LOGIC_SIG_MAX_SIZE - 10],
+            sig: Signature::default(),
+            msig: Default::default(),
+            args: vec![vec![0; 4], vec![0; 6]],
+        };
+        assert_eq!(lsig.validate(), Ok(()));
+
+        lsig.args[0].push(0);
+        assert_eq!(
+            lsig.validate(),
+            Err(LogicSigError::ArgsTooLarge(LOGIC_SIG_MAX_SIZE + 1))
+        );
+
+        lsig.args.clear();
+        lsig.logic = vec![0; LOGIC_SIG_MAX_SIZE + 1];
+        assert_eq!(
+            lsig.validate(),
+            Err(LogicSigError::ProgramTooLarge(LOGIC_SIG_MAX_SIZE + 1))
+        );
     }
 }
@@ -0,0 +1,278 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! State proof transactions, which the protocol issues to attest to the blocks of past rounds.
+//!
+//! These types only decode and re-encode state proofs, they do not verify them.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha512_256};
+
+use super::*;
+use crate::crypto::SPECIAL_ADDR_PREFIX;
+use crate::encoding::{bounded, byte_vecs, bytes};
+use crate::util::{is_default, Base64Debug, Base64ListDebug};
+
+/// Maximum number of reveals in a decoded state proof.
+const MAX_REVEALS: usize = 640;
+
+/// Captures the fields used for state proof transactions.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateProofFields {
+    /// The kind of state proof, only `0` is currently defined.
+    #[serde(rename = "sptype", default, skip_serializing_if = "is_default")]
+    pub state_proof_type: u64,
+    #[serde(rename = "sp", default, skip_serializing_if = "is_default")]
+    pub state_proof: StateProof,
+    #[serde(rename = "spmsg", default, skip_serializing_if = "is_default")]
+    pub message: StateProofMessage,
+}
+
+impl StateProofFields {
+    /// Returns the special address all state proof transactions are sent from.
+    pub fn sender() -> Address {
+        let mut hasher = Sha512_256::new();
+        hasher.update(SPECIAL_ADDR_PREFIX);
+        hasher.update(b"StateProofSender");
+        Address(hasher.finalize().into())
+    }
+}
+
+/// The message a state proof attests to, committing to a range of block headers.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateProofMessage {
+    #[serde(
+        rename = "b",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
+    pub block_headers_commitment: Vec<u8>,
+    #[serde(
+        rename = "v",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
+    pub voters_commitment: Vec<u8>,
+    /// Natural logarithm of the proven weight, with 16 bits of precision.
+    #[serde(rename = "P", default, skip_serializing_if = "is_default")]
+    pub ln_proven_weight: u64,
+    #[serde(rename = "f", default, skip_serializing_if = "is_default")]
+    pub first_attested_round: Round,
+    #[serde(rename = "l", default, skip_serializing_if = "is_default")]
+    pub last_attested_round: Round,
+}
+
+/// A compact certificate that enough of the online stake signed a `StateProofMessage`.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateProof {
+    #[serde(
+        rename = "c",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
+    pub sig_commit: Vec<u8>,
+    #[serde(rename = "w", default, skip_serializing_if = "is_default")]
+    pub signed_weight: u64,
+    #[serde(rename = "S", default, skip_serializing_if = "is_default")]
+    pub sig_proofs: MerkleProof,
+    #[serde(rename = "P", default, skip_serializing_if = "is_default")]
+    pub part_proofs: MerkleProof,
+    #[serde(rename = "v", default, skip_serializing_if = "is_default")]
+    pub merkle_signature_salt_version: u8,
+    /// The revealed signatures, by their position in the signature array.
+    #[serde(
+        rename = "r",
+        default,
+        skip_serializing_if = "is_default",
+        deserialize_with = "bounded::map::<_, _, _, MAX_REVEALS>"
+    )]
+    pub reveals: BTreeMap<u64, Reveal>,
+    #[serde(
+        rename = "pr",
+        default,
+        skip_serializing_if = "is_default",
+        deserialize_with = "bounded::vec::<_, _, MAX_REVEALS>"
+    )]
+    pub positions_to_reveal: Vec<u64>,
+}
+
+/// Proof of some elements of a Merkle array, using a hash function other than SHA512/256.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    #[serde(
+        rename = "pth",
+        default,
+        skip_serializing_if = "is_default",
+        with = "byte_vecs"
+    )]
+    pub path: Vec<Vec<u8>>,
+    #[serde(rename = "hsh", default, skip_serializing_if = "is_default")]
+    pub hash_factory: HashFactory,
+    #[serde(rename = "td", default, skip_serializing_if = "is_default")]
+    pub tree_depth: u8,
+}
+
+/// Identifies the hash function used by a `MerkleProof`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashFactory {
+    #[serde(rename = "t", default, skip_serializing_if = "is_default")]
+    pub hash_type: u16,
+}
+
+/// A revealed signature together with the participant that made it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reveal {
+    #[serde(rename = "s", default, skip_serializing_if = "is_default")]
+    pub sig_slot: SigSlotCommit,
+    #[serde(rename = "p", default, skip_serializing_if = "is_default")]
+    pub participant: Participant,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigSlotCommit {
+    #[serde(rename = "s", default, skip_serializing_if = "is_default")]
+    pub sig: MerkleSignature,
+    /// Total weight of the signatures before this one.
+    #[serde(rename = "l", default, skip_serializing_if = "is_default")]
+    pub lower_sig_weight: u64,
+}
+
+/// An online account taking part in a state proof.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Participant {
+    /// Commitment to the account's state proof keys.
+    #[serde(
+        rename = "p",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
+    pub commitment: Vec<u8>,
+    #[serde(rename = "w", default, skip_serializing_if = "is_default")]
+    pub weight: u64,
+}
+
+/// A Falcon signature with a proof that its key is part of the signer's commitment.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleSignature {
+    #[serde(
+        rename = "sig",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
+    pub signature: Vec<u8>,
+    #[serde(rename = "idx", default, skip_serializing_if = "is_default")]
+    pub vector_commitment_index: u64,
+    #[serde(rename = "prf", default, skip_serializing_if = "is_default")]
+    pub proof: MerkleProof,
+    #[serde(rename = "vkey", default, skip_serializing_if = "is_default")]
+    pub verifying_key: FalconVerifier,
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FalconVerifier {
+    #[serde(
+        rename = "k",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
+    pub public_key: Vec<u8>,
+}
+
+/// Formats the commitments as base64.
+impl fmt::Debug for StateProofMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StateProofMessage")
+            .field(
+                "block_headers_commitment",
+                &Base64Debug(&self.block_headers_commitment),
+            )
+            .field("voters_commitment", &Base64Debug(&self.voters_commitment))
+            .field("ln_proven_weight", &self.ln_proven_weight)
+            .field("first_attested_round", &self.first_attested_round)
+            .field("last_attested_round", &self.last_attested_round)
+            .finish()
+    }
+}
+
+/// Formats the signature commitment as base64.
+impl fmt::Debug for StateProof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StateProof")
+            .field("sig_commit", &Base64Debug(&self.sig_commit))
+            .field("signed_weight", &self.signed_weight)
+            .field("sig_proofs", &self.sig_proofs)
+            .field("part_proofs", &self.part_proofs)
+            .field(
+                "merkle_signature_salt_version",
+                &self.merkle_signature_salt_version,
+            )
+            .field("reveals", &self.reveals)
+            .field("positions_to_reveal", &self.positions_to_reveal)
+            .finish()
+    }
+}
+
+/// Formats the path as base64.
+impl fmt::Debug for MerkleProof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MerkleProof")
+            .field("path", &Base64ListDebug(&self.path))
+            .field("hash_factory", &self.hash_factory)
+            .field("tree_depth", &self.tree_depth)
+            .finish()
+    }
+}
+
+/// Formats the commitment as base64.
+impl fmt::Debug for Participant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Participant")
+            .field("commitment", &Base64Debug(&self.commitment))
+            .field("weight", &self.weight)
+            .finish()
+    }
+}
+
+/// Formats the signature as base64.
+impl fmt::Debug for MerkleSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MerkleSignature")
+            .field("signature", &Base64Debug(&self.signature))
+            .field("vector_commitment_index", &self.vector_commitment_index)
+            .field("proof", &self.proof)
+            .field("verifying_key", &self.verifying_key)
+            .finish()
+    }
+}
+
+/// Formats the public key as base64.
+impl fmt::Debug for FalconVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("FalconVerifier")
+            .field(&Base64Debug(&self.public_key))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_proof_sender() {
+        assert_eq!(
+            StateProofFields::sender().to_string(),
+            "XM6FEYVJ2XDU2IBH4OT6VZGW75YM63CM4TC6AV6BD3JZXFJUIICYTVB5EU"
+        );
+    }
+}
@@ -1,15 +1,54 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
-use std::str::FromStr;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
 
+use data_encoding::{BASE32_NOPAD, BASE64};
+use ed25519_dalek::{PublicKey, Verifier};
 use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use thiserror::Error;
 
 use super::*;
-use crate::util::is_default;
+use crate::crypto::{self, TX_GROUP_PREFIX, TX_PREFIX};
+#[cfg(feature = "std")]
+use crate::encoding::DecodeError;
+use crate::encoding::{self, byte_vecs, bytes, digest};
+use crate::util::{is_default, Base64Debug};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum GroupError {
+    #[error("transaction group is empty")]
+    EmptyGroup,
+    #[error(
+        "transaction group has {0} transactions, more than the maximum of {MAX_TX_GROUP_SIZE}"
+    )]
+    TooLarge(usize),
+    #[error("no signer for sender {0}")]
+    MissingSigner(Address),
+    #[error("transactions do not share a group ID")]
+    MixedGroups,
+    #[error("transaction {0} does not carry the group's ID")]
+    GroupIdMismatch(usize),
+    #[error("transaction {index} is not properly signed: {error}")]
+    InvalidSignature { index: usize, error: VerifyError },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum VerifyError {
+    #[error("transaction is not signed")]
+    NoSignature,
+    #[error("transaction has more than one kind of signature")]
+    MultipleSignatures,
+    #[error("invalid signature")]
+    InvalidSignature,
+}
 
 /// Describes a transaction that can appear in a block.
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Transaction {
     #[serde(flatten)]
     pub header: Header,
@@ -29,23 +68,38 @@ pub struct Header {
     pub first_valid: Round,
     #[serde(rename = "lv", default, skip_serializing_if = "is_default")]
     pub last_valid: Round,
-    #[serde(default, skip_serializing_if = "is_default")]
+    #[serde(default, skip_serializing_if = "is_default", with = "bytes")]
     pub note: Vec<u8>,
     #[serde(rename = "gen", default, skip_serializing_if = "is_default")]
     pub genesis_id: String,
-    #[serde(rename = "gh", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "gh",
+        default,
+        skip_serializing_if = "is_default",
+        with = "digest"
+    )]
     pub genesis_hash: Digest,
 
     /// Specifies that this transaction is part of a transaction group
     /// (and, if so, specifies the hash of the transaction group).
-    #[serde(rename = "grp", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "grp",
+        default,
+        skip_serializing_if = "is_default",
+        with = "digest"
+    )]
     pub group: Digest,
 
     /// Enforces mutual exclusion of transactions.
     /// If this field is nonzero, then once the transaction is confirmed, it acquires the
     /// lease identified by the pair (sender, lease) until the last_valid round passes.
     /// While this transaction possesses the lease, no other transaction with this lease can be confirmed.
-    #[serde(rename = "lx", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "lx",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
     pub lease: [u8; 32],
 
     /// If nonzero, sets the sender's `auth_addr` to the given address.
@@ -56,7 +110,7 @@ pub struct Header {
     pub rekey_to: Address,
 }
 
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum TxFields {
     #[serde(rename = "keyreg")]
@@ -71,15 +125,33 @@ pub enum TxFields {
     AssetFreeze(AssetFreezeFields),
     #[serde(rename = "appl")]
     AppCall(AppCallFields),
-    //#[serde(rename = "cert")]
-    //CompactCert(CompactCertFields),
+    #[serde(rename = "stpf")]
+    StateProof(StateProofFields),
+    #[serde(rename = "hb", with = "nested_heartbeat")]
+    Heartbeat(Box<HeartbeatFields>),
+}
+
+impl TxFields {
+    /// Returns the type these fields are tagged with when encoded, e.g. `"pay"`.
+    pub fn type_str(&self) -> &'static str {
+        match self {
+            TxFields::Keyreg(_) => "keyreg",
+            TxFields::Payment(_) => "pay",
+            TxFields::AssetConfig(_) => "acfg",
+            TxFields::AssetTransfer(_) => "axfer",
+            TxFields::AssetFreeze(_) => "afrz",
+            TxFields::AppCall(_) => "appl",
+            TxFields::StateProof(_) => "stpf",
+            TxFields::Heartbeat(_) => "hb",
+        }
+    }
 }
 
 /// Wraps a transaction and a signature.
 /// It exposes a `verify()` method that verifies the signature
 /// and checks that the underlying transaction is well-formed.
 // TODO: update this documentation now that there's multisig
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SignedTx {
     #[serde(rename = "sig", default, skip_serializing_if = "is_default")]
     pub sig: Signature,
@@ -94,7 +166,7 @@ pub struct SignedTx {
 }
 
 /// Captures the fields used for key registration transactions.
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct KeyregFields {
     #[serde(rename = "votekey", default, skip_serializing_if = "is_default")]
     pub vote_pk: VotePK,
@@ -110,6 +182,68 @@ pub struct KeyregFields {
     pub nonparticipation: bool,
 }
 
+/// Captures the fields used for heartbeat transactions, which show that an online account
+/// is still participating in consensus.
+///
+/// Unlike those of the other transaction types, these fields are nested under `"hb"`.
+/// They are boxed in `TxFields`, as they are much larger than the fields of the other types.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeartbeatFields {
+    /// The account this heartbeat is for, which need not be the sender.
+    #[serde(rename = "a", default, skip_serializing_if = "is_default")]
+    pub address: Address,
+    #[serde(rename = "prf", default, skip_serializing_if = "is_default")]
+    pub proof: HeartbeatProof,
+    /// The block seed of the round before the heartbeat's first valid round.
+    #[serde(
+        rename = "sd",
+        default,
+        skip_serializing_if = "is_default",
+        with = "bytes"
+    )]
+    pub seed: [u8; 32],
+    /// The account's registered vote key, which the proof must be rooted in.
+    #[serde(rename = "vid", default, skip_serializing_if = "is_default")]
+    pub vote_id: VotePK,
+    /// The account's registered key dilution.
+    #[serde(rename = "kd", default, skip_serializing_if = "is_default")]
+    pub key_dilution: u64,
+}
+
+/// (De)serializes heartbeat fields nested under `"hb"`, as go-algorand embeds them by pointer.
+/// The map is present in every heartbeat, even if all its fields are empty.
+mod nested_heartbeat {
+    use alloc::boxed::Box;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::HeartbeatFields;
+
+    #[derive(Serialize)]
+    struct Nested<'a> {
+        hb: &'a HeartbeatFields,
+    }
+
+    #[derive(Deserialize)]
+    struct NestedOwned {
+        #[serde(default)]
+        hb: Box<HeartbeatFields>,
+    }
+
+    pub fn serialize<S: Serializer>(
+        fields: &HeartbeatFields,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        Nested { hb: fields }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Box<HeartbeatFields>, D::Error> {
+        Ok(NestedOwned::deserialize(deserializer)?.hb)
+    }
+}
+
 /// The fields used by payment transactions.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PaymentFields {
@@ -125,7 +259,7 @@ pub struct PaymentFields {
 }
 
 /// Fields used for asset allocation, re-configuration, and destruction.
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AssetConfigFields {
     /// ConfigAsset is the asset being configured or destroyed.
     /// A zero value means allocation.
@@ -139,7 +273,7 @@ pub struct AssetConfigFields {
 }
 
 /// Fields used for asset transfers.
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AssetTransferFields {
     #[serde(rename = "xaid", default, skip_serializing_if = "is_default")]
     pub transfer_asset: AssetIndex,
@@ -166,8 +300,21 @@ pub struct AssetTransferFields {
     pub asset_close_to: Address,
 }
 
+/// The different operations that are encoded as an asset transfer transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetTransferKind {
+    /// Zero-amount transfer to self, allocating the asset in the sender's account.
+    OptIn,
+    /// Removes the asset from the sender's account, closing remaining holdings to `asset_close_to`.
+    OptOut,
+    /// Revocation of assets from `asset_sender`, sent by the asset's clawback address.
+    Clawback,
+    /// Regular transfer of assets between two accounts.
+    Transfer,
+}
+
 /// Fields used for freezing asset slots.
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AssetFreezeFields {
     /// Address of the account whose asset slot is being frozen or un-frozen.
     #[serde(rename = "fadd", default, skip_serializing_if = "is_default")]
@@ -188,7 +335,12 @@ struct TxGroup {
     /// Specifies a list of hashes of transactions that must appear together,
     /// sequentially, in a block in order for the group to be valid.
     /// Each hash in the list is a hash of a transaction with the `group` field omitted.
-    #[serde(rename = "txlist", default, skip_serializing_if = "is_default")]
+    #[serde(
+        rename = "txlist",
+        default,
+        skip_serializing_if = "is_default",
+        with = "byte_vecs"
+    )]
     pub tx_group_hashes: Vec<Digest>,
 }
 
@@ -208,8 +360,8 @@ pub struct SuggestedParams {
     pub genesis_id: String,
 
     /// Genesis hash
-    #[serde(skip_serializing_if = "is_default")]
-    pub genesis_hash: Vec<u8>,
+    #[serde(skip_serializing_if = "is_default", with = "digest")]
+    pub genesis_hash: Digest,
 
     /// First protocol round on which the tx is valid.
     #[serde(skip_serializing_if = "is_default")]
@@ -230,22 +382,798 @@ pub struct SuggestedParams {
 
     /// The minimum transaction fee (not per byte) required for the txn to validate for the current network protocol.
     #[serde(skip_serializing_if = "is_default")]
-    pub min_fee: u64,
+    pub min_fee: MicroAlgos,
 }
 
 impl Transaction {
-    fn add_lease(&mut self, lease: &[u8; 32], fee_per_byte: u64) {
-        self.header.lease.copy_from_slice(lease);
-        self.header.fee = MicroAlgos(self.header.fee.0 + 37 * fee_per_byte);
+    /// Returns the bytes that are signed to authorize this transaction,
+    /// i.e. the canonical msgpack encoding of the transaction prefixed by `"TX"`.
+    pub fn bytes_to_sign(&self) -> Vec<u8> {
+        let mut bytes = TX_PREFIX.to_vec();
+        bytes.extend(encoding::to_msgpack(self));
+        bytes
+    }
+
+    /// Sets the fee as suggested by `params`, either the flat fee or the fee per byte
+    /// times the estimated size of the signed transaction, but at least the minimum fee.
+    ///
+    /// The transaction constructors already do this, but it has to be
+    /// repeated after changing the transaction's size, e.g. by adding a note.
+    pub fn set_fee_from_params(&mut self, params: &SuggestedParams) {
+        self.header.fee = params.fee;
+        if !params.flat_fee {
+            let stx = SignedTx {
+                sig: Signature::default(),
+                msig: None,
+                lsig: None,
+                tx: self.clone(),
+                auth_addr: Address::default(),
+            };
+            let size = stx.estimate_size() as u64;
+            self.header.fee = MicroAlgos(params.fee.0.saturating_mul(size)).max(params.min_fee);
+        }
+    }
+
+    /// Rekeys the sender's account to `new_auth`, which has to sign all of its
+    /// future transactions, until the next rekey. See `Header::rekey_to`.
+    ///
+    /// Since this changes the transaction's size, the fee may have to be updated afterwards.
+    pub fn rekey_to(&mut self, new_auth: Address) {
+        self.header.rekey_to = new_auth;
+    }
+
+    /// Returns the transaction's type as encoded in its `type` field, e.g. `"pay"`.
+    pub fn tx_type(&self) -> &'static str {
+        self.fields.type_str()
+    }
+
+    /// Returns the transaction ID, the base32 encoding of the transaction's hash.
+    pub fn id(&self) -> String {
+        BASE32_NOPAD.encode(&self.hash())
+    }
+
+    /// Computes the SHA512_256 hash of the `"TX"` prefixed transaction.
+    pub(crate) fn hash(&self) -> Digest {
+        crypto::hash_with_prefix(TX_PREFIX, self)
+    }
+}
+
+/// Computes the group ID of a list of transactions,
+/// ignoring any group IDs the transactions might already have.
+pub fn compute_group_id(txs: &[Transaction]) -> Result<Digest, GroupError> {
+    if txs.is_empty() {
+        return Err(GroupError::EmptyGroup);
+    } else if txs.len() > MAX_TX_GROUP_SIZE {
+        return Err(GroupError::TooLarge(txs.len()));
+    }
+
+    let tx_group = TxGroup {
+        tx_group_hashes: txs
+            .iter()
+            .map(|tx| {
+                let mut tx = tx.clone();
+                tx.header.group = Digest::default();
+                tx.hash()
+            })
+            .collect(),
+    };
+
+    Ok(crypto::hash_with_prefix(TX_GROUP_PREFIX, &tx_group))
+}
+
+/// Computes the group ID of the transactions and assigns it to each of them.
+pub fn assign_group_ids(txs: &mut [Transaction]) -> Result<(), GroupError> {
+    let group = compute_group_id(txs)?;
+    for tx in txs {
+        tx.header.group = group;
+    }
+    Ok(())
+}
+
+/// Verifies a group of signed transactions, as a node does before accepting it.
+///
+/// All transactions must carry the group ID computed from the whole group,
+/// and each must be properly signed. A single transaction may also have no group ID.
+pub fn verify_group(stxs: &[SignedTx]) -> Result<(), GroupError> {
+    let txs: Vec<Transaction> = stxs.iter().map(|stx| stx.tx.clone()).collect();
+    let group = compute_group_id(&txs)?;
+    let ungrouped = txs.len() == 1 && is_default(&txs[0].header.group);
+    if let Some(index) = txs
+        .iter()
+        .position(|tx| tx.header.group != group && !ungrouped)
+    {
+        return Err(GroupError::GroupIdMismatch(index));
+    }
+
+    for (index, stx) in stxs.iter().enumerate() {
+        stx.verify()
+            .map_err(|error| GroupError::InvalidSignature { index, error })?;
+    }
+    Ok(())
+}
+
+impl SignedTx {
+    /// Encodes the signed transaction into canonical msgpack, as expected by algod.
+    pub fn encode(&self) -> Vec<u8> {
+        encoding::to_msgpack(self)
+    }
+
+    /// Returns the size of the encoded signed transaction, which determines its fee.
+    ///
+    /// An unsigned transaction is measured as if it had a single signature.
+    pub fn estimate_size(&self) -> usize {
+        let unsigned = is_default(&self.sig) && self.msig.is_none() && self.lsig.is_none();
+        if unsigned {
+            let dummy = ed25519::Signature::from_bytes(&[1; ed25519::Signature::BYTE_SIZE])
+                .expect("dummy signature is well-formed");
+            let signed = SignedTx {
+                sig: Signature(dummy),
+                ..self.clone()
+            };
+            signed.encode().len()
+        } else {
+            self.encode().len()
+        }
+    }
+
+    /// Verifies that the transaction is authorized by exactly one valid signature,
+    /// either a single signature, a multisig, or a logic signature.
+    ///
+    /// The signature is checked against the `authorizing_address`.
+    pub fn verify(&self) -> Result<(), VerifyError> {
+        let has_sig = !is_default(&self.sig);
+        let signer = self.authorizing_address();
+        match (has_sig, &self.msig, &self.lsig) {
+            (false, None, None) => Err(VerifyError::NoSignature),
+            (true, None, None) => {
+                let key =
+                    PublicKey::from_bytes(&signer.0).map_err(|_| VerifyError::InvalidSignature)?;
+                key.verify(&self.tx.bytes_to_sign(), &self.sig.0)
+                    .map_err(|_| VerifyError::InvalidSignature)
+            }
+            (false, Some(msig), None) => {
+                if bool::from(msig.address().ct_eq(&signer))
+                    && msig.verify(&self.tx.bytes_to_sign())
+                {
+                    Ok(())
+                } else {
+                    Err(VerifyError::InvalidSignature)
+                }
+            }
+            (false, None, Some(lsig)) => {
+                if lsig.verify(&signer) {
+                    Ok(())
+                } else {
+                    Err(VerifyError::InvalidSignature)
+                }
+            }
+            _ => Err(VerifyError::MultipleSignatures),
+        }
+    }
+
+    /// Returns the address whose key authorizes this transaction,
+    /// which is `auth_addr` if the sender was rekeyed and the sender otherwise.
+    pub fn authorizing_address(&self) -> Address {
+        if self.auth_addr.is_zero() {
+            self.tx.header.sender
+        } else {
+            self.auth_addr
+        }
+    }
+
+    /// Decodes a signed transaction from base64 encoded msgpack,
+    /// the format used by algod's pending transaction endpoints.
+    #[cfg(feature = "std")]
+    pub fn from_base64(s: &str) -> Result<SignedTx, DecodeError> {
+        encoding::from_base64_msgpack(s)
+    }
+
+    /// Decodes a signed transaction from msgpack, rejecting any non-canonical encoding.
+    /// See `encoding::from_msgpack_strict`.
+    #[cfg(feature = "std")]
+    pub fn from_msgpack_strict(bytes: &[u8]) -> Result<SignedTx, DecodeError> {
+        encoding::from_msgpack_strict(bytes)
+    }
+
+    /// Formats the signed transaction as pretty-printed JSON, like `goal clerk inspect` does:
+    /// with the msgpack field names as keys, addresses as base32, and other bytes as base64.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("signed transactions are always serializable")
+    }
+
+    /// Encodes the signed transaction into standard (padded) base64 of its canonical msgpack,
+    /// the format wallets exchange signed transactions in.
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(&self.encode())
+    }
+}
+
+impl AssetTransferFields {
+    /// Determines which kind of operation this transfer, sent by `sender`, represents.
+    pub fn classify(&self, sender: &Address) -> AssetTransferKind {
+        if !self.asset_sender.is_zero() {
+            AssetTransferKind::Clawback
+        } else if !self.asset_close_to.is_zero() {
+            AssetTransferKind::OptOut
+        } else if self.asset_amount == 0 && &self.asset_receiver == sender {
+            AssetTransferKind::OptIn
+        } else {
+            AssetTransferKind::Transfer
+        }
+    }
+}
+
+/// Formats the note, hashes, and lease as base64.
+impl fmt::Debug for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Header")
+            .field("sender", &self.sender)
+            .field("fee", &self.fee)
+            .field("first_valid", &self.first_valid)
+            .field("last_valid", &self.last_valid)
+            .field("note", &Base64Debug(&self.note))
+            .field("genesis_id", &self.genesis_id)
+            .field("genesis_hash", &Base64Debug(&self.genesis_hash))
+            .field("group", &Base64Debug(&self.group))
+            .field("lease", &Base64Debug(&self.lease))
+            .field("rekey_to", &self.rekey_to)
+            .finish()
+    }
+}
+
+/// Formats the participation keys as base64.
+impl fmt::Debug for KeyregFields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeyregFields")
+            .field("vote_pk", &self.vote_pk)
+            .field("selection_pk", &self.selection_pk)
+            .field("vote_first", &self.vote_first)
+            .field("vote_last", &self.vote_last)
+            .field("vote_key_dilution", &self.vote_key_dilution)
+            .field("nonparticipation", &self.nonparticipation)
+            .finish()
+    }
+}
+
+/// Formats the seed as base64.
+impl fmt::Debug for HeartbeatFields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HeartbeatFields")
+            .field("address", &self.address)
+            .field("proof", &self.proof)
+            .field("seed", &Base64Debug(&self.seed))
+            .field("vote_id", &self.vote_id)
+            .field("key_dilution", &self.key_dilution)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use data_encoding::BASE64;
+
+    use crate::account::Account;
+    use crate::mnemonic;
+
+    /// Payment transaction from the go-algorand-sdk test suite (`TestMakePaymentTxn`).
+    fn golden_payment() -> Transaction {
+        Transaction {
+            header: Header {
+                sender: Address::from_str(
+                    "47YPQTIGQEO7T4Y4RWDYWEKV6RTR2UNBQXBABEEGM72ESWDQNCQ52OPASU",
+                )
+                .unwrap(),
+                fee: MicroAlgos(1176),
+                first_valid: Round(12466),
+                last_valid: Round(13466),
+                note: BASE64.decode(b"6gAVR0Nsv5Y=").unwrap(),
+                genesis_id: "devnet-v33.0".to_owned(),
+                genesis_hash: BASE64
+                    .decode(b"JgsgCaCTqIaLeVhyL6XlRu3n7Rfk2FxMeK+wRSaQ7dI=")
+                    .unwrap()
+                    .try_into()
+                    .unwrap(),
+                ..Default::default()
+            },
+            fields: TxFields::Payment(PaymentFields {
+                receiver: Address::from_str(
+                    "PNWOET7LLOWMBMLE4KOCELCX6X3D3Q4H2Q4QJASYIEOF7YIPPQBG3YQ5YI",
+                )
+                .unwrap(),
+                amount: MicroAlgos(1000),
+                close_remainder_to: Some(
+                    Address::from_str("IDUTJEUIEVSMXTU4LGTJWZ2UE2E6TIODUKU6UW3FU3UKIQQ77RLUBBBFLA")
+                        .unwrap(),
+                ),
+            }),
+        }
+    }
+
+    const MNEMONIC: &str = "advice pudding treat near rule blouse same whisper inner electric quit surface sunny dismiss leader blood seat clown cost exist hospital century reform able sponsor";
+    const GOLDEN: &str = "gqNzaWfEQPhUAZ3xkDDcc8FvOVo6UinzmKBCqs0woYSfodlmBMfQvGbeUx3Srxy3dyJDzv7rLm26BRv9FnL2/AuT7NYfiAWjdHhui6NhbXTNA+ilY2xvc2XEIEDpNJKIJWTLzpxZpptnVCaJ6aHDoqnqW2Wm6KRCH/xXo2ZlZc0EmKJmds0wsqNnZW6sZGV2bmV0LXYzMy4womdoxCAmCyAJoJOohot5WHIvpeVG7eftF+TYXEx4r7BFJpDt0qJsds00mqRub3RlxAjqABVHQ2y/lqNyY3bEIHts4k/rW6zAsWTinCIsV/X2PcOH1DkEglhBHF/hD3wCo3NuZMQg5/D4TQaBHfnzHI2HixFV9GcdUaGFwgCQhmf0SVhwaKGkdHlwZaNwYXk=";
+
+    #[test]
+    fn encode_signed_payment() {
+        let account = Account::from_seed(&mnemonic::to_key(MNEMONIC).unwrap());
+        let tx = golden_payment();
+        assert_eq!(account.address(), tx.header.sender);
+
+        let stx = account.sign_transaction(&tx);
+        assert_eq!(stx.encode(), BASE64.decode(GOLDEN.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn signed_payment_json() {
+        let stx = SignedTx::from_base64(GOLDEN).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&stx.to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "sig": "+FQBnfGQMNxzwW85WjpSKfOYoEKqzTChhJ+h2WYEx9C8Zt5THdKvHLd3IkPO/usubboFG/0Wcvb8C5Ps1h+IBQ==",
+                "txn": {
+                    "amt": 1000,
+                    "close": "IDUTJEUIEVSMXTU4LGTJWZ2UE2E6TIODUKU6UW3FU3UKIQQ77RLUBBBFLA",
+                    "fee": 1176,
+                    "fv": 12466,
+                    "gen": "devnet-v33.0",
+                    "gh": "JgsgCaCTqIaLeVhyL6XlRu3n7Rfk2FxMeK+wRSaQ7dI=",
+                    "lv": 13466,
+                    "note": "6gAVR0Nsv5Y=",
+                    "rcv": "PNWOET7LLOWMBMLE4KOCELCX6X3D3Q4H2Q4QJASYIEOF7YIPPQBG3YQ5YI",
+                    "snd": "47YPQTIGQEO7T4Y4RWDYWEKV6RTR2UNBQXBABEEGM72ESWDQNCQ52OPASU",
+                    "type": "pay",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn signed_app_call_json() {
+        let account = Account::from_seed(&[1; 32]);
+        let tx = Transaction {
+            header: Header {
+                sender: account.address(),
+                lease: [1; 32],
+                ..Default::default()
+            },
+            fields: TxFields::AppCall(AppCallFields {
+                application_id: 7,
+                application_args: vec![b"hello".to_vec()],
+                accounts: vec![account.address()],
+                boxes: vec![BoxRef {
+                    app_index: 0,
+                    name: b"box".to_vec(),
+                }],
+                ..Default::default()
+            }),
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&account.sign_transaction(&tx).to_json()).unwrap();
+
+        let address = "RKEOHXLUBHYZL7KS3MWTZOS5OLFGOCN7DWKBEG7TOSEADNAPN5OOTUNSLE";
+        let txn = &json["txn"];
+        assert_eq!(txn["type"], "appl");
+        assert_eq!(txn["snd"], address);
+        assert_eq!(txn["lx"], BASE64.encode(&[1; 32]));
+        assert_eq!(txn["apid"], 7);
+        assert_eq!(txn["apaa"], serde_json::json!(["aGVsbG8="]));
+        assert_eq!(txn["apat"], serde_json::json!([address]));
+        assert_eq!(txn["apbx"], serde_json::json!([{"n": "Ym94"}]));
+        assert!(json["sig"].is_string());
+    }
+
+    #[test]
+    fn payment_tx_id() {
+        assert_eq!(
+            golden_payment().id(),
+            "5FJDJD5LMZC3EHUYYJNH5I23U4X6H2KXABNDGPIL557ZMJ33GZHQ"
+        );
+    }
+
+    #[test]
+    fn group_id() {
+        // from the go-algorand-sdk test suite (`TestComputeGroupID`)
+        const GOLDEN_TX1: &str = "gaN0eG6Ko2FtdM0H0KNmZWXNA+iiZnbOAArW/6NnZW6rZGV2bmV0LXYxLjCiZ2jEILAtz+3tknW6iiStLW4gnSvbXUqW3ul3ghinaDc5pY9Bomx2zgAK2uekbm90ZcQIwRKw5cJ0CMqjcmN2xCCj8AKs8kPYlx63ppj1w5410qkMRGZ9FYofNYPXxGpNLKNzbmTEIKPwAqzyQ9iXHremmPXDnjXSqQxEZn0Vih81g9fEak0spHR5cGWjcGF5";
+        const GOLDEN_TX2: &str = "gaN0eG6Ko2FtdM0H0KNmZWXNA+iiZnbOAArXc6NnZW6rZGV2bmV0LXYxLjCiZ2jEILAtz+3tknW6iiStLW4gnSvbXUqW3ul3ghinaDc5pY9Bomx2zgAK21ukbm90ZcQIdBlHI6BdrIijcmN2xCCj8AKs8kPYlx63ppj1w5410qkMRGZ9FYofNYPXxGpNLKNzbmTEIKPwAqzyQ9iXHremmPXDnjXSqQxEZn0Vih81g9fEak0spHR5cGWjcGF5";
+        const GOLDEN_GROUP: &str = "gaN0eG6Lo2FtdM0H0KNmZWXNA+iiZnbOAArW/6NnZW6rZGV2bmV0LXYxLjCiZ2jEILAtz+3tknW6iiStLW4gnSvbXUqW3ul3ghinaDc5pY9Bo2dycMQgLiQ9OBup9H/bZLSfQUH2S6iHUM6FQ3PLuv9FNKyt09SibHbOAAra56Rub3RlxAjBErDlwnQIyqNyY3bEIKPwAqzyQ9iXHremmPXDnjXSqQxEZn0Vih81g9fEak0so3NuZMQgo/ACrPJD2Jcet6aY9cOeNdKpDERmfRWKHzWD18RqTSykdHlwZaNwYXmBo3R4boujYW10zQfQo2ZlZc0D6KJmds4ACtdzo2dlbqtkZXZuZXQtdjEuMKJnaMQgsC3P7e2SdbqKJK0tbiCdK9tdSpbe6XeCGKdoNzmlj0GjZ3JwxCAuJD04G6n0f9tktJ9BQfZLqIdQzoVDc8u6/0U0rK3T1KJsds4ACttbpG5vdGXECHQZRyOgXayIo3JjdsQgo/ACrPJD2Jcet6aY9cOeNdKpDERmfRWKHzWD18RqTSyjc25kxCCj8AKs8kPYlx63ppj1w5410qkMRGZ9FYofNYPXxGpNLKR0eXBlo3BheQ==";
+
+        let mut stxs = [GOLDEN_TX1, GOLDEN_TX2].map(|s| SignedTx::from_base64(s).unwrap());
+        let mut txs = stxs.clone().map(|stx| stx.tx);
+        let group = compute_group_id(&txs).unwrap();
+        assign_group_ids(&mut txs).unwrap();
+
+        let mut encoded = Vec::new();
+        for (stx, tx) in stxs.iter_mut().zip(txs) {
+            assert_eq!(tx.header.group, group);
+            stx.tx = tx;
+            encoded.extend(stx.encode());
+        }
+        assert_eq!(BASE64.encode(&encoded), GOLDEN_GROUP);
+
+        // recomputing ignores the already assigned group
+        let txs = stxs.map(|stx| stx.tx);
+        assert_eq!(compute_group_id(&txs), Ok(group));
+    }
+
+    #[test]
+    fn verify_signed_group() {
+        let account = Account::from_seed(&[1; 32]);
+        let mut txs = vec![golden_payment(); 3];
+        for (i, tx) in txs.iter_mut().enumerate() {
+            tx.header.sender = account.address();
+            tx.header.first_valid = Round(i as u64);
+        }
+        let sign = |txs: &[Transaction]| -> Vec<SignedTx> {
+            txs.iter().map(|tx| account.sign_transaction(tx)).collect()
+        };
+
+        let lone = txs[0].clone();
+        assign_group_ids(&mut txs).unwrap();
+        assert_eq!(verify_group(&sign(&txs)), Ok(()));
+
+        // a single transaction needs no group
+        assert_eq!(verify_group(&[account.sign_transaction(&lone)]), Ok(()));
+
+        // one member moved to another group, signed after the change
+        let mut tampered = txs.clone();
+        tampered[1].header.group = [1; 32];
+        assert_eq!(
+            verify_group(&sign(&tampered)),
+            Err(GroupError::GroupIdMismatch(1))
+        );
+
+        // group ID changed after signing
+        let mut stxs = sign(&txs);
+        stxs[2].tx.header.group = [1; 32];
+        assert_eq!(verify_group(&stxs), Err(GroupError::GroupIdMismatch(2)));
+
+        // a member not part of the group
+        let mut stxs = sign(&txs);
+        stxs[0] = account.sign_transaction(&lone);
+        assert_eq!(verify_group(&stxs), Err(GroupError::GroupIdMismatch(0)));
+
+        let mut stxs = sign(&txs);
+        stxs[1].sig = Signature::default();
+        assert_eq!(
+            verify_group(&stxs),
+            Err(GroupError::InvalidSignature {
+                index: 1,
+                error: VerifyError::NoSignature
+            })
+        );
     }
 
-    fn add_lease_with_flat_fee(&mut self, lease: &[u8; 32], flat_fee: u64) {
-        self.header.lease.copy_from_slice(lease);
-        self.header.fee = MicroAlgos(flat_fee);
+    #[test]
+    fn group_size_limits() {
+        assert_eq!(compute_group_id(&[]), Err(GroupError::EmptyGroup));
+
+        let txs = vec![golden_payment(); MAX_TX_GROUP_SIZE + 1];
+        assert_eq!(
+            compute_group_id(&txs),
+            Err(GroupError::TooLarge(MAX_TX_GROUP_SIZE + 1))
+        );
+        assert!(compute_group_id(&txs[..MAX_TX_GROUP_SIZE]).is_ok());
+    }
+
+    #[test]
+    fn fee_from_params() {
+        let mut params = SuggestedParams {
+            fee: MicroAlgos(1),
+            min_fee: MicroAlgos(1000),
+            ..Default::default()
+        };
+        let mut tx = golden_payment();
+        tx.set_fee_from_params(&params);
+        assert_eq!(tx.header.fee, MicroAlgos(1000));
+
+        // the golden payment was created with a fee of 4 per byte
+        params.fee = MicroAlgos(4);
+        tx.set_fee_from_params(&params);
+        assert_eq!(tx.header.fee, MicroAlgos(1176));
+
+        params.flat_fee = true;
+        tx.set_fee_from_params(&params);
+        assert_eq!(tx.header.fee, MicroAlgos(4));
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        let stx = SignedTx::from_base64(GOLDEN).unwrap();
+        assert_eq!(stx.to_base64(), GOLDEN);
+        assert!(SignedTx::from_base64(&stx.to_base64()).unwrap() == stx);
+
+        let account = Account::from_seed(&[1; 32]);
+        let stx = account.sign_transaction(&golden_payment());
+        assert!(SignedTx::from_base64(&stx.to_base64()).unwrap() == stx);
+    }
+
+    #[test]
+    fn estimate_size() {
+        let stx = SignedTx::from_base64(GOLDEN).unwrap();
+        let size = BASE64.decode(GOLDEN.as_bytes()).unwrap().len();
+        assert_eq!(stx.estimate_size(), size);
+        assert!((200..300).contains(&size));
+
+        let unsigned = SignedTx {
+            sig: Signature::default(),
+            ..stx
+        };
+        assert_eq!(unsigned.estimate_size(), size);
+    }
+
+    #[test]
+    fn verify_signed_payment() {
+        let stx = SignedTx::from_base64(GOLDEN).unwrap();
+        assert_eq!(stx.verify(), Ok(()));
+
+        let mut tampered = stx.clone();
+        tampered.tx.header.fee = MicroAlgos(1);
+        assert_eq!(tampered.verify(), Err(VerifyError::InvalidSignature));
+
+        let mut unsigned = stx.clone();
+        unsigned.sig = Signature::default();
+        assert_eq!(unsigned.verify(), Err(VerifyError::NoSignature));
+
+        let mut double_signed = stx;
+        double_signed.msig = Some(MultisigSignature::default());
+        assert_eq!(double_signed.verify(), Err(VerifyError::MultipleSignatures));
+    }
+
+    #[test]
+    fn verify_auth_addr() {
+        let account = Account::from_seed(&mnemonic::to_key(MNEMONIC).unwrap());
+        let mut tx = golden_payment();
+        tx.header.sender = Address([3; 32]);
+
+        let mut stx = account.sign_transaction(&tx);
+        assert_eq!(stx.auth_addr, account.address());
+        assert_eq!(stx.verify(), Ok(()));
+
+        stx.auth_addr = Address::default();
+        assert_eq!(stx.verify(), Err(VerifyError::InvalidSignature));
+    }
+
+    #[test]
+    fn rekeyed_account() {
+        let old_key = Account::from_seed(&[1; 32]);
+        let new_key = Account::from_seed(&[2; 32]);
+
+        let mut rekey = golden_payment();
+        rekey.header.sender = old_key.address();
+        rekey.rekey_to(new_key.address());
+        assert_eq!(rekey.header.rekey_to, new_key.address());
+        let stx = old_key.sign_transaction(&rekey);
+        assert_eq!(stx.authorizing_address(), old_key.address());
+        assert_eq!(stx.verify(), Ok(()));
+
+        let mut tx = golden_payment();
+        tx.header.sender = old_key.address();
+        let stx = new_key.sign_transaction(&tx);
+        assert_eq!(stx.authorizing_address(), new_key.address());
+        assert_eq!(stx.verify(), Ok(()));
+
+        let mut stale = old_key.sign_transaction(&tx);
+        stale.auth_addr = new_key.address();
+        assert_eq!(stale.verify(), Err(VerifyError::InvalidSignature));
+    }
+
+    #[test]
+    fn verify_multisig() {
+        let accounts: Vec<Account> = (1..=3).map(|i| Account::from_seed(&[i; 32])).collect();
+        let keys: Vec<_> = accounts.iter().map(|a| a.public_key()).collect();
+        let mut msig = MultisigSignature::new(1, 2, &keys);
+        let mut tx = golden_payment();
+        tx.header.sender = msig.address();
+        msig.sign(accounts[0].keypair(), &tx).unwrap();
+        msig.sign(accounts[1].keypair(), &tx).unwrap();
+
+        let stx = SignedTx {
+            sig: Signature::default(),
+            msig: Some(msig),
+            lsig: None,
+            tx,
+            auth_addr: Address::default(),
+        };
+        assert_eq!(stx.verify(), Ok(()));
+
+        let mut other_sender = stx.clone();
+        other_sender.tx.header.sender = Address([3; 32]);
+        assert_eq!(other_sender.verify(), Err(VerifyError::InvalidSignature));
+
+        let mut below_threshold = stx;
+        below_threshold.msig.as_mut().unwrap().subsigs[1].sig = None;
+        assert_eq!(below_threshold.verify(), Err(VerifyError::InvalidSignature));
+    }
+
+    #[test]
+    fn decode_signed_payment() {
+        let stx = SignedTx::from_base64(GOLDEN).unwrap();
+        assert!(stx.tx == golden_payment());
+        assert!(stx.msig.is_none() && stx.lsig.is_none());
+        assert_eq!(BASE64.encode(&stx.encode()), GOLDEN);
+    }
+
+    #[test]
+    fn decode_invalid_signed_tx() {
+        assert_eq!(
+            SignedTx::from_base64("not base64!").err(),
+            Some(DecodeError::InvalidBase64)
+        );
+        assert!(matches!(
+            SignedTx::from_base64(&BASE64.encode(&[0x81, 0xa3, b's', b'i', b'g'])),
+            Err(DecodeError::InvalidMsgpack(_))
+        ));
+    }
+
+    #[test]
+    fn classify_asset_transfer() {
+        let sender = Address([1; 32]);
+        let other = Address([2; 32]);
+        let opt_in = AssetTransferFields {
+            transfer_asset: 10,
+            asset_receiver: sender,
+            ..Default::default()
+        };
+        assert_eq!(opt_in.classify(&sender), AssetTransferKind::OptIn);
+
+        let opt_out = AssetTransferFields {
+            asset_close_to: other,
+            ..opt_in.clone()
+        };
+        assert_eq!(opt_out.classify(&sender), AssetTransferKind::OptOut);
+
+        let clawback = AssetTransferFields {
+            asset_sender: other,
+            asset_receiver: sender,
+            asset_amount: 5,
+            ..opt_in.clone()
+        };
+        assert_eq!(clawback.classify(&sender), AssetTransferKind::Clawback);
+
+        let transfer = AssetTransferFields {
+            asset_receiver: other,
+            asset_amount: 5,
+            ..opt_in.clone()
+        };
+        assert_eq!(transfer.classify(&sender), AssetTransferKind::Transfer);
+
+        // zero amount to another account is still a transfer, not an opt-in
+        let zero_transfer = AssetTransferFields {
+            asset_receiver: other,
+            ..opt_in
+        };
+        assert_eq!(zero_transfer.classify(&sender), AssetTransferKind::Transfer);
+    }
+
+    #[test]
+    fn debug_readable() {
+        let stx = Account::from_seed(&[1; 32]).sign_transaction(&golden_payment());
+        let debug = format!("{:?}", stx);
+        assert!(debug.contains(
+            "sender: Address(47YPQTIGQEO7T4Y4RWDYWEKV6RTR2UNBQXBABEEGM72ESWDQNCQ52OPASU)"
+        ));
+        assert!(debug.contains("genesis_hash: JgsgCaCTqIaLeVhyL6XlRu3n7Rfk2FxMeK+wRSaQ7dI="));
+        assert!(debug.contains("note: 6gAVR0Nsv5Y="));
+        assert!(debug.contains("amount: MicroAlgos(1000)"));
+        assert!(!debug.contains("[0, 0"), "{}", debug);
+    }
+
+    /// Re-encodes a msgpack encoded signed transaction after editing the fields of its transaction.
+    fn edit_txn_fields(
+        blob: &[u8],
+        edit: impl FnOnce(&mut Vec<(rmpv::Value, rmpv::Value)>),
+    ) -> Vec<u8> {
+        let mut value = rmpv::decode::read_value(&mut &blob[..]).unwrap();
+        let rmpv::Value::Map(entries) = &mut value else {
+            panic!("not a map");
+        };
+        let (_, txn) = entries
+            .iter_mut()
+            .find(|(k, _)| k.as_str() == Some("txn"))
+            .unwrap();
+        let rmpv::Value::Map(fields) = txn else {
+            panic!("not a map");
+        };
+        edit(fields);
+        let mut edited = Vec::new();
+        rmpv::encode::write_value(&mut edited, &value).unwrap();
+        edited
+    }
+
+    #[test]
+    fn reject_duplicate_keys() {
+        let stx = Account::from_seed(&[1; 32]).sign_transaction(&golden_payment());
+        // a second amount in the transaction, which another decoder could take instead
+        let blob = edit_txn_fields(&stx.encode(), |fields| {
+            fields.push(("amt".into(), 2_000_000.into()));
+        });
+
+        assert!(matches!(
+            encoding::from_msgpack::<SignedTx>(&blob),
+            Err(DecodeError::DuplicateKey(key)) if key == "\"amt\""
+        ));
+    }
+
+    #[test]
+    fn strict_decoding() {
+        let stx = Account::from_seed(&[1; 32]).sign_transaction(&golden_payment());
+        let blob = stx.encode();
+        assert_eq!(SignedTx::from_msgpack_strict(&blob), Ok(stx.clone()));
+
+        let reason = |blob: &[u8]| match SignedTx::from_msgpack_strict(blob) {
+            Err(DecodeError::NonCanonical(reason)) => reason,
+            other => panic!("unexpected result {:?}", other),
+        };
+
+        // fee 1176 as uint64 instead of uint16
+        let fee = [0xa3, b'f', b'e', b'e', 0xcd, 0x04, 0x98];
+        let pos = blob.windows(fee.len()).position(|w| w == fee).unwrap();
+        let mut wide = blob[..pos + 4].to_vec();
+        wide.extend_from_slice(&[0xcf, 0, 0, 0, 0, 0, 0, 0x04, 0x98]);
+        wide.extend_from_slice(&blob[pos + fee.len()..]);
+        assert_eq!(encoding::from_msgpack::<SignedTx>(&wide), Ok(stx.clone()));
+        assert_eq!(reason(&wide), "values are not minimally encoded");
+
+        let unsorted = edit_txn_fields(&blob, |fields| fields.swap(0, 1));
+        assert_eq!(
+            encoding::from_msgpack::<SignedTx>(&unsorted),
+            Ok(stx.clone())
+        );
+        assert!(reason(&unsorted).contains("is not sorted before"));
+
+        let unknown = edit_txn_fields(&blob, |fields| fields.push(("zzz".into(), 1.into())));
+        assert_eq!(
+            encoding::from_msgpack::<SignedTx>(&unknown),
+            Ok(stx.clone())
+        );
+        assert_eq!(reason(&unknown), "unknown field \"zzz\"");
+
+        // an explicit zero lease, sorted right before the note
+        let empty = edit_txn_fields(&blob, |fields| {
+            let pos = fields.iter().position(|(k, _)| k.as_str() == Some("note"));
+            let lease = rmpv::Value::Binary(vec![0; 32]);
+            fields.insert(pos.unwrap(), ("lx".into(), lease));
+        });
+        assert_eq!(encoding::from_msgpack::<SignedTx>(&empty), Ok(stx.clone()));
+        assert_eq!(reason(&empty), "empty field \"lx\"");
+
+        let mut trailing = blob.clone();
+        trailing.push(0xc0);
+        assert_eq!(reason(&trailing), "1 trailing bytes");
     }
 
-    fn rekey(&mut self, addr: String) -> Result<(), AddressError> {
-        self.header.rekey_to = Address::from_str(&addr)?;
-        Ok(())
+    #[test]
+    fn tx_type() {
+        let all_fields = [
+            TxFields::Keyreg(Default::default()),
+            TxFields::Payment(Default::default()),
+            TxFields::AssetConfig(AssetConfigFields {
+                config_asset: 1,
+                asset_params: AssetParams::default(),
+            }),
+            TxFields::AssetTransfer(Default::default()),
+            TxFields::AssetFreeze(AssetFreezeFields {
+                freeze_account: Address::default(),
+                freeze_asset: 1,
+                asset_frozen: true,
+            }),
+            TxFields::AppCall(Default::default()),
+            TxFields::StateProof(Default::default()),
+            TxFields::Heartbeat(Default::default()),
+        ];
+        for fields in all_fields {
+            let tx = Transaction {
+                header: Header::default(),
+                fields,
+            };
+            let encoded = encoding::to_msgpack(&tx);
+            let value = rmpv::decode::read_value(&mut &encoded[..]).unwrap();
+            assert_eq!(value["type"].as_str(), Some(tx.tx_type()));
+        }
+        assert_eq!(golden_payment().tx_type(), "pay");
     }
 }
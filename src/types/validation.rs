@@ -0,0 +1,642 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Checks of a transaction's well-formedness before submitting it.
+
+use thiserror::Error;
+
+use super::applications::{
+    ENCODED_MAX_ACCOUNTS, ENCODED_MAX_APPLICATION_ARGS, ENCODED_MAX_BOXES,
+    ENCODED_MAX_FOREIGN_APPS, ENCODED_MAX_FOREIGN_ASSETS,
+};
+use super::basics::{MAX_TXN_LIFE, MAX_TX_NOTE_BYTES};
+use super::*;
+use crate::util::is_default;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum WellFormedError {
+    #[error("sender must not be the zero address")]
+    ZeroSender,
+    #[error("first valid round {first} is after last valid round {last}")]
+    InvalidValidityRange { first: Round, last: Round },
+    #[error("validity range from round {first} to {last} is longer than {MAX_TXN_LIFE} rounds")]
+    ValidityRangeTooLong { first: Round, last: Round },
+    #[error("fee of {fee} Algos is below the minimum of {min_fee} Algos")]
+    FeeTooLow {
+        fee: MicroAlgos,
+        min_fee: MicroAlgos,
+    },
+    #[error("fees of {fee} Algos in total are below the group's minimum of {min_fee} Algos")]
+    GroupFeeTooLow {
+        fee: MicroAlgos,
+        min_fee: MicroAlgos,
+    },
+    #[error("genesis ID does not match the network's")]
+    GenesisIdMismatch,
+    #[error("genesis hash does not match the network's")]
+    GenesisHashMismatch,
+    #[error("note is {0} bytes long, more than the maximum of {MAX_TX_NOTE_BYTES}")]
+    NoteTooLong(usize),
+    #[error("state proof transactions must be sent from the state proof sender")]
+    InvalidStateProofSender,
+    #[error("state proof transactions must not have a fee, note, group, lease, or rekey address")]
+    StateProofHeaderNotEmpty,
+    #[error("heartbeats below the minimum fee must not have a note, lease, or rekey address unless grouped")]
+    CheapHeartbeatNotEmpty,
+    #[error("heartbeats require a proof, seed, vote key, and key dilution")]
+    IncompleteHeartbeat,
+    #[error("payment to the zero address without closing the account")]
+    ZeroReceiver,
+    #[error("account must not be closed to its own address")]
+    CloseToSender,
+    #[error("going online requires both a vote and a selection key")]
+    MissingParticipationKeys,
+    #[error("first voting round {first} is after last voting round {last}")]
+    InvalidVoteRange { first: Round, last: Round },
+    #[error("going non-participating must not register participation keys")]
+    NonparticipationWithKeys,
+    #[error("asset ID must not be zero")]
+    ZeroAssetId,
+    #[error("invalid asset parameters: {0}")]
+    InvalidAssetParams(#[from] AssetParamsError),
+    #[error("both the approval and the clear state program are required")]
+    MissingProgram,
+    #[error("programs may only be set when creating or updating an application")]
+    UnexpectedProgram,
+    #[error("{0} application args, more than the maximum of {ENCODED_MAX_APPLICATION_ARGS}")]
+    TooManyAppArgs(usize),
+    #[error("{0} accounts, more than the maximum of {ENCODED_MAX_ACCOUNTS}")]
+    TooManyAccounts(usize),
+    #[error("{0} foreign apps, more than the maximum of {ENCODED_MAX_FOREIGN_APPS}")]
+    TooManyForeignApps(usize),
+    #[error("{0} foreign assets, more than the maximum of {ENCODED_MAX_FOREIGN_ASSETS}")]
+    TooManyForeignAssets(usize),
+    #[error("{0} box references, more than the maximum of {ENCODED_MAX_BOXES}")]
+    TooManyBoxes(usize),
+}
+
+impl Transaction {
+    /// Checks that the transaction is well-formed and matches the network described by `params`,
+    /// which the network would otherwise reject it for.
+    ///
+    /// This does not check anything depending on the ledger state, like balances,
+    /// or whether the transaction is still valid in the current round.
+    ///
+    /// The fees of a group are pooled, so the fee of a transaction with a group ID
+    /// is not checked here, but by `group_well_formed` for the whole group.
+    pub fn well_formed(&self, params: &SuggestedParams) -> Result<(), WellFormedError> {
+        let min_fee = if is_default(&self.header.group) {
+            self.min_fee(params)
+        } else {
+            MicroAlgos(0)
+        };
+        self.header.well_formed(params, min_fee)?;
+        match &self.fields {
+            TxFields::Payment(fields) => fields.well_formed(&self.header.sender),
+            TxFields::Keyreg(fields) => fields.well_formed(),
+            TxFields::AssetConfig(fields) => fields.well_formed(),
+            TxFields::AssetTransfer(fields) => fields.well_formed(),
+            TxFields::AssetFreeze(fields) => fields.well_formed(),
+            TxFields::AppCall(fields) => fields.well_formed(),
+            TxFields::StateProof(_) => self.header.state_proof_well_formed(),
+            TxFields::Heartbeat(fields) => fields.well_formed(&self.header, params),
+        }
+    }
+
+    /// Returns the minimum fee of this transaction on its own.
+    fn min_fee(&self, params: &SuggestedParams) -> MicroAlgos {
+        // State proofs are issued by the protocol itself, and heartbeats may be free.
+        match self.fields {
+            TxFields::StateProof(_) | TxFields::Heartbeat(_) => MicroAlgos(0),
+            _ => params.min_fee,
+        }
+    }
+}
+
+/// Checks that each transaction of a group is well-formed (see `Transaction::well_formed`)
+/// and that their fees together cover the sum of their minimum fees.
+///
+/// This allows one transaction to pay the fees of others in the group.
+/// It does not check the group ID, which `verify_group` does.
+pub fn group_well_formed(
+    txs: &[Transaction],
+    params: &SuggestedParams,
+) -> Result<(), WellFormedError> {
+    for tx in txs {
+        tx.well_formed(params)?;
+    }
+    let fee = saturating_sum(txs.iter().map(|tx| tx.header.fee));
+    let min_fee = saturating_sum(txs.iter().map(|tx| tx.min_fee(params)));
+    if fee < min_fee {
+        return Err(WellFormedError::GroupFeeTooLow { fee, min_fee });
+    }
+    Ok(())
+}
+
+/// Sums up untrusted fees without panicking, as any sum that overflows suffices anyway.
+fn saturating_sum(fees: impl Iterator<Item = MicroAlgos>) -> MicroAlgos {
+    fees.fold(MicroAlgos(0), |sum, fee| {
+        MicroAlgos(sum.0.saturating_add(fee.0))
+    })
+}
+
+impl Header {
+    fn well_formed(
+        &self,
+        params: &SuggestedParams,
+        min_fee: MicroAlgos,
+    ) -> Result<(), WellFormedError> {
+        let (first, last) = (self.first_valid, self.last_valid);
+        if self.sender.is_zero() {
+            return Err(WellFormedError::ZeroSender);
+        } else if first > last {
+            return Err(WellFormedError::InvalidValidityRange { first, last });
+        } else if last - first > MAX_TXN_LIFE {
+            return Err(WellFormedError::ValidityRangeTooLong { first, last });
+        } else if self.fee < min_fee {
+            return Err(WellFormedError::FeeTooLow {
+                fee: self.fee,
+                min_fee,
+            });
+        } else if !self.genesis_id.is_empty() && self.genesis_id != params.genesis_id {
+            return Err(WellFormedError::GenesisIdMismatch);
+        } else if self.genesis_hash != params.genesis_hash {
+            return Err(WellFormedError::GenesisHashMismatch);
+        } else if self.note.len() > MAX_TX_NOTE_BYTES {
+            return Err(WellFormedError::NoteTooLong(self.note.len()));
+        }
+        Ok(())
+    }
+
+    fn state_proof_well_formed(&self) -> Result<(), WellFormedError> {
+        if self.sender != StateProofFields::sender() {
+            Err(WellFormedError::InvalidStateProofSender)
+        } else if !is_default(&self.fee)
+            || !self.note.is_empty()
+            || !is_default(&self.group)
+            || !is_default(&self.lease)
+            || !self.rekey_to.is_zero()
+        {
+            Err(WellFormedError::StateProofHeaderNotEmpty)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl HeartbeatFields {
+    fn well_formed(
+        &self,
+        header: &Header,
+        params: &SuggestedParams,
+    ) -> Result<(), WellFormedError> {
+        let cheap = header.fee < params.min_fee && is_default(&header.group);
+        if cheap
+            && (!header.note.is_empty() || !is_default(&header.lease) || !header.rekey_to.is_zero())
+        {
+            Err(WellFormedError::CheapHeartbeatNotEmpty)
+        } else if is_default(&self.proof)
+            || is_default(&self.seed)
+            || is_default(&self.vote_id)
+            || self.key_dilution == 0
+        {
+            Err(WellFormedError::IncompleteHeartbeat)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl PaymentFields {
+    fn well_formed(&self, sender: &Address) -> Result<(), WellFormedError> {
+        match self.close_remainder_to {
+            None if self.receiver.is_zero() => Err(WellFormedError::ZeroReceiver),
+            Some(close_to) if close_to == *sender => Err(WellFormedError::CloseToSender),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl KeyregFields {
+    fn well_formed(&self) -> Result<(), WellFormedError> {
+        let has_vote_pk = !is_default(&self.vote_pk);
+        let has_selection_pk = !is_default(&self.selection_pk);
+        if self.nonparticipation && (has_vote_pk || has_selection_pk) {
+            return Err(WellFormedError::NonparticipationWithKeys);
+        } else if has_vote_pk != has_selection_pk {
+            return Err(WellFormedError::MissingParticipationKeys);
+        } else if self.vote_first > self.vote_last {
+            return Err(WellFormedError::InvalidVoteRange {
+                first: self.vote_first,
+                last: self.vote_last,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl AssetConfigFields {
+    fn well_formed(&self) -> Result<(), WellFormedError> {
+        // Zero params with a zero asset ID would create an asset without any units,
+        // which the network accepts, so only the bounds of the params are checked.
+        self.asset_params.validate()?;
+        Ok(())
+    }
+}
+
+impl AssetTransferFields {
+    fn well_formed(&self) -> Result<(), WellFormedError> {
+        if self.transfer_asset == 0 {
+            return Err(WellFormedError::ZeroAssetId);
+        }
+        Ok(())
+    }
+}
+
+impl AssetFreezeFields {
+    fn well_formed(&self) -> Result<(), WellFormedError> {
+        if self.freeze_asset == 0 {
+            return Err(WellFormedError::ZeroAssetId);
+        }
+        Ok(())
+    }
+}
+
+impl AppCallFields {
+    fn well_formed(&self) -> Result<(), WellFormedError> {
+        let has_programs =
+            !self.approval_program.is_empty() || !self.clear_state_program.is_empty();
+        let sets_programs =
+            self.application_id == 0 || self.on_completion == OnCompletion::UpdateApplicationOC;
+        if sets_programs
+            && (self.approval_program.is_empty() || self.clear_state_program.is_empty())
+        {
+            return Err(WellFormedError::MissingProgram);
+        } else if !sets_programs && has_programs {
+            return Err(WellFormedError::UnexpectedProgram);
+        } else if self.application_args.len() > ENCODED_MAX_APPLICATION_ARGS {
+            return Err(WellFormedError::TooManyAppArgs(self.application_args.len()));
+        } else if self.accounts.len() > ENCODED_MAX_ACCOUNTS {
+            return Err(WellFormedError::TooManyAccounts(self.accounts.len()));
+        } else if self.foreign_apps.len() > ENCODED_MAX_FOREIGN_APPS {
+            return Err(WellFormedError::TooManyForeignApps(self.foreign_apps.len()));
+        } else if self.foreign_assets.len() > ENCODED_MAX_FOREIGN_ASSETS {
+            return Err(WellFormedError::TooManyForeignAssets(
+                self.foreign_assets.len(),
+            ));
+        } else if self.boxes.len() > ENCODED_MAX_BOXES {
+            return Err(WellFormedError::TooManyBoxes(self.boxes.len()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::account::Account;
+
+    fn params() -> SuggestedParams {
+        SuggestedParams {
+            genesis_id: "testnet-v1.0".to_owned(),
+            genesis_hash: [1; 32],
+            first_round_valid: Round(1000),
+            last_round_valid: Round(2000),
+            min_fee: MicroAlgos(1000),
+            ..Default::default()
+        }
+    }
+
+    fn with_fields(fields: TxFields) -> Transaction {
+        let mut tx = payment();
+        tx.fields = fields;
+        tx
+    }
+
+    fn payment() -> Transaction {
+        Transaction::payment(
+            Address([1; 32]),
+            Address([2; 32]),
+            MicroAlgos(5),
+            None,
+            &params(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn header() {
+        let params = params();
+        assert_eq!(payment().well_formed(&params), Ok(()));
+
+        let mut tx = payment();
+        tx.header.sender = Address::default();
+        assert_eq!(tx.well_formed(&params), Err(WellFormedError::ZeroSender));
+
+        let mut tx = payment();
+        tx.header.first_valid = Round(2001);
+        assert_eq!(
+            tx.well_formed(&params),
+            Err(WellFormedError::InvalidValidityRange {
+                first: Round(2001),
+                last: Round(2000)
+            })
+        );
+
+        let mut tx = payment();
+        tx.header.last_valid = Round(2001);
+        assert_eq!(
+            tx.well_formed(&params),
+            Err(WellFormedError::ValidityRangeTooLong {
+                first: Round(1000),
+                last: Round(2001)
+            })
+        );
+
+        let mut tx = payment();
+        tx.header.fee = MicroAlgos(999);
+        assert_eq!(
+            tx.well_formed(&params),
+            Err(WellFormedError::FeeTooLow {
+                fee: MicroAlgos(999),
+                min_fee: MicroAlgos(1000)
+            })
+        );
+
+        let mut tx = payment();
+        tx.header.genesis_id = "mainnet-v1.0".to_owned();
+        assert_eq!(
+            tx.well_formed(&params),
+            Err(WellFormedError::GenesisIdMismatch)
+        );
+        tx.header.genesis_id.clear();
+        assert_eq!(tx.well_formed(&params), Ok(()));
+
+        let mut tx = payment();
+        tx.header.genesis_hash = [2; 32];
+        assert_eq!(
+            tx.well_formed(&params),
+            Err(WellFormedError::GenesisHashMismatch)
+        );
+
+        let mut tx = payment();
+        tx.header.note = vec![0; MAX_TX_NOTE_BYTES];
+        assert_eq!(tx.well_formed(&params), Ok(()));
+        tx.header.note.push(0);
+        assert_eq!(
+            tx.well_formed(&params),
+            Err(WellFormedError::NoteTooLong(MAX_TX_NOTE_BYTES + 1))
+        );
+    }
+
+    #[test]
+    fn pooled_group_fees() {
+        let params = params();
+        let mut txs = vec![payment(), payment(), payment()];
+        txs[0].header.fee = MicroAlgos(3000);
+        txs[1].header.fee = MicroAlgos(0);
+        txs[2].header.fee = MicroAlgos(0);
+        assign_group_ids(&mut txs).unwrap();
+        assert_eq!(txs[1].well_formed(&params), Ok(()));
+        assert_eq!(group_well_formed(&txs, &params), Ok(()));
+
+        txs[0].header.fee = MicroAlgos(2999);
+        assert_eq!(
+            group_well_formed(&txs, &params),
+            Err(WellFormedError::GroupFeeTooLow {
+                fee: MicroAlgos(2999),
+                min_fee: MicroAlgos(3000)
+            })
+        );
+
+        txs[0].header.fee = MicroAlgos(u64::MAX);
+        txs[1].header.fee = MicroAlgos(u64::MAX);
+        assert_eq!(group_well_formed(&txs, &params), Ok(()));
+
+        // without a group ID, each transaction pays its own fee
+        let mut tx = payment();
+        tx.header.fee = MicroAlgos(0);
+        assert_eq!(
+            group_well_formed(&[tx], &params),
+            Err(WellFormedError::FeeTooLow {
+                fee: MicroAlgos(0),
+                min_fee: MicroAlgos(1000)
+            })
+        );
+    }
+
+    #[test]
+    fn payment_fields() {
+        let sender = Address([1; 32]);
+        let payment = |receiver, close_remainder_to| {
+            with_fields(TxFields::Payment(PaymentFields {
+                receiver,
+                amount: MicroAlgos(0),
+                close_remainder_to,
+            }))
+            .well_formed(&params())
+        };
+        assert_eq!(
+            payment(Address::default(), None),
+            Err(WellFormedError::ZeroReceiver)
+        );
+        assert_eq!(payment(Address::default(), Some(Address([2; 32]))), Ok(()));
+        assert_eq!(
+            payment(Address([2; 32]), Some(sender)),
+            Err(WellFormedError::CloseToSender)
+        );
+    }
+
+    #[test]
+    fn keyreg_fields() {
+        let key = ed25519_dalek::PublicKey::from_bytes(&Address([1; 32]).0).unwrap();
+        let keyreg = |fields| with_fields(TxFields::Keyreg(fields)).well_formed(&params());
+
+        assert_eq!(keyreg(KeyregFields::default()), Ok(()));
+        let online = KeyregFields {
+            vote_pk: VotePK(key),
+            selection_pk: VrfPK([1; 32]),
+            vote_first: Round(1000),
+            vote_last: Round(2000),
+            ..Default::default()
+        };
+        assert_eq!(keyreg(online.clone()), Ok(()));
+
+        assert_eq!(
+            keyreg(KeyregFields {
+                selection_pk: Default::default(),
+                ..online.clone()
+            }),
+            Err(WellFormedError::MissingParticipationKeys)
+        );
+        assert_eq!(
+            keyreg(KeyregFields {
+                vote_first: Round(2001),
+                ..online.clone()
+            }),
+            Err(WellFormedError::InvalidVoteRange {
+                first: Round(2001),
+                last: Round(2000)
+            })
+        );
+        assert_eq!(
+            keyreg(KeyregFields {
+                nonparticipation: true,
+                ..online
+            }),
+            Err(WellFormedError::NonparticipationWithKeys)
+        );
+    }
+
+    #[test]
+    fn asset_fields() {
+        let params = params();
+        let config = with_fields(TxFields::AssetConfig(AssetConfigFields {
+            config_asset: 0,
+            asset_params: AssetParams {
+                decimals: 20,
+                ..Default::default()
+            },
+        }));
+        assert_eq!(
+            config.well_formed(&params),
+            Err(WellFormedError::InvalidAssetParams(
+                AssetParamsError::TooManyDecimals(20)
+            ))
+        );
+
+        let transfer = with_fields(TxFields::AssetTransfer(AssetTransferFields::default()));
+        assert_eq!(
+            transfer.well_formed(&params),
+            Err(WellFormedError::ZeroAssetId)
+        );
+        let opt_in = Transaction::asset_opt_in(Address([1; 32]), 5, &params).unwrap();
+        assert_eq!(opt_in.well_formed(&params), Ok(()));
+
+        let mut freeze =
+            Transaction::asset_freeze(Address([1; 32]), Address([2; 32]), 5, true, &params)
+                .unwrap();
+        assert_eq!(freeze.well_formed(&params), Ok(()));
+        if let TxFields::AssetFreeze(fields) = &mut freeze.fields {
+            fields.freeze_asset = 0;
+        }
+        assert_eq!(
+            freeze.well_formed(&params),
+            Err(WellFormedError::ZeroAssetId)
+        );
+    }
+
+    #[test]
+    fn state_proof_fields() {
+        let mut tx = with_fields(TxFields::StateProof(StateProofFields::default()));
+        tx.header.fee = MicroAlgos(0);
+        assert_eq!(
+            tx.well_formed(&params()),
+            Err(WellFormedError::InvalidStateProofSender)
+        );
+        tx.header.sender = StateProofFields::sender();
+        assert_eq!(tx.well_formed(&params()), Ok(()));
+        tx.header.lease = [1; 32];
+        assert_eq!(
+            tx.well_formed(&params()),
+            Err(WellFormedError::StateProofHeaderNotEmpty)
+        );
+        tx.header.lease = [0; 32];
+        tx.header.fee = MicroAlgos(1000);
+        assert_eq!(
+            tx.well_formed(&params()),
+            Err(WellFormedError::StateProofHeaderNotEmpty)
+        );
+    }
+
+    #[test]
+    fn heartbeat_fields() {
+        let fields = HeartbeatFields {
+            address: Address([2; 32]),
+            proof: HeartbeatProof {
+                pk: Account::from_seed(&[1; 32]).public_key(),
+                ..Default::default()
+            },
+            seed: [3; 32],
+            vote_id: VotePK(Account::from_seed(&[4; 32]).public_key()),
+            key_dilution: 100,
+        };
+        let mut tx = with_fields(TxFields::Heartbeat(Box::new(fields.clone())));
+        tx.header.fee = MicroAlgos(0);
+        assert_eq!(tx.well_formed(&params()), Ok(()));
+        tx.header.note = b"alive".to_vec();
+        assert_eq!(
+            tx.well_formed(&params()),
+            Err(WellFormedError::CheapHeartbeatNotEmpty)
+        );
+        tx.header.fee = params().min_fee;
+        assert_eq!(tx.well_formed(&params()), Ok(()));
+
+        let incomplete = with_fields(TxFields::Heartbeat(Box::new(HeartbeatFields {
+            key_dilution: 0,
+            ..fields
+        })));
+        assert_eq!(
+            incomplete.well_formed(&params()),
+            Err(WellFormedError::IncompleteHeartbeat)
+        );
+    }
+
+    #[test]
+    fn app_call_fields() {
+        let app_call = |fields| with_fields(TxFields::AppCall(fields)).well_formed(&params());
+        let create = AppCallFields {
+            approval_program: vec![0x05, 0x81, 0x01],
+            clear_state_program: vec![0x05, 0x81, 0x01],
+            ..Default::default()
+        };
+        assert_eq!(app_call(create.clone()), Ok(()));
+        assert_eq!(
+            app_call(AppCallFields {
+                clear_state_program: Vec::new(),
+                ..create.clone()
+            }),
+            Err(WellFormedError::MissingProgram)
+        );
+        assert_eq!(
+            app_call(AppCallFields {
+                application_id: 5,
+                ..create.clone()
+            }),
+            Err(WellFormedError::UnexpectedProgram)
+        );
+        assert_eq!(
+            app_call(AppCallFields {
+                application_id: 5,
+                on_completion: OnCompletion::UpdateApplicationOC,
+                ..create
+            }),
+            Ok(())
+        );
+
+        let call = AppCallFields {
+            application_id: 5,
+            ..Default::default()
+        };
+        assert_eq!(app_call(call.clone()), Ok(()));
+        assert_eq!(
+            app_call(AppCallFields {
+                application_args: vec![Vec::new(); ENCODED_MAX_APPLICATION_ARGS + 1],
+                ..call.clone()
+            }),
+            Err(WellFormedError::TooManyAppArgs(
+                ENCODED_MAX_APPLICATION_ARGS + 1
+            ))
+        );
+        assert_eq!(
+            app_call(AppCallFields {
+                foreign_assets: vec![1; ENCODED_MAX_FOREIGN_ASSETS + 1],
+                ..call
+            }),
+            Err(WellFormedError::TooManyForeignAssets(
+                ENCODED_MAX_FOREIGN_ASSETS + 1
+            ))
+        );
+    }
+}
@@ -1,10 +1,31 @@
 // Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
 // Distributed under terms of the MIT license.
 
+use alloc::vec::Vec;
+use core::fmt;
+
+use data_encoding::BASE64;
+
 pub fn is_default<T: Default + PartialEq>(t: &T) -> bool {
     t == &T::default()
 }
 
-fn is_zero(s: &ed25519::Signature) -> bool {
-    s.to_bytes() == [0; ed25519_dalek::SIGNATURE_LENGTH]
+/// Formats bytes as base64 in `Debug` output, rather than as a list of numbers.
+pub(crate) struct Base64Debug<'a>(pub &'a [u8]);
+
+impl fmt::Debug for Base64Debug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&BASE64.encode(self.0))
+    }
+}
+
+/// Formats a list of byte strings as base64 in `Debug` output.
+pub(crate) struct Base64ListDebug<'a>(pub &'a [Vec<u8>]);
+
+impl fmt::Debug for Base64ListDebug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|bytes| Base64Debug(bytes)))
+            .finish()
+    }
 }
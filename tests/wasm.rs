@@ -0,0 +1,57 @@
+// Copyright (C) 2021 Quentin M. Kniep <hello@quentinkniep.com>
+// Distributed under terms of the MIT license.
+
+//! Checks that building, signing, and encoding transactions works in WebAssembly.
+//!
+//! Run these in a headless browser or in Node.js, without any CI setup:
+//!
+//! ```sh
+//! rustup target add wasm32-unknown-unknown
+//! cargo install wasm-bindgen-cli
+//! CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner \
+//!     cargo test --target wasm32-unknown-unknown --no-default-features --features wasm --test wasm
+//! ```
+
+#![cfg(target_arch = "wasm32")]
+
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use rs_algorand_sdk::account::Account;
+use rs_algorand_sdk::types::{Address, MicroAlgos, Round, SignedTx, SuggestedParams, Transaction};
+
+fn payment(sender: &Account) -> Transaction {
+    let params = SuggestedParams {
+        first_round_valid: Round(1),
+        last_round_valid: Round(1001),
+        min_fee: MicroAlgos(1000),
+        ..Default::default()
+    };
+    Transaction::payment(
+        sender.address(),
+        Address([2; 32]),
+        MicroAlgos(5),
+        None,
+        &params,
+    )
+    .unwrap()
+}
+
+#[wasm_bindgen_test]
+fn sign_and_encode() {
+    let account = Account::from_seed(&[1; 32]);
+    let stx = account.sign_transaction(&payment(&account));
+    stx.verify().unwrap();
+
+    let decoded = SignedTx::from_msgpack_strict(&stx.encode()).unwrap();
+    assert!(decoded == stx);
+    assert_eq!(decoded.tx.id(), stx.tx.id());
+}
+
+#[wasm_bindgen_test]
+fn generate() {
+    let account = Account::generate();
+    account
+        .sign_transaction(&payment(&account))
+        .verify()
+        .unwrap();
+}